@@ -0,0 +1,211 @@
+//! Authenticated encryption interface
+//!
+//! Like hash functions, symmetric ciphers are supplied by the caller
+//! through the `Aead` trait. It is used by the public-key encryption
+//! constructions such as `box_`. `HmacStream` is a bundled implementation
+//! built over a `Digest` for callers without a cipher at hand.
+use std::result;
+
+use digest::Digest;
+use error::{Error, InvalidLength};
+use kdf;
+use sbuf::{DefaultAllocator, SBuf};
+use utils;
+
+
+/// Authenticated encryption scheme supplied by the caller.
+pub trait Aead {
+    /// Return the size of the keys in bytes.
+    fn key_bytes(&self) -> uint;
+
+    /// Return the size of the nonces in bytes.
+    fn nonce_bytes(&self) -> uint;
+
+    /// Return the size of the authentication tags in bytes.
+    fn tag_bytes(&self) -> uint;
+
+    /// Encrypt and authenticate `msg` with `key` and `nonce`, write the
+    /// ciphertext followed by its tag into `out` which must be
+    /// `msg.len() + tag_bytes()` long. A `nonce` must never be reused with
+    /// the same `key`. Return `Err(InvalidLength)` if `nonce` or `out`
+    /// doesn't have the expected length.
+    fn seal(&mut self, key: &[u8], nonce: &[u8], msg: &[u8],
+            out: &mut [u8]) -> result::Result<(), Error>;
+
+    /// Authenticate and decrypt `ct` (ciphertext followed by its tag)
+    /// with `key` and `nonce`, write the plaintext into `out` which must be
+    /// `ct.len() - tag_bytes()` long. Return `false` if authentication
+    /// failed or if `nonce`, `ct` or `out` doesn't have the expected
+    /// length, the content of `out` is then unspecified.
+    fn open(&mut self, key: &[u8], nonce: &[u8], ct: &[u8],
+            out: &mut [u8]) -> bool;
+}
+
+
+static ENC_LABEL: &'static [u8] = b"curve41417 hmac-stream enc";
+static MAC_LABEL: &'static [u8] = b"curve41417 hmac-stream mac";
+
+/// Encrypt-then-MAC scheme built over a `Digest`
+///
+/// Two subkeys are expanded from the key with `kdf::expand()`. The
+/// keystream block `i` (starting from `0`) is `HMAC(enc, nonce || i)` with
+/// `i` encoded as 8 little-endian bytes, and the tag is
+/// `HMAC(mac, nonce || ciphertext)`. Keys and tags are
+/// `digest.output_bytes()` long, nonces are 24 bytes long and may be
+/// generated randomly. It is slow compared to dedicated ciphers.
+pub struct HmacStream<D> {
+    digest: D
+}
+
+impl<D: Digest> HmacStream<D> {
+    /// Return a new instance using `digest`.
+    pub fn new(digest: D) -> HmacStream<D> {
+        HmacStream {
+            digest: digest
+        }
+    }
+
+    fn subkey(&mut self, key: &[u8], label: &[u8])
+              -> SBuf<DefaultAllocator, u8> {
+        assert!(key.len() == self.key_bytes());
+        let mut k: SBuf<DefaultAllocator, u8> =
+            SBuf::new_zero(self.digest.output_bytes());
+        kdf::expand(&mut self.digest, key, label, k.as_mut_slice());
+        k
+    }
+
+    // XOR data with the keystream in place.
+    fn xor_stream(&mut self, key: &[u8], nonce: &[u8], data: &mut [u8]) {
+        let enc = self.subkey(key, ENC_LABEL);
+        let hs = self.digest.output_bytes();
+        let mut block: SBuf<DefaultAllocator, u8> = SBuf::new_zero(hs);
+        let mut ctr = 0u64;
+        for chunk in data.mut_chunks(hs) {
+            let mut c = [0u8, ..8];
+            for i in range(0u, 8) {
+                c[i] = (ctr >> (8 * i)) as u8;
+            }
+            kdf::hmac(&mut self.digest, enc.as_slice(), [nonce, c.as_slice()],
+                      block.as_mut_slice());
+            for (d, s) in chunk.mut_iter().zip(block.iter()) {
+                *d ^= *s;
+            }
+            ctr += 1;
+        }
+    }
+
+    fn tag(&mut self, key: &[u8], nonce: &[u8], ct: &[u8], out: &mut [u8]) {
+        let mac = self.subkey(key, MAC_LABEL);
+        kdf::hmac(&mut self.digest, mac.as_slice(), [nonce, ct], out);
+    }
+}
+
+impl<D: Digest> Aead for HmacStream<D> {
+    fn key_bytes(&self) -> uint {
+        self.digest.output_bytes()
+    }
+
+    fn nonce_bytes(&self) -> uint {
+        24
+    }
+
+    fn tag_bytes(&self) -> uint {
+        self.digest.output_bytes()
+    }
+
+    fn seal(&mut self, key: &[u8], nonce: &[u8], msg: &[u8],
+            out: &mut [u8]) -> result::Result<(), Error> {
+        if nonce.len() != self.nonce_bytes() {
+            return Err(InvalidLength(self.nonce_bytes(), nonce.len()));
+        }
+        if out.len() != msg.len() + self.tag_bytes() {
+            return Err(InvalidLength(msg.len() + self.tag_bytes(),
+                                     out.len()));
+        }
+
+        let (ct, tag) = out.mut_split_at(msg.len());
+        utils::copy_slice_memory(ct, msg, msg.len());
+        self.xor_stream(key, nonce, ct);
+        self.tag(key, nonce, ct, tag);
+        Ok(())
+    }
+
+    fn open(&mut self, key: &[u8], nonce: &[u8], ct: &[u8],
+            out: &mut [u8]) -> bool {
+        if nonce.len() != self.nonce_bytes() || ct.len() < self.tag_bytes() {
+            return false;
+        }
+        let len = ct.len() - self.tag_bytes();
+        if out.len() != len {
+            return false;
+        }
+
+        let mut tag: SBuf<DefaultAllocator, u8> =
+            SBuf::new_zero(self.tag_bytes());
+        self.tag(key, nonce, ct.slice_to(len), tag.as_mut_slice());
+        if !utils::bytes_eq(tag.as_slice(), ct.slice_from(len)) {
+            return false;
+        }
+
+        utils::copy_slice_memory(out, ct, len);
+        self.xor_stream(key, nonce, out);
+        true
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use aead::{Aead, HmacStream};
+    use digest::sha256::Sha256;
+    use error::InvalidLength;
+
+
+    #[test]
+    fn test_hmac_stream() {
+        let mut c = HmacStream::new(Sha256::new());
+        let key = [7u8, ..32];
+        let nonce = [9u8, ..24];
+
+        for &len in [0u, 1, 31, 32, 33, 100].iter() {
+            let msg = Vec::from_fn(len, |i| i as u8);
+            let mut ct = Vec::from_elem(len + 32, 0u8);
+            c.seal(key, nonce, msg.as_slice(), ct.as_mut_slice()).unwrap();
+            if len > 0 {
+                assert!(ct.slice_to(len) != msg.as_slice());
+            }
+
+            let mut pt = Vec::from_elem(len, 0u8);
+            assert!(c.open(key, nonce, ct.as_slice(), pt.as_mut_slice()));
+            assert!(pt == msg);
+
+            *ct.get_mut(len) ^= 1;
+            assert!(!c.open(key, nonce, ct.as_slice(), pt.as_mut_slice()));
+            *ct.get_mut(len) ^= 1;
+            let other = [8u8, ..24];
+            assert!(!c.open(key, other, ct.as_slice(), pt.as_mut_slice()));
+        }
+    }
+
+    #[test]
+    fn test_hmac_stream_lengths() {
+        let mut c = HmacStream::new(Sha256::new());
+        let key = [7u8, ..32];
+        let nonce = [9u8, ..24];
+        let msg = [1u8, ..10];
+        let mut ct = [0u8, ..42];
+
+        assert!(c.seal(key, nonce.slice_to(23), msg, ct) ==
+                Err(InvalidLength(24, 23)));
+        assert!(c.seal(key, nonce, msg, ct.mut_slice_to(41)) ==
+                Err(InvalidLength(42, 41)));
+        c.seal(key, nonce, msg, ct).unwrap();
+
+        let mut pt = [0u8, ..10];
+        assert!(!c.open(key, nonce.slice_to(23), ct, pt));
+        assert!(!c.open(key, nonce, ct.slice_to(31), []));
+        assert!(!c.open(key, nonce, ct, pt.mut_slice_to(9)));
+        assert!(c.open(key, nonce, ct, pt));
+        assert!(pt == msg);
+    }
+}
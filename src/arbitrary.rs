@@ -0,0 +1,197 @@
+//! Structured random inputs
+//!
+//! Random generators biased toward edge cases, for fuzz targets and
+//! property tests. Byte values are frequently chosen next to the
+//! boundaries where reductions happen (`0`, `L`, multiples of `2^411`,
+//! `P`, `2^416 - 1`) instead of being uniformly distributed. Only available
+//! with the `arbitrary` feature.
+use std::rand::Rng;
+
+use bytes::{B416, B512, B672, B768, B832, B912, Bytes, EdPoint, MontPoint};
+use bytes::Scalar;
+use ed::GroupElem;
+use fe::FieldElem;
+use sc::ScalarElem;
+
+
+/// Types which can be randomly generated with a bias toward edge cases.
+pub trait Arbitrary {
+    /// Return a new instance generated from `rng`.
+    fn arbitrary<R: Rng>(rng: &mut R) -> Self;
+}
+
+// Return n + delta mod 2^(8 * n.len()).
+fn offset<T: Bytes>(n: &T, delta: int) -> T {
+    let mut r = n.clone();
+    let mut carry = delta;
+    for i in range(0u, r.len()) {
+        let v = *r.get(i) as int + carry;
+        *r.get_mut(i) = (v & 0xff) as u8;
+        carry = v >> 8;
+    }
+    r
+}
+
+// Return a value next to a reduction boundary.
+fn edge_b416<R: Rng>(rng: &mut R) -> B416 {
+    let delta = rng.gen_range(-1i, 2);
+    let zero: B416 = Bytes::new_zero();
+
+    match rng.gen_range(0u, 5) {
+        // 0, 1 and 2
+        0 => offset(&zero, delta + 1),
+        // L - 1, L and L + 1
+        1 => offset(&ScalarElem::order(), delta),
+        // k.2^411 for 0 < k < 32
+        2 => {
+            let mut n = zero.clone();
+            *n.get_mut(51) = rng.gen_range(1u8, 32) << 3;
+            offset(&n, delta)
+        },
+        // P - 1, P and P + 1
+        3 => {
+            let pm1 = (FieldElem::zero() - FieldElem::one()).pack();
+            offset(&pm1, delta + 1)
+        },
+        // 2^416 - 1
+        _ => offset(&zero, -1)
+    }
+}
+
+impl Arbitrary for B416 {
+    fn arbitrary<R: Rng>(rng: &mut R) -> B416 {
+        match rng.gen() {
+            true => edge_b416(rng),
+            false => rng.gen()
+        }
+    }
+}
+
+macro_rules! arbitrary_wide_impl(($name:ident) => (
+impl Arbitrary for $name {
+    /// Zero-extended edge value, or uniformly random value.
+    fn arbitrary<R: Rng>(rng: &mut R) -> $name {
+        match rng.gen() {
+            true => {
+                let e = edge_b416(rng);
+                let mut n: $name = Bytes::new_zero();
+                for (d, s) in n.as_mut_bytes().mut_iter().zip(e.as_bytes()
+                                                              .iter()) {
+                    *d = *s;
+                }
+                n
+            },
+            false => rng.gen()
+        }
+    }
+}
+))
+
+arbitrary_wide_impl!(B512)
+arbitrary_wide_impl!(B672)
+arbitrary_wide_impl!(B768)
+arbitrary_wide_impl!(B832)
+arbitrary_wide_impl!(B912)
+
+impl Arbitrary for Scalar {
+    /// Arbitrary bytes, they may be non-canonical.
+    fn arbitrary<R: Rng>(rng: &mut R) -> Scalar {
+        Scalar(Arbitrary::arbitrary(rng))
+    }
+}
+
+impl Arbitrary for ScalarElem {
+    /// Arbitrary value reduced `mod L`, values such as `L - 1`, `L` or
+    /// `L + 1` go through the reduction code.
+    fn arbitrary<R: Rng>(rng: &mut R) -> ScalarElem {
+        let n: B512 = Arbitrary::arbitrary(rng);
+        ScalarElem::unpack(&n).unwrap()
+    }
+}
+
+impl Arbitrary for GroupElem {
+    /// Neutral element, base point, its opposite or a random multiple of
+    /// the base point.
+    fn arbitrary<R: Rng>(rng: &mut R) -> GroupElem {
+        match rng.gen_range(0u, 4) {
+            0 => GroupElem::neutral(),
+            1 => GroupElem::base(),
+            2 => -GroupElem::base(),
+            _ => {
+                let n: ScalarElem = Arbitrary::arbitrary(rng);
+                GroupElem::base() * n
+            }
+        }
+    }
+}
+
+impl Arbitrary for EdPoint {
+    /// Encoding of an arbitrary point, or arbitrary bytes which may not
+    /// decode to a point.
+    fn arbitrary<R: Rng>(rng: &mut R) -> EdPoint {
+        match rng.gen() {
+            true => {
+                let p: GroupElem = Arbitrary::arbitrary(rng);
+                p.pack()
+            },
+            false => EdPoint(Arbitrary::arbitrary(rng))
+        }
+    }
+}
+
+impl Arbitrary for MontPoint {
+    /// x-coordinate of an arbitrary point, or arbitrary bytes.
+    fn arbitrary<R: Rng>(rng: &mut R) -> MontPoint {
+        match rng.gen() {
+            true => {
+                let p: GroupElem = Arbitrary::arbitrary(rng);
+                p.to_mont()
+            },
+            false => MontPoint(Arbitrary::arbitrary(rng))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::Arbitrary;
+    use bytes::{B416, B832, Bytes, EdPoint};
+    use ed::GroupElem;
+    use sc::ScalarElem;
+    use utils;
+
+
+    #[test]
+    fn test_offset() {
+        let zero: B416 = Bytes::new_zero();
+        let m1 = super::offset(&zero, -1);
+        assert!(m1.as_bytes().iter().all(|b| *b == 0xff));
+        assert!(super::offset(&m1, 1) == zero);
+    }
+
+    #[test]
+    fn test_arbitrary() {
+        let mut rng = utils::urandom_rng();
+
+        for _ in range(0u, 64) {
+            let s: ScalarElem = Arbitrary::arbitrary(&mut rng);
+            assert!(ScalarElem::is_canonical(s.pack().get_ref())
+                    .unwrap_u8() == 1);
+
+            let w: B832 = Arbitrary::arbitrary(&mut rng);
+            let t = ScalarElem::unpack(&w).unwrap();
+            assert!(ScalarElem::is_canonical(t.pack().get_ref())
+                    .unwrap_u8() == 1);
+
+            let e: EdPoint = Arbitrary::arbitrary(&mut rng);
+            match GroupElem::unpack(&e) {
+                Some(p) => {
+                    let q = GroupElem::unpack(&p.pack()).unwrap();
+                    assert!(p == q);
+                },
+                None => ()
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Memory-access audit hooks
+//!
+//! Scalar multiplications report the memory accesses they make to secret
+//! dependent data (bits of scalars, conditional swaps of coordinates,
+//! entries read by table scans such as `choice::lookup()`) to an
+//! `Observer`, so that their access pattern may be recorded and checked
+//! for independence from the secrets, e.g. by dudect-like statistical
+//! tests or by comparing traces. Only compiled in with the `audit`
+//! feature, see `set_observer()`.
+
+
+/// Receiver of memory-access reports.
+pub trait Observer {
+    /// Report an access to `len` bytes at address `addr` made at `site`
+    /// (e.g. `"mont::ladder"`). For constant-time code the sequence of
+    /// reported sites and lengths doesn't depend on secret values.
+    fn access(&mut self, site: &'static str, addr: uint, len: uint);
+}
+
+#[cfg(feature = "audit")]
+mod imphooks {
+    use super::Observer;
+
+
+    task_hook!(OBSERVER, Observer, set_observer, take_observer)
+
+    #[inline]
+    pub fn access(site: &'static str, addr: uint, len: uint) {
+        match OBSERVER.get() {
+            Some(o) => o.borrow_mut().access(site, addr, len),
+            None => ()
+        }
+    }
+}
+
+#[cfg(not(feature = "audit"))]
+mod imphooks {
+    use super::Observer;
+
+
+    no_task_hook!(Observer, set_observer, take_observer)
+
+    #[inline(always)]
+    pub fn access(_: &'static str, _: uint, _: uint) {
+    }
+}
+
+/// Install `observer` for the current task, replacing the previous one.
+/// Return `false` if the crate was not built with the `audit` feature, the
+/// observer is then never called.
+pub fn set_observer(observer: Box<Observer>) -> bool {
+    self::imphooks::set_observer(observer)
+}
+
+/// Remove and return the observer of the current task.
+pub fn take_observer() -> Option<Box<Observer>> {
+    self::imphooks::take_observer()
+}
+
+// Report an access to the elements of s made at site.
+#[doc(hidden)]
+#[inline(always)]
+pub fn access<T>(site: &'static str, s: &[T]) {
+    self::imphooks::access(site, s.as_ptr() as uint,
+                           s.len() * ::std::mem::size_of::<T>());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::comm;
+
+    use audit;
+    use audit::Observer;
+    use bytes::{B416, Bytes, Scalar};
+    use ed::{GroupElem, PointTable};
+    use mont;
+
+
+    // Record sites and lengths. Addresses of heap buffers vary between
+    // runs, only the addresses of table entries are kept.
+    struct Trace {
+        tx: comm::Sender<(&'static str, uint, uint)>
+    }
+
+    impl Observer for Trace {
+        fn access(&mut self, site: &'static str, addr: uint, len: uint) {
+            let addr = if site == "choice::lookup" { addr } else { 0 };
+            self.tx.send((site, addr, len));
+        }
+    }
+
+    fn trace(f: ||) -> Vec<(&'static str, uint, uint)> {
+        let (tx, rx) = comm::channel();
+        audit::set_observer(box Trace { tx: tx });
+        f();
+        drop(audit::take_observer());
+        rx.iter().collect()
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut one: B416 = Bytes::new_zero();
+        *one.get_mut(0) = 1;
+        let (n1, n2) = (Scalar(one), Scalar(Bytes::new_rand()));
+        let t1 = trace(|| { mont::scalar_mult_base(&n1); });
+        let t2 = trace(|| { mont::scalar_mult_base(&n2); });
+
+        if cfg!(feature = "audit") {
+            assert!(t1.len() > 0);
+        } else {
+            assert!(t1.len() == 0);
+        }
+        assert!(t1 == t2);
+    }
+
+    #[test]
+    fn test_table_scan() {
+        let t = PointTable::with_window(&GroupElem::base(), 4).unwrap();
+        let (n1, n2) = (Scalar(Bytes::new_rand()), Scalar(Bytes::new_rand()));
+        assert!(n1 != n2);
+        let t1 = trace(|| { t.scalar_mult(&n1); });
+        let t2 = trace(|| { t.scalar_mult(&n2); });
+
+        // Every entry is read for each of the 104 windows.
+        let scans = t1.iter().filter(|&&(s, _, _)| s == "choice::lookup")
+            .count();
+        if cfg!(feature = "audit") {
+            assert!(scans == 16 * 104);
+        } else {
+            assert!(t1.len() == 0);
+        }
+        assert!(t1 == t2);
+    }
+}
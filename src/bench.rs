@@ -0,0 +1,139 @@
+//! Built-in performance probe
+//!
+//! Standardized workloads (signatures, verifications, Diffie-Hellman and
+//! a multi-scalar multiplication) timed in the caller's environment, for
+//! integrators tracking performance regressions programmatically. Inputs
+//! are derived from a fixed seed and generated before timing starts, the
+//! timings only cover the measured operations. Only available with the
+//! `bench` feature.
+use std::rand::Rng;
+use time;
+
+use bytes::{MontPoint, Scalar};
+use digest::sha256::Sha256;
+use ed::GroupElem;
+use kdf::KdfRng;
+use mont;
+use sign;
+use sign::Signature;
+
+
+static SEED: &'static [u8] = b"curve41417 bench";
+static MESSAGE: &'static [u8] = b"curve41417 bench message";
+
+
+/// Timing of a workload.
+#[deriving(Clone, Show)]
+pub struct Timing {
+    /// Name of the workload, e.g. `"sign"`.
+    pub name: &'static str,
+    /// Number of operations executed.
+    pub iterations: uint,
+    /// Total elapsed time in nanoseconds.
+    pub total_ns: u64
+}
+
+impl Timing {
+    /// Return the average time of one operation in nanoseconds.
+    pub fn per_iteration_ns(&self) -> u64 {
+        match self.iterations {
+            0 => 0,
+            n => self.total_ns / n as u64
+        }
+    }
+}
+
+fn rng(label: &[u8]) -> KdfRng<Sha256> {
+    KdfRng::new(Sha256::new(), SEED, label)
+}
+
+// Time f, which executes iterations operations.
+fn timed(name: &'static str, iterations: uint, f: ||) -> Timing {
+    let start = time::precise_time_ns();
+    f();
+    Timing {
+        name: name,
+        iterations: iterations,
+        total_ns: time::precise_time_ns() - start
+    }
+}
+
+/// Time `iterations` signatures of a short message with `sign::sign()`
+/// and Sha256.
+pub fn sign(iterations: uint) -> Timing {
+    let (pk, sk) = GroupElem::keypair_from_rng(&mut rng(b"sign"));
+    let mut h = Sha256::new();
+    timed("sign", iterations, || {
+        for _ in range(0u, iterations) {
+            sign::sign(&mut h, &sk, &pk, MESSAGE);
+        }
+    })
+}
+
+/// Time `iterations` verifications of valid signatures with
+/// `sign::verify()` and Sha256. Fails if a signature is rejected.
+pub fn verify(iterations: uint) -> Timing {
+    let (pk, sk) = GroupElem::keypair_from_rng(&mut rng(b"verify"));
+    let mut h = Sha256::new();
+    let sig: Signature = sign::sign(&mut h, &sk, &pk, MESSAGE);
+    let mut valid = true;
+    let t = timed("verify", iterations, || {
+        for _ in range(0u, iterations) {
+            valid &= sign::verify(&mut h, &pk, MESSAGE, &sig);
+        }
+    });
+    assert!(valid);
+    t
+}
+
+/// Time `iterations` Diffie-Hellman shared secrets with
+/// `mont::scalar_mult()`, each with a different peer public key.
+pub fn dh(iterations: uint) -> Timing {
+    let mut r = rng(b"dh");
+    let (_, sk) = mont::keypair_from_rng(&mut r);
+    let pks: Vec<MontPoint> = Vec::from_fn(iterations, |_| {
+        let (pk, _) = mont::keypair_from_rng(&mut r);
+        pk
+    });
+    timed("dh", iterations, || {
+        for pk in pks.iter() {
+            mont::scalar_mult(&sk, pk);
+        }
+    })
+}
+
+/// Time one multi-scalar multiplication of `points` points with
+/// `GroupElem::multiscalar_mult()`, `iterations` is set to `points`.
+pub fn msm(points: uint) -> Timing {
+    let mut r = rng(b"msm");
+    let ns: Vec<Scalar> = Vec::from_fn(points, |_| Scalar(r.gen()));
+    let ps: Vec<GroupElem> = Vec::from_fn(points, |_| r.gen());
+    timed("msm", points, || {
+        GroupElem::multiscalar_mult(ns.as_slice(), ps.as_slice());
+    })
+}
+
+/// Run the standard workloads: 1000 signatures, 1000 verifications, 1000
+/// Diffie-Hellman and one multi-scalar multiplication of 10000 points.
+/// This takes a while, call the individual workloads for quicker probes.
+pub fn run_all() -> Vec<Timing> {
+    vec![sign(1000), verify(1000), dh(1000), msm(10000)]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bench;
+
+
+    #[test]
+    fn test_workloads() {
+        let ts = vec![bench::sign(2), bench::verify(2), bench::dh(2),
+                      bench::msm(3)];
+        let names: Vec<&'static str> = ts.iter().map(|t| t.name).collect();
+        assert!(names == vec!["sign", "verify", "dh", "msm"]);
+        assert!(ts.get(3).iterations == 3);
+        assert!(ts.iter().all(|t| t.per_iteration_ns() <= t.total_ns));
+        assert!(bench::dh(0).per_iteration_ns() == 0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Arbitrary-precision integer conversions
+//!
+//! Conversions between scalars, field elements and `num::bigint::BigUint`
+//! for audit scripts and tooling, these are slow and not constant-time.
+//! Only available with the `bigint` feature, the core of this crate
+//! doesn't depend on `num`.
+use num::bigint::{BigUint, ToBigUint};
+use serialize::hex::{FromHex, ToHex};
+use std::num::ToStrRadix;
+use std::result;
+
+use bytes::{B416, Bytes, Scalar};
+use error::{Error, NonCanonical};
+use fe::FieldElem;
+use sc::ScalarElem;
+
+
+// Return the integer of the big-endian bytes b.
+fn from_be_bytes(b: &B416) -> BigUint {
+    BigUint::parse_bytes(b.to_hex().as_bytes(), 16).unwrap()
+}
+
+// Return the 52 bytes little-endian encoding of n, NonCanonical if it
+// doesn't fit.
+fn to_le_bytes(n: &BigUint) -> result::Result<B416, Error> {
+    let hex = n.to_str_radix(16);
+    if hex.len() > 104 {
+        return Err(NonCanonical);
+    }
+    let padded = format!("{}{}", String::from_char(104 - hex.len(), '0'),
+                         hex);
+    let be = padded.as_slice().from_hex().unwrap();
+    Ok(Scalar::from_bytes_be(be.as_slice()).unwrap().unwrap())
+}
+
+impl ToBigUint for ScalarElem {
+    /// Return the canonical value of this scalar, never `None`.
+    fn to_biguint(&self) -> Option<BigUint> {
+        Some(from_be_bytes(&self.to_bytes_be()))
+    }
+}
+
+impl ToBigUint for FieldElem {
+    /// Return the canonical value of this field element, never `None`.
+    fn to_biguint(&self) -> Option<BigUint> {
+        let be = Scalar(self.pack()).to_bytes_be();
+        Some(from_be_bytes(&be))
+    }
+}
+
+/// Return the scalar of value `n`, or `Err(NonCanonical)` if `n` is not
+/// in `[0, L-1]`.
+pub fn scalar_from_biguint(n: &BigUint) -> result::Result<ScalarElem, Error> {
+    let b = try!(to_le_bytes(n));
+    ScalarElem::decode(b.as_bytes())
+}
+
+/// Return the field element of value `n`, or `Err(NonCanonical)` if `n` is
+/// not in `[0, P-1]`.
+pub fn field_from_biguint(n: &BigUint) -> result::Result<FieldElem, Error> {
+    let b = try!(to_le_bytes(n));
+    FieldElem::from_bytes_strict(b.as_bytes())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use num::bigint::{BigUint, ToBigUint};
+    use std::num::{One, Zero};
+
+    use bigint;
+    use error::NonCanonical;
+    use fe::FieldElem;
+    use sc::ScalarElem;
+
+
+    #[test]
+    fn test_scalar() {
+        let a = ScalarElem::new_rand();
+        let n = a.to_biguint().unwrap();
+        assert!(bigint::scalar_from_biguint(&n).unwrap() == a);
+
+        let l = ScalarElem::unpack(&ScalarElem::order()).unwrap();
+        assert!(l.to_biguint().unwrap().is_zero());
+        let one: BigUint = One::one();
+        let s1: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        let lm1 = (ScalarElem::zero() - s1).to_biguint().unwrap();
+        assert!(bigint::scalar_from_biguint(&(lm1 + one)).err() ==
+                Some(NonCanonical));
+    }
+
+    #[test]
+    fn test_field() {
+        let x = FieldElem::new_rand();
+        let n = x.to_biguint().unwrap();
+        assert!(bigint::field_from_biguint(&n).unwrap() == x);
+
+        // P = 2^414 - 17
+        let one: BigUint = One::one();
+        let p = (one << 414) - 17u.to_biguint().unwrap();
+        assert!(bigint::field_from_biguint(&p).err() == Some(NonCanonical));
+        let pm1 = (FieldElem::zero() - FieldElem::one()).to_biguint()
+            .unwrap();
+        assert!(pm1 + One::one() == p);
+    }
+}
@@ -0,0 +1,225 @@
+//! Public-key authenticated encryption
+//!
+//! NaCl `crypto_box`-like construction: the sender's secret key and the
+//! recipient's public key (or conversely) give a shared key which is used
+//! with a caller-supplied `Aead` (e.g. `aead::HmacStream`) for encrypting
+//! and authenticating messages. Keys are Montgomery keys such as those
+//! returned by `mont::keypair()`. The shared key may be computed once
+//! with `beforenm()` and reused for many messages, nonces must then never
+//! be reused with the same pair of keys. Sealed boxes (`sealed_box()`)
+//! encrypt to a public key without identifying the sender.
+use std::result;
+
+use aead::Aead;
+use bytes::{Bytes, MontPoint, Scalar};
+use digest::Digest;
+use error::Error;
+use kdf;
+use mont;
+use sbuf::{DefaultAllocator, SBuf};
+
+
+static BOX_LABEL: &'static [u8] = b"curve41417 box";
+static SEALED_NONCE_LABEL: &'static [u8] = b"curve41417 sealed box nonce";
+
+
+/// Precomputed shared key between two key pairs, see `beforenm()`.
+pub struct PrecomputedKey {
+    key: SBuf<DefaultAllocator, u8>
+}
+
+impl PrecomputedKey {
+    /// Return the shared key as a byte slice.
+    pub fn as_bytes<'a>(&'a self) -> &'a [u8] {
+        self.key.as_slice()
+    }
+}
+
+/// Precompute a shared key
+///
+/// Derive a `aead.key_bytes()` bytes shared key from the shared secret
+/// between the secret key `sk` and the peer's public key `pk` (see
+/// `mont::shared_secret_checked()`), both sides get the same key. Return
+/// an error if `pk` is not a valid public key or if the shared secret is
+/// zero.
+pub fn beforenm<D: Digest, A: Aead>(digest: &mut D, aead: &A, sk: &Scalar,
+                                    pk: &MontPoint)
+                                    -> result::Result<PrecomputedKey, Error> {
+    let shared = try!(mont::shared_secret_checked(
+        sk, pk.get_ref().as_bytes()));
+    let mut key: SBuf<DefaultAllocator, u8> =
+        SBuf::new_zero(aead.key_bytes());
+    kdf::derive(digest, [], shared.get_ref().as_bytes(), BOX_LABEL,
+                key.as_mut_slice());
+    Ok(PrecomputedKey {
+        key: key
+    })
+}
+
+/// Encrypt and authenticate `msg` with the precomputed key `k` and
+/// `nonce` (`aead.nonce_bytes()` long). Return the ciphertext followed by
+/// its tag, or the error of `aead.seal()` (e.g. `Err(InvalidLength)` for
+/// a `nonce` of the wrong length).
+pub fn seal_afternm<A: Aead>(aead: &mut A, k: &PrecomputedKey, nonce: &[u8],
+                             msg: &[u8]) -> result::Result<Vec<u8>, Error> {
+    let mut c = Vec::from_elem(msg.len() + aead.tag_bytes(), 0u8);
+    try!(aead.seal(k.as_bytes(), nonce, msg, c.as_mut_slice()));
+    Ok(c)
+}
+
+/// Authenticate and decrypt `c` produced by `seal_afternm()` with the
+/// precomputed key `k` and `nonce`. Return the plaintext, or `None` if `c`
+/// is too short or not authentic.
+pub fn open_afternm<A: Aead>(aead: &mut A, k: &PrecomputedKey, nonce: &[u8],
+                             c: &[u8]) -> Option<Vec<u8>> {
+    if c.len() < aead.tag_bytes() {
+        return None;
+    }
+    let mut m = Vec::from_elem(c.len() - aead.tag_bytes(), 0u8);
+    match aead.open(k.as_bytes(), nonce, c, m.as_mut_slice()) {
+        true => Some(m),
+        false => None
+    }
+}
+
+/// Encrypt and authenticate a message
+///
+/// Same as `seal_afternm()` with the key `beforenm(sk, pk)` where `sk`
+/// is the sender's secret key and `pk` the recipient's public key.
+pub fn seal<D: Digest, A: Aead>(digest: &mut D, aead: &mut A, nonce: &[u8],
+                                msg: &[u8], sk: &Scalar, pk: &MontPoint)
+                                -> result::Result<Vec<u8>, Error> {
+    let k = try!(beforenm(digest, aead, sk, pk));
+    seal_afternm(aead, &k, nonce, msg)
+}
+
+/// Authenticate and decrypt a message
+///
+/// Same as `open_afternm()` with the key `beforenm(sk, pk)` where `sk`
+/// is the recipient's secret key and `pk` the sender's public key. Also
+/// return `None` if `pk` is not a valid public key.
+pub fn open<D: Digest, A: Aead>(digest: &mut D, aead: &mut A, nonce: &[u8],
+                                c: &[u8], sk: &Scalar, pk: &MontPoint)
+                                -> Option<Vec<u8>> {
+    match beforenm(digest, aead, sk, pk) {
+        Ok(k) => open_afternm(aead, &k, nonce, c),
+        Err(_) => None
+    }
+}
+
+// Derive the nonce of a sealed box from the ephemeral public key epk and
+// the recipient's public key pk.
+fn sealed_nonce<D: Digest, A: Aead>(digest: &mut D, aead: &A,
+                                    epk: &MontPoint, pk: &MontPoint)
+                                    -> Vec<u8> {
+    let mut ikm = Vec::with_capacity(104);
+    ikm.push_all(epk.get_ref().as_bytes());
+    ikm.push_all(pk.get_ref().as_bytes());
+    let mut nonce = Vec::from_elem(aead.nonce_bytes(), 0u8);
+    kdf::derive(digest, [], ikm.as_slice(), SEALED_NONCE_LABEL,
+                nonce.as_mut_slice());
+    nonce
+}
+
+/// Encrypt a message anonymously (sealed box)
+///
+/// Encrypt `msg` for the recipient's public key `pk` with a new ephemeral
+/// key pair whose secret key is dropped right after, the sender remains
+/// anonymous and can't decrypt the result. The nonce is derived from the
+/// ephemeral public key and `pk`. Return `epk || c` where `epk` is the 52
+/// bytes ephemeral public key and `c` the output of `seal()`.
+pub fn sealed_box<D: Digest, A: Aead>(digest: &mut D, aead: &mut A,
+                                      msg: &[u8], pk: &MontPoint)
+                                      -> result::Result<Vec<u8>, Error> {
+    let (epk, esk) = mont::keypair();
+    let nonce = sealed_nonce(digest, aead, &epk, pk);
+    let c = try!(seal(digest, aead, nonce.as_slice(), msg, &esk, pk));
+
+    let mut r = Vec::with_capacity(52 + c.len());
+    r.push_all(epk.get_ref().as_bytes());
+    r.push_all(c.as_slice());
+    Ok(r)
+}
+
+/// Decrypt a sealed box
+///
+/// Decrypt `c` produced by `sealed_box()` with the recipient's key pair
+/// `(pk, sk)`. Return the plaintext, or `None` if `c` is malformed or not
+/// authentic.
+pub fn sealed_box_open<D: Digest, A: Aead>(digest: &mut D, aead: &mut A,
+                                           c: &[u8], pk: &MontPoint,
+                                           sk: &Scalar) -> Option<Vec<u8>> {
+    if c.len() < 52 {
+        return None;
+    }
+    let epk = MontPoint(Bytes::from_bytes(c.slice_to(52)).unwrap());
+    let nonce = sealed_nonce(digest, aead, &epk, pk);
+    open(digest, aead, nonce.as_slice(), c.slice_from(52), sk, &epk)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use aead::HmacStream;
+    use box_;
+    use bytes::{B416, Bytes, MontPoint};
+    use digest::sha256::Sha256;
+    use error::{InvalidLength, SmallOrder};
+    use mont;
+
+
+    #[test]
+    fn test_box() {
+        let mut h = Sha256::new();
+        let mut c = HmacStream::new(Sha256::new());
+        let (apk, ask) = mont::keypair();
+        let (bpk, bsk) = mont::keypair();
+        let nonce = [3u8, ..24];
+        let msg = "message".as_bytes();
+
+        let ct = box_::seal(&mut h, &mut c, nonce, msg, &ask, &bpk).unwrap();
+        assert!(ct.len() == msg.len() + 32);
+        let pt = box_::open(&mut h, &mut c, nonce, ct.as_slice(), &bsk, &apk);
+        assert!(pt.unwrap().as_slice() == msg);
+
+        let ka = box_::beforenm(&mut h, &c, &ask, &bpk).unwrap();
+        let kb = box_::beforenm(&mut h, &c, &bsk, &apk).unwrap();
+        assert!(ka.as_bytes() == kb.as_bytes());
+        assert!(box_::seal_afternm(&mut c, &ka, nonce, msg).unwrap() == ct);
+
+        let (cpk, _) = mont::keypair();
+        assert!(box_::open(&mut h, &mut c, nonce, ct.as_slice(), &bsk,
+                           &cpk).is_none());
+        assert!(box_::open_afternm(&mut c, &kb, nonce,
+                                   ct.slice_to(31)).is_none());
+        assert!(box_::seal_afternm(&mut c, &ka, nonce.slice_to(8), msg)
+                .err() == Some(InvalidLength(24, 8)));
+        assert!(box_::open_afternm(&mut c, &kb, nonce.slice_to(8),
+                                   ct.as_slice()).is_none());
+
+        let zero: B416 = Bytes::new_zero();
+        assert!(box_::beforenm(&mut h, &c, &ask, &MontPoint(zero)).err() ==
+                Some(SmallOrder));
+    }
+
+    #[test]
+    fn test_sealed_box() {
+        let mut h = Sha256::new();
+        let mut c = HmacStream::new(Sha256::new());
+        let (pk, sk) = mont::keypair();
+        let msg = "message".as_bytes();
+
+        let ct = box_::sealed_box(&mut h, &mut c, msg, &pk).unwrap();
+        assert!(ct.len() == 52 + msg.len() + 32);
+        assert!(ct != box_::sealed_box(&mut h, &mut c, msg, &pk).unwrap());
+        let pt = box_::sealed_box_open(&mut h, &mut c, ct.as_slice(), &pk,
+                                       &sk);
+        assert!(pt.unwrap().as_slice() == msg);
+
+        let (pk2, sk2) = mont::keypair();
+        assert!(box_::sealed_box_open(&mut h, &mut c, ct.as_slice(), &pk2,
+                                      &sk2).is_none());
+        assert!(box_::sealed_box_open(&mut h, &mut c, ct.slice_to(51), &pk,
+                                      &sk).is_none());
+    }
+}
@@ -1,12 +1,16 @@
 //! Raw bytes representations
 //!
 //! These containers are used to store packed scalars and curve points.
+use serialize::{Decodable, Decoder};
 use serialize::hex::{FromHex, ToHex};
-use std::fmt::{Show, Formatter, Result};
+use std::fmt::{LowerHex, Show, Formatter, UpperHex, Result};
 use std::from_str::FromStr;
+use std::hash::{Hash, Writer};
 use std::rand::{Rand, Rng};
 use std::slice::bytes;
 
+use choice;
+use choice::{Choice, ConditionallySelectable};
 use ed::GroupElem;
 use mont;
 use sbuf::{DefaultAllocator, SBuf};
@@ -57,10 +61,25 @@ pub trait Bytes: PartialEq + Eq + Rand + Show + Clone + Collection {
 }
 
 
+/// Format `bytes` as an hex-string in their stored order with lowercase or
+/// uppercase digits, honoring `f`'s width, fill, alignment and `#` (`0x`
+/// prefix) flags.
+#[doc(hidden)]
+pub fn fmt_hex(bytes: &[u8], upper: bool, f: &mut Formatter) -> Result {
+    let mut h = bytes.to_hex().into_bytes();
+    if upper {
+        for c in h.mut_iter() {
+            *c = (*c as char).to_uppercase() as u8;
+        }
+    }
+    f.pad_integral(true, "0x", h.as_slice())
+}
+
+
 // These struct declarations are not inserted in the macro because
 // it seems the macros variables cannot be expanded in the comments.
 /// 52-bytes container.
-#[deriving(Clone, Eq, PartialEq, Encodable, Decodable)]
+#[deriving(Clone, Eq, PartialEq, Encodable)]
 pub struct B416 {
     bytes: SBuf<DefaultAllocator, u8>
 }
@@ -74,20 +93,57 @@ impl B416 {
 }
 
 /// 64-bytes container.
-#[deriving(Clone, Eq, PartialEq, Encodable, Decodable)]
+#[deriving(Clone, Eq, PartialEq, Encodable)]
 pub struct B512 {
     bytes: SBuf<DefaultAllocator, u8>
 }
 
+/// 84-bytes container.
+#[deriving(Clone, Eq, PartialEq, Encodable)]
+pub struct B672 {
+    bytes: SBuf<DefaultAllocator, u8>
+}
+
+/// 96-bytes container.
+#[deriving(Clone, Eq, PartialEq, Encodable)]
+pub struct B768 {
+    bytes: SBuf<DefaultAllocator, u8>
+}
+
 /// 104-bytes container.
-#[deriving(Clone, Eq, PartialEq, Encodable, Decodable)]
+#[deriving(Clone, Eq, PartialEq, Encodable)]
 pub struct B832 {
     bytes: SBuf<DefaultAllocator, u8>
 }
 
+/// 114-bytes container.
+#[deriving(Clone, Eq, PartialEq, Encodable)]
+pub struct B912 {
+    bytes: SBuf<DefaultAllocator, u8>
+}
+
 
 macro_rules! bytes_impl(($name:ident, $test_mod_id:ident, $size:expr) => (
 
+impl $name {
+    /// Constant-time equality comparison.
+    pub fn ct_eq(&self, other: &$name) -> Choice {
+        Choice::from_u8(utils::bytes_ct_eq(self.as_bytes(), other.as_bytes()))
+    }
+}
+
+impl ConditionallySelectable for $name {
+    fn conditional_swap(&mut self, other: &mut $name, choice: Choice) {
+        let mask = -(choice.unwrap_u8() as i8) as u8;
+        for (x, y) in self.as_mut_bytes().mut_iter()
+            .zip(other.as_mut_bytes().mut_iter()) {
+            let t = mask & (*x ^ *y);
+            *x ^= t;
+            *y ^= t;
+        }
+    }
+}
+
 impl Bytes for $name {
     /// Return a new instance initialized to zero.
     fn new_zero() -> $name {
@@ -133,6 +189,20 @@ impl Show for $name {
     }
 }
 
+impl LowerHex for $name {
+    /// Format as lowercase hex-string, `{:#x}` adds a `0x` prefix.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        fmt_hex(self.as_bytes(), false, f)
+    }
+}
+
+impl UpperHex for $name {
+    /// Format as uppercase hex-string, `{:#X}` adds a `0x` prefix.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        fmt_hex(self.as_bytes(), true, f)
+    }
+}
+
 impl Rand for $name {
     /// Generate a new random instance. Be sure to use a secure
     /// PRNG when calling this method. For instance `Bytes::new_rand()`
@@ -150,6 +220,33 @@ impl Collection for $name {
     }
 }
 
+impl<S: Writer> Hash<S> for $name {
+    /// Hash the bytes, for using containers as keys of hash maps. Hashing
+    /// is not constant-time, don't use secret values as keys.
+    fn hash(&self, state: &mut S) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl<E, D: Decoder<E>> Decodable<D, E> for $name {
+    /// Decode a sequence of bytes, an error is returned if it doesn't
+    /// have exactly the expected length.
+    fn decode(d: &mut D) -> ::std::result::Result<$name, E> {
+        d.read_seq(|d, len| {
+            if len != $size {
+                return Err(d.error("invalid number of bytes"));
+            }
+            let mut n: $name = Bytes::new_zero();
+            for i in range(0u, len) {
+                *n.get_mut(i) = try!(d.read_seq_elt(i, |d| {
+                    Decodable::decode(d)
+                }));
+            }
+            Ok(n)
+        })
+    }
+}
+
 impl ToHex for $name {
     fn to_hex(&self) -> String {
         self.bytes.to_hex()
@@ -159,6 +256,7 @@ impl ToHex for $name {
 
 #[cfg(test)]
 mod $test_mod_id {
+    use serialize::json;
     use std::io::MemWriter;
     use std::rand::{Rng, Rand};
     use std::str;
@@ -209,6 +307,31 @@ mod $test_mod_id {
         }
     }
 
+    #[test]
+    fn test_decodable() {
+        let a: $name = Bytes::new_rand();
+        let s = json::encode(&a);
+        let b: $name = json::decode(s.as_slice()).unwrap();
+        assert!(a == b);
+
+        let short: json::DecodeResult<$name> = json::decode("[1,2,3]");
+        assert!(short.is_err());
+        let empty: json::DecodeResult<$name> = json::decode("[]");
+        assert!(empty.is_err());
+    }
+
+    #[test]
+    fn test_hex_fmt() {
+        let mut t: [u8, ..$size] = [0u8, ..$size];
+        t[0] = 0xab;
+        let a: $name = Bytes::from_bytes(t).unwrap();
+        let zeros = String::from_char(2 * $size - 2, '0');
+
+        assert!(format!("{:x}", a) == format!("ab{}", zeros));
+        assert!(format!("{:X}", a) == format!("AB{}", zeros));
+        assert!(format!("{:#x}", a) == format!("0xab{}", zeros));
+    }
+
     #[test]
     fn test_rand() {
         let mut rng = MockRng::new();
@@ -224,21 +347,93 @@ mod $test_mod_id {
 
 bytes_impl!(B416, test_b416, 52)
 bytes_impl!(B512, test_b512, 64)
+bytes_impl!(B672, test_b672, 84)
+bytes_impl!(B768, test_b768, 96)
 bytes_impl!(B832, test_b832, 104)
+bytes_impl!(B912, test_b912, 114)
+
+
+/// Return `table[index]`, or a zeroed container if `index` is out of
+/// bounds. Every byte of the table is read and masked, the accessed
+/// memory and the executed operations don't depend on `index`: use this
+/// for looking up secret-indexed entries of user tables. See
+/// `choice::lookup()`.
+pub fn select<T: Bytes + ConditionallySelectable>(table: &[T], index: uint)
+                                                  -> T {
+    choice::lookup(table, index, Bytes::new_zero())
+}
 
 
+/// Minimum bias bound (see `Uniformity::bias_bound()`) of the containers
+/// implementing `Uniformity`.
+pub static MIN_BIAS_BOUND: uint = 96;
+
 /// Tag `Bytes` containers deemed sufficiently large for providing a good
 /// uniformity of the distribution `mod L`.
-pub trait Uniformity {
+///
+/// For a uniformly distributed `8n` bits input the statistical distance
+/// of its reduction `mod L` (`L ~ 2^411`) from the uniform distribution
+/// is at most `2^(411 - 8n)`, and at most `2^(414 - 8n)` for its reduction
+/// `mod P` (`P ~ 2^414`). This trait is only implemented for sizes whose
+/// bound is at most `2^-MIN_BIAS_BOUND` for both reductions, functions
+/// requiring `Uniformity` (such as `ScalarElem::reduce_from_bytes()`)
+/// then fail to compile when given a too small container: `B416` inputs
+/// would have a bias up to `2^-5 mod L` and `2^-2 mod P`.
+pub trait Uniformity: Bytes {
+    /// Return `k` such that the statistical distance of the reduction
+    /// `mod L` of a uniform input from the uniform distribution is at most
+    /// `2^-k`. The bound for the reduction `mod P` is `2^-(k - 3)`.
+    fn bias_bound(&self) -> uint;
 }
 
 impl Uniformity for B512 {
+    /// `2^-101` `mod L`, `2^-98` `mod P`.
+    fn bias_bound(&self) -> uint {
+        101
+    }
+}
+
+impl Uniformity for B672 {
+    /// `2^-261` `mod L`, `2^-258` `mod P`.
+    fn bias_bound(&self) -> uint {
+        261
+    }
+}
+
+impl Uniformity for B768 {
+    /// `2^-357` `mod L`, `2^-354` `mod P`.
+    fn bias_bound(&self) -> uint {
+        357
+    }
 }
 
 impl Uniformity for B832 {
+    /// `2^-421` `mod L`, `2^-418` `mod P`.
+    fn bias_bound(&self) -> uint {
+        421
+    }
+}
+
+impl Uniformity for B912 {
+    /// `2^-501` `mod L`, `2^-498` `mod P`.
+    fn bias_bound(&self) -> uint {
+        501
+    }
 }
 
 
+// Return bytes in reverse order, None if it isn't 52 bytes long.
+fn reversed(bytes: &[u8]) -> Option<B416> {
+    let mut r: B416 = Bytes::new_zero();
+    if bytes.len() != r.len() {
+        return None;
+    }
+    for (d, s) in r.as_mut_bytes().mut_iter().zip(bytes.iter().rev()) {
+        *d = *s;
+    }
+    Some(r)
+}
+
 macro_rules! wrapper_impl(($name:ident) => (
 
 /// Wrapper around `B416` packed value used in crypto ops.
@@ -262,7 +457,7 @@ macro_rules! wrapper_impl(($name:ident) => (
 /// let sk = Scalar(s);
 /// let pk: MontPoint = mont::scalar_mult_base(&sk);
 /// ```
-#[deriving(Clone, Show, Eq, PartialEq, Encodable, Decodable)]
+#[deriving(Clone, Show, Eq, PartialEq, Hash, Encodable, Decodable)]
 pub struct $name(pub B416);
 
 impl $name {
@@ -283,6 +478,29 @@ impl $name {
         let $name(val) = self;
         val
     }
+
+    /// Return the wrapped bytes in little-endian order, that is as they
+    /// are stored.
+    pub fn to_bytes_le(&self) -> B416 {
+        self.get_ref().clone()
+    }
+
+    /// Return the wrapped bytes in big-endian order (reversed).
+    pub fn to_bytes_be(&self) -> B416 {
+        reversed(self.get_ref().as_bytes()).unwrap()
+    }
+
+    /// Wrap 52 bytes given in little-endian order. Return `None` if
+    /// `bytes` isn't 52 bytes long.
+    pub fn from_bytes_le(bytes: &[u8]) -> Option<$name> {
+        Bytes::from_bytes(bytes).map(|b| $name(b))
+    }
+
+    /// Wrap 52 bytes given in big-endian order. Return `None` if `bytes`
+    /// isn't 52 bytes long.
+    pub fn from_bytes_be(bytes: &[u8]) -> Option<$name> {
+        reversed(bytes).map(|b| $name(b))
+    }
 }
 
 impl ToHex for $name {
@@ -291,6 +509,20 @@ impl ToHex for $name {
     }
 }
 
+impl LowerHex for $name {
+    /// Format the wrapped value as lowercase hex-string.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        fmt_hex(self.get_ref().as_bytes(), false, f)
+    }
+}
+
+impl UpperHex for $name {
+    /// Format the wrapped value as uppercase hex-string.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        fmt_hex(self.get_ref().as_bytes(), true, f)
+    }
+}
+
 ))
 
 wrapper_impl!(Scalar)
@@ -298,6 +530,31 @@ wrapper_impl!(MontPoint)
 wrapper_impl!(EdPoint)
 
 
+// Points are public values, they are ordered for sorting and
+// deduplicating them deterministically. Scalars are not ordered as
+// comparisons are not constant-time.
+macro_rules! ord_impl(($name:ident) => (
+
+impl PartialOrd for $name {
+    fn partial_cmp(&self, other: &$name) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for $name {
+    /// Lexicographic order of the packed bytes, as they are stored (in
+    /// little-endian order).
+    fn cmp(&self, other: &$name) -> Ordering {
+        self.get_ref().as_bytes().cmp(&other.get_ref().as_bytes())
+    }
+}
+
+))
+
+ord_impl!(MontPoint)
+ord_impl!(EdPoint)
+
+
 #[doc(hidden)]
 trait ScalarMul<P> {
     fn mul(&self, lhs: &Scalar) -> P;
@@ -316,6 +573,12 @@ impl ScalarMul<EdPoint> for EdPoint {
     }
 }
 
+impl ScalarMul<GroupElem> for GroupElem {
+    fn mul(&self, lhs: &Scalar) -> GroupElem {
+        self.scalar_mult(lhs)
+    }
+}
+
 impl<S, R: ScalarMul<S>> Mul<R, S> for Scalar {
     /// Multiply scalar with point.
     fn mul(&self, other: &R) -> S {
@@ -341,6 +604,12 @@ impl Mul<Scalar, EdPoint> for EdPoint {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use bytes;
+    use bytes::{B416, B512, B672, B768, B832, B912, Bytes, EdPoint};
+    use bytes::{MIN_BIAS_BOUND, MontPoint, Scalar};
+    use bytes::Uniformity;
     use ed::GroupElem;
     use mont;
 
@@ -367,4 +636,87 @@ mod tests {
         assert!(sk2 == sk);
         assert!(sk2.get_ref() == sk.get_ref());
     }
+
+    #[test]
+    fn test_hash() {
+        let (pk1, _) = mont::keypair();
+        let (pk2, _) = mont::keypair();
+        let mut set = HashSet::new();
+        assert!(set.insert(pk1.clone()));
+        assert!(set.insert(pk2.clone()));
+        assert!(!set.insert(pk1.clone()));
+        assert!(set.contains(&pk2) && set.len() == 2);
+
+        let b: B512 = Bytes::new_rand();
+        let mut map = HashMap::new();
+        map.insert(b.clone(), 1u);
+        assert!(map.find(&b) == Some(&1));
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut b1: B416 = Bytes::new_zero();
+        let mut b2: B416 = Bytes::new_zero();
+        *b1.get_mut(0) = 2;
+        *b1.get_mut(51) = 1;
+        *b2.get_mut(0) = 3;
+        assert!(EdPoint(b1.clone()) < EdPoint(b2.clone()));
+        assert!(MontPoint(b2.clone()) > MontPoint(b1.clone()));
+
+        let mut v = Vec::from_fn(5, |_| {
+            let (pk, _) = mont::keypair();
+            pk
+        });
+        v.push(v.get(2).clone());
+        v.sort();
+        v.dedup();
+        assert!(v.len() == 5);
+        for i in range(1u, v.len()) {
+            assert!(v.get(i - 1).get_ref().as_bytes() <
+                    v.get(i).get_ref().as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let t: Vec<B512> = Vec::from_fn(4, |_| Bytes::new_rand());
+        for i in range(0u, 4) {
+            assert!(bytes::select(t.as_slice(), i) == *t.get(i));
+        }
+        let z: B512 = Bytes::new_zero();
+        assert!(bytes::select(t.as_slice(), 4) == z);
+    }
+
+    #[test]
+    fn test_bias_bound() {
+        let b1: B512 = Bytes::new_zero();
+        let b2: B672 = Bytes::new_zero();
+        let b3: B768 = Bytes::new_zero();
+        let b4: B832 = Bytes::new_zero();
+        let b5: B912 = Bytes::new_zero();
+        for &(bound, len) in [(b1.bias_bound(), b1.len()),
+                              (b2.bias_bound(), b2.len()),
+                              (b3.bias_bound(), b3.len()),
+                              (b4.bias_bound(), b4.len()),
+                              (b5.bias_bound(), b5.len())].iter() {
+            assert!(bound == 8 * len - 411);
+            assert!(bound - 3 >= MIN_BIAS_BOUND);
+        }
+    }
+
+    #[test]
+    fn test_endianness() {
+        let (pk, sk) = mont::keypair();
+        let le = sk.to_bytes_le();
+        let be = sk.to_bytes_be();
+        assert!(le == *sk.get_ref());
+        for i in range(0u, 52) {
+            assert!(*be.get(i) == *le.get(51 - i));
+        }
+        assert!(Scalar::from_bytes_le(le.as_bytes()).unwrap() == sk);
+        assert!(Scalar::from_bytes_be(be.as_bytes()).unwrap() == sk);
+        assert!(MontPoint::from_bytes_be(pk.to_bytes_be().as_bytes())
+                .unwrap() == pk);
+        assert!(Scalar::from_bytes_be(be.as_bytes().slice_to(51)).is_none());
+    }
 }
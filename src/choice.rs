@@ -0,0 +1,149 @@
+//! Constant-time booleans
+//!
+//! Constant-time predicates such as `ct_eq()`, `is_zero()` or
+//! `ScalarElem::is_canonical()` return a `Choice` instead of a `bool`, so
+//! that their results are not branched on by accident. Choices are
+//! combined with `&`, `|` and `!` without branching, the underlying bit is
+//! only revealed by an explicit call to `unwrap_u8()`, once the result is
+//! no longer secret. Types holding secrets implement
+//! `ConditionallySelectable` for writing generic constant-time algorithms
+//! such as `lookup()`.
+use std::slice;
+
+use audit;
+use utils;
+
+
+/// Opaque constant-time boolean, holds `0` or `1`.
+#[deriving(Clone)]
+pub struct Choice {
+    bit: u8
+}
+
+impl Choice {
+    /// Return the choice of bit `bit`, which must be `0` or `1` strictly.
+    pub fn from_u8(bit: u8) -> Choice {
+        debug_assert!(bit <= 1);
+        Choice {
+            bit: bit
+        }
+    }
+
+    /// Return the underlying bit of this choice: `1` for true, `0` for
+    /// false. Branching on the result leaks it, only unwrap choices which
+    /// may become public.
+    pub fn unwrap_u8(&self) -> u8 {
+        self.bit
+    }
+}
+
+/// Constant-time conditional assignment
+///
+/// Implemented by scalars, field elements, points and byte containers.
+/// Implementors only provide `conditional_swap()`, every method executes
+/// the same operations whatever the value of the choice.
+pub trait ConditionallySelectable: Clone {
+    /// Swap `self` and `other` iff `choice` is true, leave them unchanged
+    /// otherwise.
+    fn conditional_swap(&mut self, other: &mut Self, choice: Choice);
+
+    /// Assign `other` to `self` iff `choice` is true.
+    fn conditional_assign(&mut self, other: &Self, choice: Choice) {
+        let mut t = other.clone();
+        self.conditional_swap(&mut t, choice);
+    }
+
+    /// Return a copy of `b` if `choice` is true, of `a` otherwise.
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut r = a.clone();
+        r.conditional_assign(b, choice);
+        r
+    }
+}
+
+/// Return `table[index]`, or `default` if `index` is out of bounds. Every
+/// entry of the table is read and conditionally assigned, the accessed
+/// memory and the executed operations don't depend on `index`. Each read
+/// is reported to the audit hooks.
+pub fn lookup<T: ConditionallySelectable>(table: &[T], index: uint,
+                                          default: T) -> T {
+    let mut r = default;
+    for (j, e) in table.iter().enumerate() {
+        audit::access("choice::lookup", slice::ref_slice(e));
+        let c = Choice::from_u8(utils::uint_eq(j, index) as u8);
+        r.conditional_assign(e, c);
+    }
+    r
+}
+
+impl BitAnd<Choice, Choice> for Choice {
+    fn bitand(&self, other: &Choice) -> Choice {
+        Choice::from_u8(self.bit & other.bit)
+    }
+}
+
+impl BitOr<Choice, Choice> for Choice {
+    fn bitor(&self, other: &Choice) -> Choice {
+        Choice::from_u8(self.bit | other.bit)
+    }
+}
+
+impl Not<Choice> for Choice {
+    fn not(&self) -> Choice {
+        Choice::from_u8(self.bit ^ 1)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bytes::{B416, Bytes};
+    use choice;
+    use choice::{Choice, ConditionallySelectable};
+    use ed::GroupElem;
+    use fe::FieldElem;
+    use sc::ScalarElem;
+
+
+    fn check<T: ConditionallySelectable + PartialEq>(a: T, b: T) {
+        let t = Choice::from_u8(1);
+        let f = Choice::from_u8(0);
+        let s: T = ConditionallySelectable::conditional_select(&a, &b, t);
+        assert!(s == b);
+        let s: T = ConditionallySelectable::conditional_select(&a, &b, f);
+        assert!(s == a);
+
+        let (mut x, mut y) = (a.clone(), b.clone());
+        x.conditional_swap(&mut y, f);
+        assert!(x == a && y == b);
+        x.conditional_swap(&mut y, t);
+        assert!(x == b && y == a);
+        x.conditional_assign(&b, t);
+        assert!(x == b);
+
+        let table = vec![a.clone(), b.clone()];
+        assert!(choice::lookup(table.as_slice(), 1, a.clone()) == b);
+        assert!(choice::lookup(table.as_slice(), 2, b.clone()) == b);
+    }
+
+    #[test]
+    fn test_conditionally_selectable() {
+        check(ScalarElem::new_rand(), ScalarElem::new_rand());
+        check(FieldElem::new_rand(), FieldElem::new_rand());
+        check(GroupElem::new_rand(), GroupElem::new_rand());
+        let a: B416 = Bytes::new_rand();
+        let b: B416 = Bytes::new_rand();
+        check(a, b);
+    }
+
+
+    #[test]
+    fn test_combinators() {
+        let t = Choice::from_u8(1);
+        let f = Choice::from_u8(0);
+        assert!(t.unwrap_u8() == 1 && f.unwrap_u8() == 0);
+        assert!((t & t).unwrap_u8() == 1 && (t & f).unwrap_u8() == 0);
+        assert!((f | t).unwrap_u8() == 1 && (f | f).unwrap_u8() == 0);
+        assert!((!t).unwrap_u8() == 0 && (!f).unwrap_u8() == 1);
+    }
+}
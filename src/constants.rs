@@ -0,0 +1,167 @@
+//! Curve41417 constants
+//!
+//! Parameters of the curve as little-endian byte arrays, usable in static
+//! contexts (e.g. for embedding protocol constants in other crates), and
+//! as typed values returned by the accessors of this module, such as
+//! `l_minus_one()` or `p_minus_one()`. `ScalarElem`, `FieldElem` and
+//! `GroupElem` wipe their limbs when dropped, types with a destructor
+//! can't be statics.
+use bytes::{B416, Bytes, EdPoint, MontPoint};
+use ed::GroupElem;
+use fe::FieldElem;
+use sc::ScalarElem;
+
+
+/// Cofactor of the curve, the order of the curve is `COFACTOR * L`.
+pub static COFACTOR: uint = 8;
+
+/// `L = 2^411 - d` with
+/// `d = 33364140863755142520810177694098385178984727200411208589594759`,
+/// the order of the base point.
+pub static L: [u8, ..52] = [
+    0x79, 0xaf, 0x06, 0xe1, 0xa5, 0x71, 0x0e, 0x1b,
+    0x18, 0xcf, 0x63, 0xad, 0x38, 0x03, 0x1c, 0x6f,
+    0xb3, 0x22, 0x60, 0x70, 0xcf, 0x14, 0x24, 0xc9,
+    0x3c, 0xeb, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0x07];
+
+/// `L - 1`, the largest canonical scalar value.
+pub static L_MINUS_ONE: [u8, ..52] = [
+    0x78, 0xaf, 0x06, 0xe1, 0xa5, 0x71, 0x0e, 0x1b,
+    0x18, 0xcf, 0x63, 0xad, 0x38, 0x03, 0x1c, 0x6f,
+    0xb3, 0x22, 0x60, 0x70, 0xcf, 0x14, 0x24, 0xc9,
+    0x3c, 0xeb, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0x07];
+
+/// `P = 2^414 - 17`, the prime of the field.
+pub static P: [u8, ..52] = [
+    0xef, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0x3f];
+
+/// `P - 1`, the largest canonical field element.
+pub static P_MINUS_ONE: [u8, ..52] = [
+    0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0x3f];
+
+/// `d = 3617`, parameter of the Edwards curve `x^2 + y^2 = 1 + d.x^2.y^2`.
+pub static EDWARDS_D: [u8, ..52] = [
+    0x21, 0x0e, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0];
+
+/// Packed base point in Edwards representation: `y = 34` and the sign of
+/// its `x` coordinate (odd) in the top bit.
+pub static BASE_POINT: [u8, ..52] = [
+    0x22, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0x80];
+
+/// Base point in Montgomery representation, its x-coordinate
+/// `u = (1 + y) / (1 - y)`.
+pub static BASE_POINT_MONT: [u8, ..52] = [
+    0x0e, 0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0,
+    0xc1, 0x07, 0x1f, 0x7c, 0xf0, 0xc1, 0x07, 0x1f,
+    0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0, 0xc1,
+    0x07, 0x1f, 0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c,
+    0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0, 0xc1, 0x07,
+    0x1f, 0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0,
+    0xc1, 0x07, 0x1f, 0x3c];
+
+
+/// Return `L` as a `B416`.
+pub fn l() -> B416 {
+    Bytes::from_bytes(L).unwrap()
+}
+
+/// Return `L_MINUS_ONE` as a scalar.
+pub fn l_minus_one() -> ScalarElem {
+    ScalarElem::l_minus_one()
+}
+
+/// Return `P` as a `B416`.
+pub fn p() -> B416 {
+    Bytes::from_bytes(P).unwrap()
+}
+
+/// Return `P_MINUS_ONE` as a field element.
+pub fn p_minus_one() -> FieldElem {
+    FieldElem::p_minus_one()
+}
+
+/// Return `EDWARDS_D` as a field element.
+pub fn edwards_d() -> FieldElem {
+    let d: B416 = Bytes::from_bytes(EDWARDS_D).unwrap();
+    FieldElem::unpack(&d)
+}
+
+/// Return the packed base point `BASE_POINT`.
+pub fn base_point() -> EdPoint {
+    EdPoint(Bytes::from_bytes(BASE_POINT).unwrap())
+}
+
+/// Return the Montgomery base point `BASE_POINT_MONT`.
+pub fn base_point_mont() -> MontPoint {
+    MontPoint(Bytes::from_bytes(BASE_POINT_MONT).unwrap())
+}
+
+/// Return the base point `BASE_POINT` as a group element.
+pub fn base() -> GroupElem {
+    GroupElem::base()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use constants;
+    use ed::GroupElem;
+    use fe::FieldElem;
+    use sc::ScalarElem;
+
+
+    #[test]
+    fn test_constants() {
+        assert!(constants::l() == ScalarElem::order());
+        assert!(ScalarElem::l_minus_one().pack().get_ref().as_bytes() ==
+                constants::L_MINUS_ONE.as_slice());
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        assert!(ScalarElem::l_minus_one() + one == ScalarElem::zero());
+        assert!(constants::l_minus_one() == ScalarElem::l_minus_one());
+
+        assert!(FieldElem::p_minus_one() + FieldElem::one() ==
+                FieldElem::zero());
+        assert!(FieldElem::p_minus_one().pack().as_bytes() ==
+                constants::P_MINUS_ONE.as_slice());
+        assert!(FieldElem::unpack(&constants::p()) == FieldElem::zero());
+        assert!(constants::p_minus_one() == FieldElem::p_minus_one());
+        let d = FieldElem::from_u64_limbs([3617, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert!(constants::edwards_d() == d);
+
+        let bp = GroupElem::base();
+        assert!(constants::base() == bp);
+        assert!(bp.pack() == constants::base_point());
+        assert!(bp.to_mont() == constants::base_point_mont());
+        assert!(bp.scalar_mult_cofactor() != GroupElem::neutral());
+    }
+}
@@ -0,0 +1,194 @@
+//! Hash function interface
+//!
+//! This crate doesn't ship any hash function, instead callers supply
+//! their own implementation through the `Digest` trait. It is used by
+//! the key derivation functions and by any other construction needing
+//! a hash function.
+
+
+/// Cryptographic hash function supplied by the caller.
+pub trait Digest {
+    /// Feed `input` to the hash function.
+    fn input(&mut self, input: &[u8]);
+
+    /// Write the final digest into `out` which must be at least
+    /// `output_bytes()` long. The digest must be `reset()` before being
+    /// reused.
+    fn result(&mut self, out: &mut [u8]);
+
+    /// Reset the hash function to its initial state.
+    fn reset(&mut self);
+
+    /// Return the size of the digest in bits.
+    fn output_bits(&self) -> uint;
+
+    /// Return the size of the digest in bytes.
+    fn output_bytes(&self) -> uint {
+        (self.output_bits() + 7) / 8
+    }
+
+    /// Return the internal block size in bytes.
+    fn block_size(&self) -> uint;
+}
+
+
+// Plain SHA-256 implementation only used to exercise the constructions
+// built over `Digest` in unit tests.
+#[cfg(test)]
+pub mod sha256 {
+    use digest::Digest;
+
+
+    static K: [u32, ..64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5,
+        0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+        0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc,
+        0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+        0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3,
+        0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5,
+        0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+        0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2];
+
+    static H0: [u32, ..8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+        0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+    fn rotr(x: u32, n: uint) -> u32 {
+        (x >> n) | (x << (32 - n))
+    }
+
+    #[deriving(Clone)]
+    pub struct Sha256 {
+        h: [u32, ..8],
+        buf: Vec<u8>,
+        len: u64
+    }
+
+    impl Sha256 {
+        pub fn new() -> Sha256 {
+            Sha256 {
+                h: H0,
+                buf: Vec::new(),
+                len: 0
+            }
+        }
+
+        fn compress(&mut self, block: &[u8]) {
+            let mut w = [0u32, ..64];
+            for i in range(0u, 16) {
+                w[i] = (block[4 * i] as u32 << 24) |
+                    (block[4 * i + 1] as u32 << 16) |
+                    (block[4 * i + 2] as u32 << 8) |
+                    (block[4 * i + 3] as u32);
+            }
+            for i in range(16u, 64) {
+                let s0 = rotr(w[i - 15], 7) ^ rotr(w[i - 15], 18) ^
+                    (w[i - 15] >> 3);
+                let s1 = rotr(w[i - 2], 17) ^ rotr(w[i - 2], 19) ^
+                    (w[i - 2] >> 10);
+                w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+            }
+
+            let mut v = self.h;
+            for i in range(0u, 64) {
+                let s1 = rotr(v[4], 6) ^ rotr(v[4], 11) ^ rotr(v[4], 25);
+                let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+                let t1 = v[7] + s1 + ch + K[i] + w[i];
+                let s0 = rotr(v[0], 2) ^ rotr(v[0], 13) ^ rotr(v[0], 22);
+                let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+                let t2 = s0 + maj;
+                v[7] = v[6];
+                v[6] = v[5];
+                v[5] = v[4];
+                v[4] = v[3] + t1;
+                v[3] = v[2];
+                v[2] = v[1];
+                v[1] = v[0];
+                v[0] = t1 + t2;
+            }
+            for i in range(0u, 8) {
+                self.h[i] += v[i];
+            }
+        }
+    }
+
+    impl Digest for Sha256 {
+        fn input(&mut self, input: &[u8]) {
+            self.len += input.len() as u64;
+            self.buf.push_all(input);
+            while self.buf.len() >= 64 {
+                let block: Vec<u8> = self.buf.slice_to(64).to_vec();
+                self.compress(block.as_slice());
+                self.buf = self.buf.slice_from(64).to_vec();
+            }
+        }
+
+        fn result(&mut self, out: &mut [u8]) {
+            let bits = self.len * 8;
+            let mut pad = vec![0x80u8];
+            while (self.buf.len() + pad.len()) % 64 != 56 {
+                pad.push(0);
+            }
+            for i in range(0u, 8).rev() {
+                pad.push((bits >> (8 * i)) as u8);
+            }
+            self.input(pad.as_slice());
+            for i in range(0u, 8) {
+                out[4 * i] = (self.h[i] >> 24) as u8;
+                out[4 * i + 1] = (self.h[i] >> 16) as u8;
+                out[4 * i + 2] = (self.h[i] >> 8) as u8;
+                out[4 * i + 3] = self.h[i] as u8;
+            }
+        }
+
+        fn reset(&mut self) {
+            self.h = H0;
+            self.buf = Vec::new();
+            self.len = 0;
+        }
+
+        fn output_bits(&self) -> uint {
+            256
+        }
+
+        fn block_size(&self) -> uint {
+            64
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::ToHex;
+
+    use digest::Digest;
+    use digest::sha256::Sha256;
+
+
+    #[test]
+    fn test_sha256_ref() {
+        let mut h = Sha256::new();
+        let mut out = [0u8, ..32];
+
+        h.input("abc".as_bytes());
+        h.result(out);
+        assert!(out.to_hex().as_slice() ==
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        h.reset();
+        h.input("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+                .as_bytes());
+        h.result(out);
+        assert!(out.to_hex().as_slice() ==
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    }
+}
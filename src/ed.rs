@@ -2,9 +2,23 @@
 use serialize::hex::ToHex;
 use std::default::Default;
 use std::fmt::{Show, Formatter, Result};
+use std::iter::Skip;
+use std::rand::{Rand, Rng};
+use std::result;
+use std::slice::Items;
 
+use audit;
 use bytes::{B416, Bytes, EdPoint, MontPoint, Scalar, Uniformity};
+use choice;
+use choice::{Choice, ConditionallySelectable};
+use constants;
+use digest::Digest;
+use error;
+use error::{Error, Malformed, NonCanonical, OffCurve, SmallOrder};
 use fe::FieldElem;
+use kdf;
+use sc::{ScalarElem, ScalarElemMul};
+use utils;
 
 
 static BASEX: [u8, ..52] = [
@@ -52,24 +66,6 @@ static B1: [u8, ..52] = [
     0, 0, 0, 0, 0, 0, 0, 0,
     0, 0, 0, 0];
 
-static BMINUS1: [u8, ..52] = [
-    0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-    0xff, 0xff, 0xff, 0x3f];
-
-static EDD: [u8, ..52] = [
-    0x21, 0x0e, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0];
-
 static ELLIGATORA: [u8, ..52] = [
     0xcd, 0xf1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
@@ -136,7 +132,25 @@ impl GroupElem {
          }
     }
 
-    /// Return the base point.
+    /// Return a new uniformly random point of the subgroup generated by the
+    /// base point. Use urandom as PRNG.
+    pub fn new_rand() -> GroupElem {
+        let rng = &mut utils::urandom_rng();
+        Rand::rand(rng)
+    }
+
+    /// Return the identity point, same as `neutral()`.
+    pub fn identity() -> GroupElem {
+        GroupElem::neutral()
+    }
+
+    /// Return a true choice iff this point is the identity point. Always
+    /// executes the same operations.
+    pub fn is_identity(&self) -> Choice {
+        self.x.is_zero() & self.y.ct_eq(&self.z)
+    }
+
+    /// Return the standard Curve41417 base point.
     pub fn base() -> GroupElem {
         let bx: B416 = Bytes::from_bytes(BASEX).unwrap();
         let by: B416 = Bytes::from_bytes(BASEY).unwrap();
@@ -156,11 +170,11 @@ impl GroupElem {
     }
 
     fn bminus1() -> B416 {
-        Bytes::from_bytes(BMINUS1).unwrap()
+        Bytes::from_bytes(constants::P_MINUS_ONE).unwrap()
     }
 
     fn edd() -> FieldElem {
-        let bedd: B416 = Bytes::from_bytes(EDD).unwrap();
+        let bedd: B416 = Bytes::from_bytes(constants::EDWARDS_D).unwrap();
         FieldElem::unpack(&bedd)
     }
 
@@ -185,6 +199,21 @@ impl GroupElem {
         self.t = self.x * self.y;
     }
 
+    // Return the Edwards point corresponding to the Montgomery point of
+    // projective coordinates (x:y:z), using x_ed = u/v, y_ed = (u-1)/(u+1).
+    #[doc(hidden)]
+    pub fn from_mont_projective(x: &FieldElem, y: &FieldElem,
+                                z: &FieldElem) -> GroupElem {
+        let xpz = *x + *z;
+        let xmz = *x - *z;
+        GroupElem {
+            x: *x * xpz,
+            y: *y * xmz,
+            z: *y * xpz,
+            t: *x * xmz
+        }
+    }
+
     /// Unpack a Curve41417 point in Edwards representation from its
     /// `bytes` representation. `bytes` must hold a packed point wrapped
     /// in `EdPoint`, usually a previous result obtained from `pack()`.
@@ -226,14 +255,53 @@ impl GroupElem {
         }
     }
 
+    /// Decode a point from its 52 bytes encoding (see `pack()`) and reject
+    /// malformed inputs: `Err(InvalidLength)` on wrong length,
+    /// `Err(NonCanonical)` if `y >= P`, if the unused bit `414` is set or
+    /// if the sign bit is set while `x == 0`, `Err(OffCurve)` if no point
+    /// has this `y` coordinate and `Err(SmallOrder)` if the point has a
+    /// small order (including the neutral element).
+    pub fn decode(bytes: &[u8]) -> result::Result<GroupElem, Error> {
+        let p = try!(GroupElem::decode_canonical(bytes));
+        if p.scalar_mult_cofactor().is_identity().unwrap_u8() == 1 {
+            return Err(SmallOrder);
+        }
+        Ok(p)
+    }
+
+    /// Same as `decode()` but points of small order are accepted.
+    pub fn decode_canonical(bytes: &[u8]) -> result::Result<GroupElem,
+                                                              Error> {
+        try!(error::check_length(bytes, 52));
+        let b: B416 = Bytes::from_bytes(bytes).unwrap();
+
+        // Only the sign bit of x may be set on top of the encoding of y.
+        let mut y = b.clone();
+        *y.get_mut(51) &= 0x7f;
+        try!(FieldElem::from_bytes_strict(y.as_bytes()));
+
+        let p = match GroupElem::unpack(&EdPoint(b.clone())) {
+            Some(p) => p,
+            None => return Err(OffCurve)
+        };
+        if p.x() == FieldElem::zero() && (*b.get(51) >> 7) == 1 {
+            return Err(NonCanonical);
+        }
+        Ok(p)
+    }
+
     /// Pack a group elem's coordinate `y` along with a sign bit taken from
     /// its `x` coordinate. This packed point may be unpacked with
     /// `unpack()`.
     pub fn pack(&self) -> EdPoint {
+        self.pack_with_inv(&self.z.inv())
+    }
+
+    // Same as pack() with zi = 1/z.
+    fn pack_with_inv(&self, zi: &FieldElem) -> EdPoint {
         // Pack y
-        let zi = self.z.inv();
-        let tx = self.x * zi;
-        let ty = self.y * zi;
+        let tx = self.x * *zi;
+        let ty = self.y * *zi;
         let mut r = ty.pack();
 
         // Sign(x): same as EdDSA25519
@@ -241,6 +309,77 @@ impl GroupElem {
         EdPoint(r)
     }
 
+    /// Pack all the points of `points`, same as calling `pack()` on each
+    /// of them but the results are converted to affine coordinates with a
+    /// single field inversion shared by all points (see
+    /// `FieldElem::batch_inv()`). This is much faster for packing the
+    /// projective results of many scalar multiplications, e.g. in batch
+    /// Diffie-Hellman or multi-party protocols.
+    pub fn batch_pack(points: &[GroupElem]) -> Vec<EdPoint> {
+        let zs: Vec<FieldElem> = points.iter().map(|p| p.z.clone()).collect();
+        let zis = FieldElem::batch_inv(zs.as_slice());
+        points.iter().zip(zis.iter()).map(|(p, zi)| {
+            p.pack_with_inv(zi)
+        }).collect()
+    }
+
+    /// Return a scalar bound to the canonical encoding of this point, to
+    /// the domain separation label `domain` and to `data`, computed as
+    /// `kdf::derive_scalar(digest, domain, [pack(self), data[0], ...])`:
+    /// the domain and each part are length-prefixed, so distinct domains
+    /// or lists of parts never hash the same input. Use a distinct domain
+    /// for each protocol and each challenge of a protocol; the challenges
+    /// of `sign` are derived this way.
+    pub fn hash_with_domain<D: Digest>(&self, digest: &mut D, domain: &[u8],
+                                       data: &[&[u8]]) -> ScalarElem {
+        GroupElem::hash_encoded_with_domain(digest, domain, &self.pack(),
+                                            data)
+    }
+
+    /// Same as `hash_with_domain()` for the point encoded by `p`, hashed
+    /// as is without being unpacked, e.g. a point received by a verifier.
+    /// Both agree iff `p` is the canonical encoding of the point.
+    pub fn hash_encoded_with_domain<D: Digest>(digest: &mut D, domain: &[u8],
+                                               p: &EdPoint, data: &[&[u8]])
+                                               -> ScalarElem {
+        let mut parts: Vec<&[u8]> = vec![p.get_ref().as_bytes()];
+        parts.push_all(data);
+        kdf::derive_scalar(digest, domain, parts.as_slice())
+    }
+
+    // Return the points of `points` in affine coordinates (z = 1), with a
+    // single field inversion shared by all points.
+    fn batch_affine(points: &[GroupElem]) -> Vec<GroupElem> {
+        let zs: Vec<FieldElem> = points.iter().map(|p| p.z.clone()).collect();
+        let zis = FieldElem::batch_inv(zs.as_slice());
+        points.iter().zip(zis.iter()).map(|(p, zi)| {
+            let x = p.x * *zi;
+            let y = p.y * *zi;
+            GroupElem {
+                t: x * y,
+                x: x,
+                y: y,
+                z: FieldElem::one()
+            }
+        }).collect()
+    }
+
+    /// Return the affine x-coordinate of this point.
+    pub fn x(&self) -> FieldElem {
+        self.x * self.z.inv()
+    }
+
+    /// Return the affine y-coordinate of this point.
+    pub fn y(&self) -> FieldElem {
+        self.y * self.z.inv()
+    }
+
+    /// Return the sign of the affine x-coordinate i.e. its parity bit, as
+    /// encoded in the top bit of packed points.
+    pub fn sign_of_x(&self) -> u8 {
+        self.x().parity_bit()
+    }
+
     fn cleanup(&mut self) {
         // Nothing to do.
     }
@@ -252,6 +391,34 @@ impl GroupElem {
         self.t.cswap(cond, &mut other.t);
     }
 
+    /// Return `table[index]`, or the neutral point if `index` is out of
+    /// bounds. Every entry of the table is read and conditionally swapped
+    /// in with masks, the accessed memory and the executed operations
+    /// don't depend on `index`: use this for looking up secret-indexed
+    /// entries of precomputed tables instead of `table[index]`.
+    pub fn select(table: &[GroupElem], index: uint) -> GroupElem {
+        choice::lookup(table, index, GroupElem::neutral())
+    }
+
+    // Return the same point with randomized projective coordinates
+    // (rX:rY:rZ:rT) for a random r when built with the hardened backend,
+    // return a copy of self otherwise.
+    #[cfg(not(feature = "backend_hardened"))]
+    fn randomize(&self) -> GroupElem {
+        self.clone()
+    }
+
+    #[cfg(feature = "backend_hardened")]
+    fn randomize(&self) -> GroupElem {
+        let r = FieldElem::new_rand();
+        GroupElem {
+            x: self.x * r,
+            y: self.y * r,
+            z: self.z * r,
+            t: self.t * r
+        }
+    }
+
     /// Return point `q` such that `q=n.self` where `n` is the scalar value
     /// applied to the point `self`. Note that the value of `n` is not
     /// clamped by this method before the scalar multiplication is
@@ -263,14 +430,16 @@ impl GroupElem {
     /// multiplication. Whereas a `ScalarElem` may automatically be reduced
     /// `mod L` before any scalar multiplication takes place.
     pub fn scalar_mult(&self, n: &Scalar) -> GroupElem {
-        let mut p = self.clone();
+        let mut p = self.randomize();
         let mut q = GroupElem::neutral();
 
         for i in range(0u, 415).rev() {
+            audit::access("ed::scalar_mult",
+                          n.get_ref().as_bytes().slice(i / 8, i / 8 + 1));
             let c = ((*n.get(i / 8) >> (i & 7)) & 1) as i64;
             q.cswap(c, &mut p);
             p = p + q;
-            q = q + q;
+            q = q.dbl();
             q.cswap(c, &mut p);
         }
         q
@@ -279,12 +448,64 @@ impl GroupElem {
     /// Return point `q` such that `q=8.self` where `8` is curve's cofactor
     /// applied to this point's instance.
     pub fn scalar_mult_cofactor(&self) -> GroupElem {
-        let mut q = *self + *self;
-        q = q + q;
-        q = q + q;
+        self.dbl().dbl().dbl()
+    }
+
+    /// Return `2.self`, faster than `self + self`.
+    pub fn dbl(&self) -> GroupElem {
+        // 2008/522.pdf section 3.3 (a=1)
+        let a = self.x.square();
+        let b = self.y.square();
+        let mut c = self.z.square();
+        c = c + c;
+        let mut e = (self.x + self.y).square();
+        e = e - a;
+        e = e - b;
+        let g = a + b;
+        let f = g - c;
+        let h = a - b;
+        GroupElem {
+            x: e * f,
+            y: g * h,
+            z: f * g,
+            t: e * h
+        }
+    }
+
+    /// Return `3.self`.
+    pub fn tpl(&self) -> GroupElem {
+        self.dbl() + *self
+    }
+
+    /// Return `n.self` for a small multiplier `n`, e.g. the cofactor or a
+    /// NAF digit, with a few doublings and additions instead of a full
+    /// scalar multiplication. The executed operations depend on `n`, only
+    /// use it with public multipliers.
+    pub fn mul_small(&self, n: u8) -> GroupElem {
+        let mut q = GroupElem::neutral();
+        for i in range(0u, 8 - n.leading_zeros() as uint).rev() {
+            q = q.dbl();
+            if (n >> i) & 1 == 1 {
+                q = q + *self;
+            }
+        }
         q
     }
 
+    /// Return a true choice iff this point is in the subgroup of order `L`
+    /// generated by the base point, i.e. iff `L.self` is the neutral
+    /// element (no small-order component).
+    pub fn is_torsion_free(&self) -> Choice {
+        self.scalar_mult(&Scalar(ScalarElem::order())).is_identity()
+    }
+
+    /// Return a true choice iff `8.self == 8.other`, that is iff both
+    /// points only differ by a small-order (torsion) component. Use `==`
+    /// for strict equality. Always executes the same operations.
+    pub fn cofactor_equal(&self, other: &GroupElem) -> Choice {
+        (*self - *other).scalar_mult_cofactor().is_identity()
+    }
+
     /// Return point `q` such that `q=n.BP` where `n` is a scalar value applied
     /// to the base point `BP`. Note that `n` is not clamped by this method
     /// before the multiplication. Calling this method is equivalent to calling
@@ -302,10 +523,60 @@ impl GroupElem {
         p1.scalar_mult(n1) + p2.scalar_mult(n2)
     }
 
+    /// Return point `q` such that `q=n1.p1+n2.p2+...` where `ns=[n1, n2,
+    /// ...]` are scalar values and `ps=[p1, p2, ...]` are group elements.
+    /// Note that scalars are not clamped by this method. Fails if `ns` and
+    /// `ps` don't have the same length.
+    pub fn multiscalar_mult(ns: &[Scalar], ps: &[GroupElem]) -> GroupElem {
+        assert!(ns.len() == ps.len());
+        let mut q = GroupElem::neutral();
+        for (n, p) in ns.iter().zip(ps.iter()) {
+            q = q + p.scalar_mult(n);
+        }
+        q
+    }
+
+    /// Same as `multiscalar_mult()` but variable-time: the scalar
+    /// multiplications are interleaved (Straus) with a table of 16
+    /// multiples of each point, sharing their 415 doublings. The executed
+    /// operations and the accessed memory depend on the scalars, only use
+    /// it with public values, e.g. for verifying signatures.
+    pub fn multiscalar_mult_vartime(ns: &[Scalar], ps: &[GroupElem])
+                                    -> GroupElem {
+        assert!(ns.len() == ps.len());
+        let tables: Vec<PointTable> = ps.iter().map(|p| {
+            PointTable::with_window(p, 4).unwrap()
+        }).collect();
+        let mut q = GroupElem::neutral();
+
+        for k in range(0u, 104).rev() {
+            for _ in range(0u, 4) {
+                q = q.dbl();
+            }
+            for (n, t) in ns.iter().zip(tables.iter()) {
+                let mut nibble = (*n.get(k / 2) >> (4 * (k & 1))) & 0xf;
+                if k == 103 {
+                    // Bit 415 is dropped like in scalar_mult().
+                    nibble &= 0x7;
+                }
+                if nibble != 0 {
+                    q = q + *t.points.get(nibble as uint);
+                }
+            }
+        }
+        q
+    }
+
     /// Generate keypair `(pk, sk)` such that `pk=sk.BP` with secret scalar
     /// `sk` appropriately clamped and `pk` the resulting public key.
     pub fn keypair() -> (GroupElem, Scalar) {
-        let mut sk: B416 = Bytes::new_rand();
+        GroupElem::keypair_from_rng(&mut utils::urandom_rng())
+    }
+
+    /// Same as `keypair()` but the secret scalar is generated from `rng`,
+    /// see `mont::keypair_from_rng()`.
+    pub fn keypair_from_rng<R: Rng>(rng: &mut R) -> (GroupElem, Scalar) {
+        let mut sk: B416 = Rand::rand(rng);
         sk.clamp_41417();
         let sk_val = Scalar(sk);
         let pk = GroupElem::scalar_mult_base(&sk_val);
@@ -325,6 +596,43 @@ impl GroupElem {
         MontPoint(num.pack())
     }
 
+    /// Convert all the points of `points` to Montgomery's x-coordinate,
+    /// same as calling `to_mont()` on each of them but with a single field
+    /// inversion shared by all points, see `batch_pack()`.
+    pub fn batch_to_mont(points: &[GroupElem]) -> Vec<MontPoint> {
+        // u = (1 + y) / (1 - y) = (Z + Y) / (Z - Y). The denominator is
+        // zero for the neutral point, for which to_mont() returns 0, it
+        // is replaced by 1 and its numerator by 0 as a single zero would
+        // zero all the inverses.
+        let mut nums = Vec::with_capacity(points.len());
+        let mut dens = Vec::with_capacity(points.len());
+        for p in points.iter() {
+            let mut num = p.z + p.y;
+            let mut den = p.z - p.y;
+            let c = (den == FieldElem::zero()) as i64;
+            num.cswap(c, &mut FieldElem::zero());
+            den.cswap(c, &mut FieldElem::one());
+            nums.push(num);
+            dens.push(den);
+        }
+        let invs = FieldElem::batch_inv(dens.as_slice());
+        nums.iter().zip(invs.iter()).map(|(n, i)| {
+            MontPoint((*n * *i).pack())
+        }).collect()
+    }
+
+    /// Return the Edwards point of Montgomery x-coordinate `u` whose
+    /// affine x-coordinate has the sign `sign_of_x` (`0` or `1`, see
+    /// `sign_of_x()`), the inverse of `to_mont()` up to this sign:
+    /// `y = (u - 1) / (u + 1)`. Return `None` if no such point exists.
+    pub fn from_mont(u: &MontPoint, sign_of_x: u8) -> Option<GroupElem> {
+        let uf = FieldElem::unpack(u.get_ref());
+        let y = (uf - FieldElem::one()) * (uf + FieldElem::one()).inv();
+        let mut b = y.pack();
+        *b.get_mut(51) |= sign_of_x << 7;
+        GroupElem::unpack(&EdPoint(b))
+    }
+
     // FIXME: would there be a risk of lack of uniformity of the distribution
     // mod L if the input string r was a 52 bytes random string? At least as
     // specified in ed25519-20110926.pdf a 64 bytes input should provide
@@ -438,13 +746,43 @@ impl Neg<GroupElem> for GroupElem {
     }
 }
 
-impl Mul<Scalar, GroupElem> for GroupElem {
-    /// Multiply point `self` with scalar value `other`. Note that
-    /// currently it is not possible to symmetrically overload the `*`
-    /// operator of a scalar value to support `scalar * point` when
-    /// `point` is a `GroupElem`.
-    fn mul(&self, other: &Scalar) -> GroupElem {
-        self.scalar_mult(other)
+impl Sub<GroupElem, GroupElem> for GroupElem {
+    /// Substract points.
+    fn sub(&self, other: &GroupElem) -> GroupElem {
+        *self + -*other
+    }
+}
+
+#[doc(hidden)]
+pub trait GroupElemMul {
+    fn mul_group_elem(&self, lhs: &GroupElem) -> GroupElem;
+}
+
+impl GroupElemMul for Scalar {
+    fn mul_group_elem(&self, lhs: &GroupElem) -> GroupElem {
+        lhs.scalar_mult(self)
+    }
+}
+
+impl GroupElemMul for ScalarElem {
+    fn mul_group_elem(&self, lhs: &GroupElem) -> GroupElem {
+        lhs.scalar_mult(&self.pack())
+    }
+}
+
+impl<R: GroupElemMul> Mul<R, GroupElem> for GroupElem {
+    /// Multiply point `self` with scalar value `other`, `other` may either
+    /// be a `Scalar` or a `ScalarElem`. In the latter case `other` is
+    /// reduced `mod L` before the multiplication.
+    fn mul(&self, other: &R) -> GroupElem {
+        other.mul_group_elem(self)
+    }
+}
+
+impl ScalarElemMul<GroupElem> for GroupElem {
+    /// Multiply scalar with point, see `GroupElem`'s `Mul`.
+    fn mul_scalar_elem(&self, lhs: &ScalarElem) -> GroupElem {
+        self.scalar_mult(&lhs.pack())
     }
 }
 
@@ -466,6 +804,17 @@ impl Clone for GroupElem {
     }
 }
 
+impl Rand for GroupElem {
+    /// Generate a random point `n.BP` where `n` is a random scalar value
+    /// in `[0, L-1]` and `BP` the base point. Be sure to use a secure PRNG
+    /// when calling this method. For instance `GroupElem::new_rand()` uses
+    /// urandom.
+    fn rand<R: Rng>(rng: &mut R) -> GroupElem {
+        let n: ScalarElem = Rand::rand(rng);
+        GroupElem::base() * n
+    }
+}
+
 impl Default for GroupElem {
     /// By default return the neutral point.
     fn default() -> GroupElem {
@@ -486,6 +835,12 @@ impl ToHex for GroupElem {
     }
 }
 
+impl ConditionallySelectable for GroupElem {
+    fn conditional_swap(&mut self, other: &mut GroupElem, choice: Choice) {
+        self.cswap(choice.unwrap_u8() as i64, other);
+    }
+}
+
 impl PartialEq for GroupElem {
     /// Constant-time points equality comparison.
     fn eq(&self, other: &GroupElem) -> bool {
@@ -497,15 +852,424 @@ impl Eq for GroupElem {
 }
 
 
+/// Default window width of `PointTable`, in bits.
+#[cfg(not(feature = "small"))]
+pub static DEFAULT_WINDOW: uint = 4;
+/// Default window width of `PointTable`, in bits. The `small` build
+/// profile builds the smallest tables by default: 2 points, no
+/// precomputation beyond the point itself.
+#[cfg(feature = "small")]
+pub static DEFAULT_WINDOW: uint = 1;
+
+/// Maximum window width of `PointTable`, in bits.
+#[cfg(not(feature = "small"))]
+pub static MAX_WINDOW: uint = 8;
+/// Maximum window width of `PointTable`, in bits. The `small` build
+/// profile limits tables to 16 points of about 0.8KB, 13KB. Elements and
+/// arithmetic temporaries hold their limbs inline whatever the profile,
+/// the profile also allocates byte buffers (`B416`, ...) on the standard
+/// heap instead of guarded pages, see `sbuf::DefaultAllocator`.
+#[cfg(feature = "small")]
+pub static MAX_WINDOW: uint = 4;
+
+/// Precomputed multiples of a point
+///
+/// Table of the `2^w` multiples `0.p, 1.p, ..., (2^w-1).p` of a point `p`,
+/// used for computing scalar multiplications of `p` with about `415 / w`
+/// additions instead of `415`. The window width `w` trades memory (`2^w`
+/// points, 4 field elements each) for speed, from `1` (no precomputation)
+/// to `MAX_WINDOW`. Table entries are looked up by scanning the whole
+/// table, the accessed memory doesn't depend on the scalar.
+///
+/// Tables are deterministic: `to_bytes()` and `to_rust_source()` export
+/// them for embedding or auditing, `verify()` checks a table loaded with
+/// `from_bytes()`. Entries are stored in affine coordinates and exposed
+/// read-only by `multiples()`.
+#[deriving(Clone)]
+pub struct PointTable {
+    window: uint,
+    points: Vec<GroupElem>
+}
+
+impl PointTable {
+    /// Return the table of `p` with the default window width
+    /// `DEFAULT_WINDOW`.
+    pub fn new(p: &GroupElem) -> PointTable {
+        PointTable::with_window(p, DEFAULT_WINDOW).unwrap()
+    }
+
+    /// Return the table of `p` with a window of `window` bits, or `None`
+    /// if `window` is not in `[1, MAX_WINDOW]`.
+    pub fn with_window(p: &GroupElem, window: uint) -> Option<PointTable> {
+        if window < 1 || window > MAX_WINDOW {
+            return None;
+        }
+        let size = 1u << window;
+        let mut points = Vec::with_capacity(size);
+        points.push(GroupElem::neutral());
+        for i in range(1u, size) {
+            let q = *points.get(i - 1) + *p;
+            points.push(q);
+        }
+        Some(PointTable {
+            window: window,
+            points: GroupElem::batch_affine(points.as_slice())
+        })
+    }
+
+    /// Return the window width of this table, in bits.
+    pub fn window(&self) -> uint {
+        self.window
+    }
+
+    /// Return the entry `index.p` of this table, see `GroupElem::select()`.
+    pub fn lookup(&self, index: uint) -> GroupElem {
+        GroupElem::select(self.points.as_slice(), index)
+    }
+
+    /// Return an iterator over the non-neutral entries of this table, the
+    /// multiples `1.p, 2.p, ..., (2^w-1).p` of its point `p` in affine
+    /// coordinates. E.g. the multiples of `PointTable::base(w).unwrap()`
+    /// are `B, 2.B, 3.B, ...`, for building other precomputations without
+    /// recomputing them.
+    pub fn multiples<'a>(&'a self) -> Skip<Items<'a, GroupElem>> {
+        self.points.iter().skip(1)
+    }
+
+    /// Return point `q` such that `q=n.p` where `p` is the point of this
+    /// table. Same as `p.scalar_mult(n)`, scalar `n` is not clamped.
+    pub fn scalar_mult(&self, n: &Scalar) -> GroupElem {
+        let w = self.window;
+        let mut q = GroupElem::neutral();
+
+        for k in range(0u, (415 + w - 1) / w).rev() {
+            for _ in range(0u, w) {
+                q = q.dbl();
+            }
+            let mut index = 0u;
+            for b in range(0u, w).rev() {
+                let i = k * w + b;
+                let bit = match i < 415 {
+                    true => (*n.get(i / 8) >> (i & 7)) & 1,
+                    false => 0
+                };
+                index = (index << 1) | bit as uint;
+            }
+            q = q + self.lookup(index);
+        }
+        q
+    }
+
+    /// Return the table of the base point with a window of `window` bits,
+    /// see `with_window()`.
+    pub fn base(window: uint) -> Option<PointTable> {
+        PointTable::with_window(&GroupElem::base(), window)
+    }
+
+    /// Return the `52 * 2^w` bytes concatenation of the packed entries of
+    /// this table (see `GroupElem::pack()`), from `0.p` to `(2^w-1).p`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut r = Vec::with_capacity(52 * self.points.len());
+        for p in GroupElem::batch_pack(self.points.as_slice()).iter() {
+            r.push_all(p.get_ref().as_bytes());
+        }
+        r
+    }
+
+    /// Load a table of window `window` from its encoding `bytes` returned
+    /// by `to_bytes()`. Return `Err(InvalidLength(52 * 2^window, len))` if
+    /// `bytes` doesn't have the expected length, or the error returned by
+    /// `GroupElem::decode_canonical()` for the first invalid entry. The
+    /// entries are not checked to be multiples of a same point, see
+    /// `verify()`. Return `Err(Malformed)` if `window` is not in
+    /// `[1, MAX_WINDOW]`.
+    pub fn from_bytes(window: uint, bytes: &[u8])
+                      -> result::Result<PointTable, Error> {
+        if window < 1 || window > MAX_WINDOW {
+            return Err(Malformed);
+        }
+        try!(error::check_length(bytes, 52 << window));
+        let mut points = Vec::with_capacity(1u << window);
+        for b in bytes.chunks(52) {
+            points.push(try!(GroupElem::decode_canonical(b)));
+        }
+        Ok(PointTable {
+            window: window,
+            points: points
+        })
+    }
+
+    /// Return the Rust source of a static array named `name` holding the
+    /// encoding `to_bytes()` of this table.
+    pub fn to_rust_source(&self, name: &str) -> String {
+        let bytes = self.to_bytes();
+        let mut s = format!("// Multiples 0.p to {}.p of p = {}, window {}.\n",
+                            self.points.len() - 1, self.points.get(1),
+                            self.window);
+        s.push_str(format!("static {}: [u8, ..{}] = [", name,
+                           bytes.len()).as_slice());
+        for (i, b) in bytes.iter().enumerate() {
+            if i % 8 == 0 {
+                s.push_str("\n   ");
+            }
+            s.push_str(format!(" 0x{:02x}", *b).as_slice());
+            if i + 1 < bytes.len() {
+                s.push_str(",");
+            }
+        }
+        s.push_str("];\n");
+        s
+    }
+
+    /// Return `true` iff the entries of this table are the multiples
+    /// `0.p, 1.p, ..., (2^w-1).p` of `p`. Each entry is checked against
+    /// both the sum of the previous one with `p` and a scalar
+    /// multiplication of `p`.
+    pub fn verify(&self, p: &GroupElem) -> bool {
+        let mut ok = self.points.len() == 1u << self.window;
+        let mut acc = GroupElem::neutral();
+        for (i, q) in self.points.iter().enumerate() {
+            let mut n: B416 = Bytes::new_zero();
+            *n.get_mut(0) = (i & 0xff) as u8;
+            *n.get_mut(1) = (i >> 8) as u8;
+            ok = ok && *q == acc && *q == p.scalar_mult(&Scalar(n));
+            acc = acc + *p;
+        }
+        ok
+    }
+}
+
+/// Exponent blinding pair
+///
+/// Pair `(r, 1/r mod L)` of a random non-zero scalar and its inverse, for
+/// computing a secret scalar multiplication `n.p` as `(1/r).((n.r).p)`:
+/// the multiplications handle the blinded scalar `n.r mod L` and `1/r`
+/// instead of `n`. The pair is replaced by a fresh random one after each
+/// use, so that successive multiplications with the same secret scalar
+/// use independent blinds: blinds derived from each other (e.g. `r` and
+/// `r^2`) would let `n` be recovered from two blinded scalars. Blinded
+/// results are only correct for points of order `L` (e.g. public keys
+/// checked with `is_torsion_free()`).
+pub struct BlindingPair {
+    r: ScalarElem,
+    r_inv: ScalarElem
+}
+
+impl BlindingPair {
+    /// Return a new pair with a random `r` generated from `rng`.
+    pub fn new<R: Rng>(rng: &mut R) -> BlindingPair {
+        loop {
+            let r: ScalarElem = Rand::rand(rng);
+            if r.is_zero().unwrap_u8() == 0 {
+                return BlindingPair {
+                    r_inv: r.inv(),
+                    r: r
+                };
+            }
+        }
+    }
+
+    /// Return the blinded scalar `n.r mod L`.
+    pub fn blind(&self, n: &ScalarElem) -> ScalarElem {
+        *n * self.r
+    }
+
+    /// Return `(1/r).p`, removing the blinding of a point `p` computed
+    /// with a blinded scalar.
+    pub fn unblind(&self, p: &GroupElem) -> GroupElem {
+        *p * self.r_inv
+    }
+
+    /// Replace this pair by a new random pair, see `refresh_from_rng()`.
+    pub fn refresh(&mut self) {
+        self.refresh_from_rng(&mut utils::urandom_rng());
+    }
+
+    /// Replace this pair by a new pair with a random `r` generated from
+    /// `rng`, independent of the previous one.
+    pub fn refresh_from_rng<R: Rng>(&mut self, rng: &mut R) {
+        *self = BlindingPair::new(rng);
+    }
+
+    /// Return `n.p` computed with blinding, then refresh this pair. `p`
+    /// must be of order `L`.
+    pub fn scalar_mult(&mut self, n: &ScalarElem, p: &GroupElem)
+                       -> GroupElem {
+        let q = self.unblind(&(*p * self.blind(n)));
+        self.refresh();
+        q
+    }
+
+    /// Same as `scalar_mult()` for the base point.
+    pub fn scalar_mult_base(&mut self, n: &ScalarElem) -> GroupElem {
+        self.scalar_mult(n, &GroupElem::base())
+    }
+}
+
+/// Check the consistency of the built-in base point constants: the base
+/// point must be a point of the curve of order `L` whose projective
+/// coordinates `(X:Y:Z:T)` verify `X.Y = Z.T`, and its table of default
+/// window (`PointTable::base(DEFAULT_WINDOW)`) must verify. Return `true`
+/// iff all the checks passed.
+pub fn verify_table() -> bool {
+    let bp = GroupElem::base();
+    let on_curve = match GroupElem::unpack(&bp.pack()) {
+        Some(p) => p == bp,
+        None => false
+    };
+    on_curve && bp.x * bp.y == bp.z * bp.t &&
+        bp.is_identity().unwrap_u8() == 0 &&
+        bp.is_torsion_free().unwrap_u8() == 1 &&
+        PointTable::base(DEFAULT_WINDOW).unwrap().verify(&bp)
+}
+
+
 #[cfg(test)]
 mod tests {
     extern crate test;
     use self::test::Bencher;
 
-    use bytes::{B416, B512, B832, Bytes, Scalar};
+    use std::mem;
+
+    use bytes::{B416, B512, B832, Bytes, EdPoint, Scalar};
+    use constants;
+    use digest::sha256::Sha256;
     use ed;
+    use error::{InvalidLength, Malformed, NonCanonical, OffCurve};
+    use error::SmallOrder;
+    use fe::FieldElem;
+    use kdf;
     use mont;
+    use sc::ScalarElem;
+    use utils;
+
+
+    // Footprint of the elements and of the largest table: 26 inline limbs
+    // per field element, plus a drop flag.
+    #[test]
+    fn test_footprint() {
+        let fe = mem::size_of::<FieldElem>();
+        assert!(fe >= 208 && fe <= 216);
+        assert!(mem::size_of::<ed::GroupElem>() <= 4 * 216);
+        let t = ed::PointTable::base(ed::MAX_WINDOW).unwrap();
+        let bytes = (t.multiples().count() + 1) *
+            mem::size_of::<ed::GroupElem>();
+        if cfg!(feature = "small") {
+            assert!(bytes <= 16 * 4 * 216);
+        }
+    }
+
+    #[test]
+    fn test_point_table() {
+        let (p, n) = ed::GroupElem::keypair();
+        let q = p.scalar_mult(&n);
+        assert!(ed::DEFAULT_WINDOW <= ed::MAX_WINDOW);
+        for w in range(1u, ed::MAX_WINDOW + 1) {
+            let t = ed::PointTable::with_window(&p, w).unwrap();
+            assert!(t.window() == w);
+            assert!(t.scalar_mult(&n) == q);
+        }
+        let t = ed::PointTable::new(&ed::GroupElem::base());
+        assert!(t.window() == ed::DEFAULT_WINDOW);
+        assert!(t.lookup(1) == ed::GroupElem::base());
+        assert!(t.lookup(0) == ed::GroupElem::neutral());
+        assert!(t.verify(&ed::GroupElem::base()));
+        assert!(!t.verify(&p));
+        assert!(t.scalar_mult(&n) == ed::GroupElem::scalar_mult_base(&n));
+        let zero: B416 = Bytes::new_zero();
+        assert!(t.scalar_mult(&Scalar(zero)).is_identity().unwrap_u8() == 1);
+        assert!(ed::PointTable::with_window(&p, 0).is_none());
+        assert!(ed::PointTable::base(ed::MAX_WINDOW + 1).is_none());
+    }
+
+    #[test]
+    fn test_table_multiples() {
+        let bp = ed::GroupElem::base();
+        let t = ed::PointTable::base(3).unwrap();
+        assert!(t.multiples().count() == 7);
+        let mut acc = ed::GroupElem::neutral();
+        for q in t.multiples() {
+            acc = acc + bp;
+            assert!(*q == acc);
+            assert!(q.z == FieldElem::one() && q.t == q.x * q.y);
+        }
+        let t = ed::PointTable::from_bytes(3, t.to_bytes().as_slice())
+            .unwrap();
+        assert!(t.multiples().last().unwrap() == &acc);
+    }
+
+    #[test]
+    fn test_blinding_pair() {
+        let mut rng = utils::urandom_rng();
+        let mut b = ed::BlindingPair::new(&mut rng);
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        assert!(b.r * b.r_inv == one);
+        let n = ScalarElem::new_rand();
+        let p = ed::GroupElem::new_rand();
+        let r = b.r.clone();
+        let b1 = b.blind(&n);
+
+        assert!(b.scalar_mult(&n, &p) == p * n);
+        assert!(b.r != r && b.r * b.r_inv == one);
+        // Consecutive blinds are not powers of each other, n can't be
+        // recovered as b1^k / b2.
+        let b2 = b.blind(&n);
+        for k in range(2u8, 9) {
+            assert!(b.r != r.pow_vartime([k]));
+            assert!(b1.pow_vartime([k]) != n.pow_vartime([k - 1]) * b2);
+        }
+        assert!(b.unblind(&(p * b.blind(&n))) == p * n);
+        assert!(b.scalar_mult_base(&n) == ed::GroupElem::base() * n);
+    }
+
+    #[test]
+    fn test_select() {
+        let ps = Vec::from_fn(5, |_| ed::GroupElem::new_rand());
+        for i in range(0u, 5) {
+            assert!(ed::GroupElem::select(ps.as_slice(), i) == *ps.get(i));
+        }
+        assert!(ed::GroupElem::select(ps.as_slice(), 5) ==
+                ed::GroupElem::neutral());
+        assert!(ed::GroupElem::select(ps.as_slice(), -1u) ==
+                ed::GroupElem::neutral());
+        assert!(ed::GroupElem::select([], 0) == ed::GroupElem::neutral());
+    }
+
+    #[test]
+    fn test_table_export() {
+        assert!(ed::verify_table());
 
+        let bp = ed::GroupElem::base();
+        let t = ed::PointTable::base(2).unwrap();
+        let b = t.to_bytes();
+        assert!(b.len() == 52 * 4);
+        assert!(b.slice(52, 104) == bp.pack().get_ref().as_bytes());
+        assert!(ed::PointTable::base(2).unwrap().to_bytes() == b);
+
+        let u = ed::PointTable::from_bytes(2, b.as_slice()).unwrap();
+        assert!(u.window() == 2 && u.verify(&bp));
+        assert!(u.to_bytes() == b);
+        assert!(ed::PointTable::from_bytes(1, b.as_slice()).err() ==
+                Some(InvalidLength(104, 208)));
+        assert!(ed::PointTable::from_bytes(0, []).err() == Some(Malformed));
+        assert!(ed::PointTable::from_bytes(ed::MAX_WINDOW + 1, b.as_slice())
+                .err() == Some(Malformed));
+
+        // Swapped entries decode but don't verify.
+        let mut s = Vec::from_slice(b.slice_to(52));
+        s.push_all(b.slice(104, 156));
+        s.push_all(b.slice(52, 104));
+        s.push_all(b.slice_from(156));
+        let v = ed::PointTable::from_bytes(2, s.as_slice()).unwrap();
+        assert!(!v.verify(&bp));
+
+        let src = t.to_rust_source("BASE_TABLE");
+        assert!(src.as_slice().contains("static BASE_TABLE: [u8, ..208] = ["));
+        assert!(src.as_slice().contains(format!(
+            " 0x{:02x}, 0x{:02x},", *b.get(0), *b.get(1)).as_slice()));
+        assert!(src.as_slice().ends_with(
+            format!(" 0x{:02x}];\n", *b.get(207)).as_slice()));
+    }
 
     #[test]
     fn test_dh_rand() {
@@ -518,6 +1282,56 @@ mod tests {
         assert!(ssk1 == ssk2);
     }
 
+    #[test]
+    fn test_multiscalar_mult() {
+        let (p1, n1) = ed::GroupElem::keypair();
+        let (p2, n2) = ed::GroupElem::keypair();
+        let q1 = ed::GroupElem::multiscalar_mult([n1.clone(), n2.clone()],
+                                                 [p1.clone(), p2.clone()]);
+        let q2 = ed::GroupElem::double_scalar_mult(&n1, &p1, &n2, &p2);
+        assert!(q1 == q2);
+        let q3 = ed::GroupElem::multiscalar_mult_vartime([n1, n2], [p1, p2]);
+        assert!(q1 == q3);
+        assert!(ed::GroupElem::multiscalar_mult_vartime([], []) ==
+                ed::GroupElem::neutral());
+    }
+
+    #[test]
+    fn test_randomize() {
+        let (p, _) = ed::GroupElem::keypair();
+        let q = p.randomize();
+        assert!(p == q);
+        assert!(p.pack() == q.pack());
+        assert!(p.to_mont() == q.to_mont());
+    }
+
+    #[test]
+    fn test_decode() {
+        let (p, _) = ed::GroupElem::keypair();
+        let b = p.pack();
+        assert!(ed::GroupElem::decode(b.get_ref().as_bytes()).unwrap() == p);
+        assert!(ed::GroupElem::decode(b.get_ref().as_bytes().slice_to(8)) ==
+                Err(InvalidLength(52, 8)));
+
+        let mut nc = b.get_ref().clone();
+        *nc.get_mut(51) |= 0x40;
+        assert!(ed::GroupElem::decode(nc.as_bytes()) == Err(NonCanonical));
+
+        let n = ed::GroupElem::neutral().pack();
+        assert!(ed::GroupElem::decode(n.get_ref().as_bytes()) ==
+                Err(SmallOrder));
+
+        // Find some y without a matching x.
+        let mut y: B416 = Bytes::new_zero();
+        loop {
+            *y.get_mut(0) += 1;
+            if ed::GroupElem::unpack(&EdPoint(y.clone())).is_none() {
+                break;
+            }
+        }
+        assert!(ed::GroupElem::decode(y.as_bytes()) == Err(OffCurve));
+    }
+
     #[test]
     fn test_ops() {
         let mut b = ed::GroupElem::base();
@@ -533,6 +1347,69 @@ mod tests {
         assert!(ed::GroupElem::base() == b);
     }
 
+    #[test]
+    fn test_operators() {
+        let bp = ed::GroupElem::base();
+        let a = ScalarElem::new_rand();
+        let b = ScalarElem::new_rand();
+
+        let p1 = bp * a;
+        let p2 = a * bp;
+        let p3 = a.pack() * bp;
+        assert!(p1 == p2);
+        assert!(p1 == p3);
+        assert!(p1 == bp * a.pack());
+
+        let q = bp * b;
+        assert!(p1 + q == bp * (a + b));
+        assert!(p1 - q == bp * (a - b));
+        assert!(p1 - p1 == ed::GroupElem::neutral());
+        assert!(-p1 == (-a) * bp);
+    }
+
+    #[test]
+    fn test_identity() {
+        let bp = ed::GroupElem::base();
+        assert!(ed::GroupElem::identity().is_identity().unwrap_u8() == 1);
+        assert!((bp - bp).is_identity().unwrap_u8() == 1);
+        assert!((bp + ed::GroupElem::identity()) == bp);
+        assert!(bp.is_identity().unwrap_u8() == 0);
+
+        let mut n: B416 = Bytes::new_zero();
+        *n.get_mut(0) = 1;
+        let mut q = bp * Scalar(n);
+        assert!(q.is_identity().unwrap_u8() == 0);
+        q = q * ScalarElem::zero();
+        assert!(q.is_identity().unwrap_u8() == 1);
+    }
+
+    #[test]
+    fn test_rand() {
+        let p = ed::GroupElem::new_rand();
+        let q = ed::GroupElem::new_rand();
+        assert!(p != q);
+        assert!(p.is_identity().unwrap_u8() == 0);
+
+        // Points are in the subgroup of order L.
+        assert!((p * Scalar(constants::l())).is_identity().unwrap_u8() == 1);
+    }
+
+    #[test]
+    fn test_cofactor_equal() {
+        let p = ed::GroupElem::new_rand();
+        let q = ed::GroupElem::new_rand();
+
+        // (0, -1) is the point of order 2.
+        let mut t = ed::GroupElem::neutral();
+        t.y = -t.y;
+        let pt = p + t;
+
+        assert!(p != pt);
+        assert!(p.cofactor_equal(&pt).unwrap_u8() == 1);
+        assert!(p.cofactor_equal(&p).unwrap_u8() == 1);
+        assert!(p.cofactor_equal(&q).unwrap_u8() == 0);
+    }
+
     #[test]
     fn test_scalar_cofactor() {
         let n: B416 = Bytes::new_rand();
@@ -553,6 +1430,41 @@ mod tests {
         assert!(s == r);
     }
 
+    #[test]
+    fn test_hash_with_domain() {
+        let mut h = Sha256::new();
+        let p = ed::GroupElem::new_rand();
+        let pb = p.pack();
+        let s = p.hash_with_domain(&mut h, b"domain", [b"data"]);
+        assert!(s == kdf::derive_scalar(&mut h, b"domain",
+                                        [pb.get_ref().as_bytes(), b"data"]));
+        assert!(s == ed::GroupElem::hash_encoded_with_domain(
+            &mut h, b"domain", &pb, [b"data"]));
+        assert!(s != p.hash_with_domain(&mut h, b"other", [b"data"]));
+        assert!(s != p.hash_with_domain(&mut h, b"domain", []));
+        assert!(s != p.hash_with_domain(&mut h, b"domain", [b"da", b"ta"]));
+        assert!(s != p.hash_with_domain(&mut h, b"domain", [b"data", b""]));
+
+        // Projective coordinates don't matter.
+        let q = ed::GroupElem::unpack(&pb).unwrap();
+        assert!(s == q.hash_with_domain(&mut h, b"domain", [b"data"]));
+    }
+
+    #[test]
+    fn test_small_mult() {
+        let q = ed::GroupElem::base() * Scalar(Bytes::new_rand());
+        assert!(q.dbl() == q + q);
+        assert!(q.tpl() == q + q + q);
+        assert!(ed::GroupElem::neutral().dbl().is_identity().unwrap_u8() == 1);
+
+        let mut s = ed::GroupElem::neutral();
+        for n in range(0u, 256) {
+            assert!(q.mul_small(n as u8) == s);
+            s = s + q;
+        }
+        assert!(q.mul_small(8) == q.scalar_mult_cofactor());
+    }
+
     #[test]
     fn test_pack() {
         let bp = ed::GroupElem::base();
@@ -576,6 +1488,23 @@ mod tests {
         assert!(xs == uxs);
     }
 
+    #[test]
+    fn test_coordinates() {
+        let bp = ed::GroupElem::base();
+        let q = bp * Scalar(Bytes::new_rand());
+        let qs = q.pack();
+
+        // Affine coordinates are independent of the projective ones.
+        let uq = ed::GroupElem::unpack(&qs).unwrap();
+        assert!(q.x() == uq.x());
+        assert!(q.y() == uq.y());
+        assert!(q.x().pack() != q.y().pack());
+
+        let mut ys = q.y().pack();
+        *ys.get_mut(51) ^= q.sign_of_x() << 7;
+        assert!(ys == qs.unwrap());
+    }
+
     #[test]
     fn test_ed_to_mont() {
         let bp = ed::GroupElem::base();
@@ -592,6 +1521,34 @@ mod tests {
         assert!(m1.unwrap() == m2.unwrap());
     }
 
+    #[test]
+    fn test_from_mont() {
+        for _ in range(0u, 4) {
+            let p = ed::GroupElem::new_rand();
+            let u = p.to_mont();
+            let q = ed::GroupElem::from_mont(&u, p.sign_of_x()).unwrap();
+            assert!(q == p);
+            let q = ed::GroupElem::from_mont(&u, p.sign_of_x() ^ 1).unwrap();
+            assert!(q == -p);
+        }
+    }
+
+    #[test]
+    fn test_batch_affine() {
+        let bp = ed::GroupElem::base();
+        let mut ps = Vec::from_fn(5, |_| bp * Scalar(Bytes::new_rand()));
+        ps.push(ed::GroupElem::neutral());
+
+        let packed = ed::GroupElem::batch_pack(ps.as_slice());
+        let monts = ed::GroupElem::batch_to_mont(ps.as_slice());
+        assert!(packed.len() == ps.len() && monts.len() == ps.len());
+        for (i, p) in ps.iter().enumerate() {
+            assert!(*packed.get(i) == p.pack());
+            assert!(*monts.get(i) == p.to_mont());
+        }
+        assert!(ed::GroupElem::batch_pack([]).len() == 0);
+    }
+
     #[test]
     fn test_elligator_map_ref() {
         let n1: [u8, ..64] = [
@@ -0,0 +1,145 @@
+//! Errors
+//!
+//! Errors returned by the checked decoding functions, such as
+//! `ed::GroupElem::decode()` or `mont::decode_point()`, when rejecting
+//! malformed inputs. `Error` implements `std::error::Error`, its `Show`
+//! output is a human readable message.
+use std::error;
+use std::fmt::{Show, Formatter, Result};
+
+
+/// Reason of the rejection of an input.
+///
+/// New variants may be added in future versions, matches on this enum
+/// should have a wildcard arm.
+#[deriving(Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Input has length `.1` instead of the expected length `.0`.
+    InvalidLength(uint, uint),
+    /// Input has length `.1` which is none of the accepted lengths `.0`.
+    UnsupportedLength(&'static [uint], uint),
+    /// Input is not the canonical encoding of its value, e.g. the encoding
+    /// of a field element `>= P` or of a scalar `>= L`.
+    NonCanonical,
+    /// Input does not encode a point of the curve.
+    OffCurve,
+    /// Input encodes a point of small order (its order divides `8`).
+    SmallOrder,
+    /// Computed shared secret is zero.
+    ZeroSharedSecret,
+    /// Secret scalar is not clamped (see `B416::clamp_41417()`).
+    NotClamped,
+    /// The same public key appears more than once in a set of keys.
+    DuplicateKey,
+    /// Input is not a well-formed instance of its container format, e.g.
+    /// invalid DER.
+    Malformed,
+    /// Authenticated decryption failed: wrong key or password, or
+    /// tampered input.
+    AuthenticationFailed,
+    /// Checksum of the input doesn't match its contents, e.g. a mistyped
+    /// mnemonic.
+    BadChecksum,
+    #[doc(hidden)]
+    __Nonexhaustive
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            InvalidLength(..) | UnsupportedLength(..) =>
+                "invalid input length",
+            NonCanonical => "non-canonical encoding",
+            OffCurve => "point not on the curve",
+            SmallOrder => "point of small order",
+            ZeroSharedSecret => "zero shared secret",
+            NotClamped => "secret scalar not clamped",
+            DuplicateKey => "duplicate public key",
+            Malformed => "malformed encoding",
+            AuthenticationFailed => "authentication failed",
+            BadChecksum => "checksum mismatch",
+            __Nonexhaustive => "unknown error"
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match *self {
+            InvalidLength(expected, actual) =>
+                Some(format!("expected {} bytes, got {} bytes", expected,
+                             actual)),
+            UnsupportedLength(accepted, actual) =>
+                Some(format!("expected {} bytes, got {} bytes",
+                             fmt_lengths(accepted), actual)),
+            NonCanonical =>
+                Some("value is not reduced or has unused bits set"
+                     .to_string()),
+            OffCurve => Some("no point has this coordinate".to_string()),
+            SmallOrder =>
+                Some("point order divides the cofactor 8".to_string()),
+            _ => None
+        }
+    }
+}
+
+// Format lengths as e.g. "52, 64 or 104".
+fn fmt_lengths(lengths: &[uint]) -> String {
+    let mut s = String::new();
+    for (i, l) in lengths.iter().enumerate() {
+        if i > 0 {
+            s.push_str(if i + 1 == lengths.len() { " or " } else { ", " });
+        }
+        s.push_str(l.to_string().as_slice());
+    }
+    s
+}
+
+impl Show for Error {
+    /// Format as `description: detail`, e.g. `invalid input length:
+    /// expected 52 bytes, got 51 bytes`.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let e = self as &error::Error;
+        match e.detail() {
+            Some(d) => write!(f, "{}: {}", e.description(), d),
+            None => write!(f, "{}", e.description())
+        }
+    }
+}
+
+/// Return `Err(InvalidLength(expected, bytes.len()))` if `bytes` isn't
+/// `expected` bytes long.
+#[doc(hidden)]
+pub fn check_length(bytes: &[u8], expected: uint)
+                    -> ::std::result::Result<(), Error> {
+    match bytes.len() == expected {
+        true => Ok(()),
+        false => Err(InvalidLength(expected, bytes.len()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use error::{InvalidLength, NotClamped, SmallOrder, UnsupportedLength};
+
+
+    #[test]
+    fn test_messages() {
+        let e = InvalidLength(52, 51);
+        assert!(e.description() == "invalid input length");
+        assert!(format!("{}", e) ==
+                "invalid input length: expected 52 bytes, got 51 bytes"
+                .to_string());
+        assert!(format!("{}", SmallOrder) ==
+                "point of small order: point order divides the cofactor 8"
+                .to_string());
+        static LENGTHS: &'static [uint] = &[52, 64, 104];
+        assert!(format!("{}", UnsupportedLength(LENGTHS, 51)) ==
+                "invalid input length: expected 52, 64 or 104 bytes, got 51 \
+                 bytes".to_string());
+        assert!(NotClamped.detail().is_none());
+        assert!(format!("{}", NotClamped) ==
+                "secret scalar not clamped".to_string());
+    }
+}
@@ -0,0 +1,261 @@
+//! Fast key erasure generator
+//!
+//! `FastRng` is a generator for workloads drawing many secrets per second
+//! (e.g. thousands of ephemeral keys), for which reading the operating
+//! system's entropy source for every key would be too slow. It is seeded
+//! from `os_random` and follows the fast key erasure construction: its
+//! key `K` generates a buffer of blocks `HMAC(K, "block" || i)`, the first
+//! block immediately replaces `K`, the other ones are output and erased
+//! from the buffer as soon as they are returned. A compromise of the
+//! generator's state hence reveals none of its previous outputs. It is
+//! reseeded with fresh OS entropy every `reseed_interval()` output bytes
+//! and may be reseeded explicitly with `reseed()`. `with_thread_rng()`
+//! gives access to a generator private to the current task.
+use std::cell::RefCell;
+use std::io::IoResult;
+use std::rand::Rng;
+
+use digest::Digest;
+use digest::sha256::Sha256;
+use kdf;
+use os_random;
+use sbuf::{DefaultAllocator, SBuf};
+use utils;
+
+
+static SEED_LABEL: &'static [u8] = b"curve41417 fast rng";
+static BLOCK_TAG: &'static [u8] = b"block";
+static RESEED_TAG: &'static [u8] = b"reseed";
+
+// Number of blocks computed from each key, the first one is the next key.
+static BUFFER_BLOCKS: uint = 24;
+
+/// Default number of output bytes between two automatic reseeds.
+pub static DEFAULT_RESEED_INTERVAL: u64 = 1 << 20;
+
+
+/// Fast key erasure generator, see the module documentation.
+pub struct FastRng<D> {
+    digest: D,
+    key: SBuf<DefaultAllocator, u8>,
+    buf: SBuf<DefaultAllocator, u8>,
+    pos: uint,
+    output: u64,
+    interval: u64
+}
+
+impl<D: Digest> FastRng<D> {
+    /// Return a new generator seeded with 64 bytes from `os_random`, it is
+    /// reseeded every `DEFAULT_RESEED_INTERVAL` output bytes. Return the
+    /// I/O error if the entropy source can't be read.
+    pub fn new(digest: D) -> IoResult<FastRng<D>> {
+        let mut seed: SBuf<DefaultAllocator, u8> = SBuf::new_zero(64);
+        try!(os_random::fill(seed.as_mut_slice()));
+        let mut rng = FastRng::from_seed(digest, seed.as_slice());
+        rng.interval = DEFAULT_RESEED_INTERVAL;
+        Ok(rng)
+    }
+
+    /// Return a new generator deterministically seeded with `seed`, e.g.
+    /// for tests. It is never reseeded automatically, see
+    /// `set_reseed_interval()`.
+    pub fn from_seed(mut digest: D, seed: &[u8]) -> FastRng<D> {
+        let hs = digest.output_bytes();
+        let mut key: SBuf<DefaultAllocator, u8> = SBuf::new_zero(hs);
+        kdf::derive(&mut digest, [], seed, SEED_LABEL, key.as_mut_slice());
+        let buf = SBuf::new_zero(hs * (BUFFER_BLOCKS - 1));
+        let pos = buf.len();
+        FastRng {
+            digest: digest,
+            key: key,
+            buf: buf,
+            pos: pos,
+            output: 0,
+            interval: 0
+        }
+    }
+
+    /// Return the number of output bytes between two automatic reseeds,
+    /// `0` if this generator is never reseeded automatically.
+    pub fn reseed_interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Reseed this generator from `os_random` every `bytes` output bytes,
+    /// never if `bytes` is `0`.
+    pub fn set_reseed_interval(&mut self, bytes: u64) {
+        self.interval = bytes;
+    }
+
+    /// Mix 64 bytes from `os_random` into the key, see `reseed_with()`.
+    /// Return the I/O error if the entropy source can't be read, the
+    /// generator is then left unchanged.
+    pub fn reseed(&mut self) -> IoResult<()> {
+        let mut e: SBuf<DefaultAllocator, u8> = SBuf::new_zero(64);
+        try!(os_random::fill(e.as_mut_slice()));
+        self.reseed_with(e.as_slice());
+        Ok(())
+    }
+
+    /// Update the key to `HMAC(K, "reseed" || entropy)` and erase the
+    /// buffered output, later outputs depend on both the previous state
+    /// and `entropy`.
+    pub fn reseed_with(&mut self, entropy: &[u8]) {
+        let prev: SBuf<DefaultAllocator, u8> =
+            SBuf::from_slice(self.key.as_slice());
+        kdf::hmac(&mut self.digest, prev.as_slice(), [RESEED_TAG, entropy],
+                  self.key.as_mut_slice());
+        for b in self.buf.mut_iter() {
+            *b = 0;
+        }
+        self.pos = self.buf.len();
+        self.output = 0;
+    }
+
+    // Replace the key and the buffer with the blocks generated from the
+    // current key.
+    fn refill(&mut self) {
+        if self.interval > 0 && self.output >= self.interval {
+            match self.reseed() {
+                Ok(()) => (),
+                Err(e) => fail!("fast_rng: reseeding failed: {}", e)
+            }
+        }
+
+        let hs = self.key.len();
+        let key: SBuf<DefaultAllocator, u8> =
+            SBuf::from_slice(self.key.as_slice());
+        for i in range(0u, BUFFER_BLOCKS) {
+            let ctr = utils::le64(i);
+            let out = match i {
+                0 => self.key.as_mut_slice(),
+                _ => self.buf.mut_slice((i - 1) * hs, i * hs)
+            };
+            kdf::hmac(&mut self.digest, key.as_slice(),
+                      [BLOCK_TAG, ctr.as_slice()], out);
+        }
+        self.pos = 0;
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        if self.pos == self.buf.len() {
+            self.refill();
+        }
+        let b = *self.buf.get(self.pos);
+        *self.buf.get_mut(self.pos) = 0;
+        self.pos += 1;
+        self.output += 1;
+        b
+    }
+}
+
+impl<D: Digest> Rng for FastRng<D> {
+    fn next_u32(&mut self) -> u32 {
+        let mut r = 0u32;
+        for i in range(0u, 4) {
+            r |= self.next_byte() as u32 << (8 * i);
+        }
+        r
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut r = 0u64;
+        for i in range(0u, 8) {
+            r |= self.next_byte() as u64 << (8 * i);
+        }
+        r
+    }
+
+    /// Fails if an automatic reseed can't read the entropy source.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for d in dest.mut_iter() {
+            *d = self.next_byte();
+        }
+    }
+}
+
+
+local_data_key!(THREAD_RNG: RefCell<FastRng<Sha256>>)
+
+/// Call `f` with the generator of the current task, created and seeded
+/// with `FastRng::new()` on first use. For instance
+/// `with_thread_rng(|rng| mont::keypair_from_rng(rng))` generates a key
+/// pair without reading the entropy source. Fails if the entropy source
+/// can't be read, or if `f` itself calls `with_thread_rng()`.
+pub fn with_thread_rng<T>(f: |&mut FastRng<Sha256>| -> T) -> T {
+    if THREAD_RNG.get().is_none() {
+        let rng = match FastRng::new(Sha256::new()) {
+            Ok(rng) => rng,
+            Err(e) => fail!("fast_rng: seeding failed: {}", e)
+        };
+        THREAD_RNG.replace(Some(RefCell::new(rng)));
+    }
+    let cell = THREAD_RNG.get().unwrap();
+    let mut rng = cell.borrow_mut();
+    f(&mut *rng)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::rand::Rng;
+
+    use digest::sha256::Sha256;
+    use fast_rng;
+    use fast_rng::FastRng;
+    use mont;
+
+
+    #[test]
+    fn test_deterministic() {
+        let mut r1 = FastRng::from_seed(Sha256::new(), b"seed");
+        let mut r2 = FastRng::from_seed(Sha256::new(), b"seed");
+        let mut r3 = FastRng::from_seed(Sha256::new(), b"other");
+        let v1: Vec<u64> = r1.gen_iter().take(200).collect();
+        let v2: Vec<u64> = r2.gen_iter().take(200).collect();
+        assert!(v1 == v2);
+        assert!(r3.next_u64() != *v1.get(0));
+
+        r1.reseed_with(b"entropy");
+        assert!(r1.next_u64() != r2.next_u64());
+    }
+
+    #[test]
+    fn test_erasure() {
+        let mut r = FastRng::from_seed(Sha256::new(), b"seed");
+        let key = Vec::from_slice(r.key.as_slice());
+        r.next_u32();
+        assert!(r.key.as_slice() != key.as_slice());
+        assert!(r.buf.slice_to(4).iter().all(|b| *b == 0));
+        assert!(r.buf.slice_from(4).iter().any(|b| *b != 0));
+    }
+
+    #[test]
+    fn test_reseed() {
+        let mut r1 = FastRng::new(Sha256::new()).unwrap();
+        assert!(r1.reseed_interval() == fast_rng::DEFAULT_RESEED_INTERVAL);
+
+        let mut r2 = FastRng::from_seed(Sha256::new(), b"seed");
+        let mut r3 = FastRng::from_seed(Sha256::new(), b"seed");
+        assert!(r2.reseed_interval() == 0);
+        r2.set_reseed_interval(1);
+        let mut b2 = [0u8, ..2048];
+        let mut b3 = [0u8, ..2048];
+        r2.fill_bytes(b2);
+        r3.fill_bytes(b3);
+        assert!(b2.slice_to(736) == b3.slice_to(736));
+        assert!(b2.slice_from(736) != b3.slice_from(736));
+        assert!(r1.reseed().is_ok());
+    }
+
+    #[test]
+    fn test_thread_rng() {
+        let (pk1, _) = fast_rng::with_thread_rng(|rng| {
+            mont::keypair_from_rng(rng)
+        });
+        let (pk2, _) = fast_rng::with_thread_rng(|rng| {
+            mont::keypair_from_rng(rng)
+        });
+        assert!(pk1 != pk2);
+    }
+}
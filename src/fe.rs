@@ -1,15 +1,29 @@
-// A Curve41417 field element representation.
+//! Curve41417 field elements
+//!
+//! Field elements are integers `mod P` where `P = 2^414 - 17`, they are
+//! mostly used as points coordinates.
 use serialize::hex::ToHex;
 use std::default::Default;
 use std::fmt::{Show, Formatter, Result};
-
-use bytes::{B416, Bytes, Uniformity};
-use sbuf::{DefaultAllocator, SBuf};
+use std::result;
+
+use audit;
+use bytes::{B416, B512, Bytes, Uniformity};
+use choice::{Choice, ConditionallySelectable};
+use constants;
+use error;
+use error::{Error, NonCanonical};
+use limbs;
+use sbuf::Limbs26;
+use sc::ScalarElem;
 use utils;
 
 
 static FE_SIZE: uint = 26;
 
+// 2^416 = 68 mod P, top carries are folded into limb 0.
+static FOLD: [i64, ..1] = [68];
+
 static ONE: [i64, ..FE_SIZE] = [
     1, 0, 0, 0, 0, 0, 0, 0,
     0, 0, 0, 0, 0, 0, 0, 0,
@@ -17,40 +31,56 @@ static ONE: [i64, ..FE_SIZE] = [
     0, 0];
 
 
+/// Field element used in points operations.
+///
+/// Arithmetic operations are computed `mod P`. Limbs are held inline
+/// (see `sbuf::Limbs26`) and zeroed-out when the element is dropped, the
+/// arithmetic makes no allocation.
 #[deriving(Clone)]
 pub struct FieldElem {
-    elem: SBuf<DefaultAllocator, i64>
+    elem: Limbs26
 }
 
 impl FieldElem {
+    /// Return a new field element with its value set to `0`.
     pub fn new() -> FieldElem {
         FieldElem::zero()
     }
 
+    /// Return field element `0`.
     pub fn zero() -> FieldElem {
         FieldElem {
-            elem: SBuf::new_zero(FE_SIZE)
+            elem: Limbs26::new_zero()
         }
     }
 
+    /// Return field element `1`.
     pub fn one() -> FieldElem {
         FieldElem {
-            elem: SBuf::from_slice(ONE.as_slice())
+            elem: Limbs26::from_slice(ONE.as_slice())
         }
     }
 
-    // Return a reference to the limb at index `index`. Fails if
-    // `index` is out of bounds.
-    pub fn get<'a>(&'a self, index: uint) -> &'a i64 {
-        self.elem.get(index)
+    /// Return field element `P - 1`, see `constants::P_MINUS_ONE`.
+    pub fn p_minus_one() -> FieldElem {
+        let b: B416 = Bytes::from_bytes(constants::P_MINUS_ONE).unwrap();
+        FieldElem::unpack(&b)
+    }
+
+    // Return a reference to the limb at index `index`, in
+    // [0, FE_SIZE - 1].
+    fn get<'a>(&'a self, index: uint) -> &'a i64 {
+        &self.elem.as_slice()[index]
     }
 
-    // Return a mutable reference to the limb at index `index`. Fails
-    // if `index` is out of bounds.
-    pub fn get_mut<'a>(&'a mut self, index: uint) -> &'a mut i64 {
-        self.elem.get_mut(index)
+    // Return a mutable reference to the limb at index `index`, in
+    // [0, FE_SIZE - 1].
+    fn get_mut<'a>(&'a mut self, index: uint) -> &'a mut i64 {
+        &mut self.elem.as_mut_slice()[index]
     }
 
+    /// Unpack a field element from its little-endian byte representation,
+    /// the top 2 bits of `bytes` are ignored.
     pub fn unpack(bytes: &B416) -> FieldElem {
         let mut n = FieldElem::new();
 
@@ -62,6 +92,7 @@ impl FieldElem {
         n
     }
 
+    /// Pack this field element fully reduced `mod P`.
     pub fn pack(&self) -> B416 {
         let t = self.clone().reduce();
         let mut r: B416 = Bytes::new_zero();
@@ -73,27 +104,52 @@ impl FieldElem {
         r
     }
 
+    /// Decode a field element from its 52 bytes little-endian canonical
+    /// encoding. Every element has exactly one accepted encoding: return
+    /// `Err(InvalidLength)` if `bytes` isn't 52 bytes long and
+    /// `Err(NonCanonical)` if either of the top 2 bits (where packed
+    /// points store the sign of `x`) is set or if the value is not in
+    /// `[0, P-1]`.
+    pub fn from_bytes_strict(bytes: &[u8]) -> result::Result<FieldElem,
+                                                               Error> {
+        try!(error::check_length(bytes, 52));
+        let b: B416 = Bytes::from_bytes(bytes).unwrap();
+        if (*b.get(51) & 0xc0) != 0 {
+            return Err(NonCanonical);
+        }
+        let n = FieldElem::unpack(&b);
+        match n.pack() == b {
+            true => Ok(n),
+            false => Err(NonCanonical)
+        }
+    }
+
+    /// Return the canonical encoding of this field element, its value
+    /// reduced `mod P` in little-endian order with the top 2 bits cleared,
+    /// same as `pack()`. `from_bytes_strict()` accepts it back.
+    pub fn to_bytes(&self) -> B416 {
+        self.pack()
+    }
+
+    /// Conditionally swap this field element with `other`. `cond` must be
+    /// `0` or `1` strictly, values are swapped iff `cond == 1`.
     pub fn cswap(&mut self, cond: i64, other: &mut FieldElem) {
+        audit::access("fe::cswap", self.elem.as_slice());
+        audit::access("fe::cswap", other.elem.as_slice());
         utils::bytes_cswap::<i64>(cond,
                                   self.elem.as_mut_slice(),
                                   other.elem.as_mut_slice());
     }
 
+    #[doc(hidden)]
     pub fn carry(&self) -> FieldElem {
         let mut r = self.clone();
-        let mut c: i64;
-
-        for i in range(0u, r.len()) {
-            *r.get_mut(i) += 1_i64 << 16;
-            c = *r.get(i) >> 16;
-            *r.get_mut((i + 1) * ((i < 25) as uint)) += c - 1 + 67 * (c - 1) *
-                ((i == 25) as i64);
-            *r.get_mut(i) -= c << 16;
-        }
+        limbs::carry(r.elem.as_mut_slice(), 16, FOLD, 0);
         r
     }
 
     // Fully reduce n mod 2^414 - 17
+    #[doc(hidden)]
     pub fn reduce(&self) -> FieldElem {
         let mut r = self.clone().carry().carry().carry();
         let mut m = FieldElem::new();
@@ -114,10 +170,12 @@ impl FieldElem {
     }
 
     // Reduce n mod 2^416 - 68 and put limbs between [0, 2^16-1] through carry.
-    // Requirement: 52 < n.len() <= 104
+    // Limb i >= 26 has weight 2^(16.(i mod 26)).68^(i / 26).
+    // Requirement: 52 < n.len() <= 156, n.len() even
+    #[doc(hidden)]
     pub fn reduce_weak_from_bytes<T: Bytes + Uniformity>(n: &T) -> FieldElem {
         let l = n.as_bytes().len() / 2;
-        assert!(l > 26 && l <= 52);
+        assert!(l > 26 && l <= 78 && n.as_bytes().len() % 2 == 0);
 
         let mut r = FieldElem::new();
 
@@ -126,18 +184,24 @@ impl FieldElem {
                 (*n.get(2 * i + 1) as i64 << 8);
         }
         for i in range(26u, l) {
-            *r.get_mut(i - 26) += (*n.get(2 * i) as i64 +
-                                   (*n.get(2 * i + 1) as i64 << 8)) * 68;
+            let w = match i < 52 {
+                true => 68,
+                false => 68 * 68
+            };
+            *r.get_mut(i % 26) += (*n.get(2 * i) as i64 +
+                                   (*n.get(2 * i + 1) as i64 << 8)) * w;
         }
 
         r.carry().carry()
     }
 
+    /// Return the least significant bit of this element's canonical value.
     pub fn parity_bit(&self) -> u8 {
         let t = self.pack();
         *t.get(0) & 1
     }
 
+    /// Return `self * other` for a small integer `other`.
     pub fn muli(&self, other: i16) -> FieldElem {
         let mut r = self.clone();
 
@@ -148,60 +212,185 @@ impl FieldElem {
         r.carry().carry()
     }
 
+    /// Return a new random field element. Use urandom as PRNG.
+    pub fn new_rand() -> FieldElem {
+        let b: B416 = Bytes::new_rand();
+        FieldElem::unpack(&b)
+    }
+
+    /// Return `self * self`.
     pub fn square(&self) -> FieldElem {
-        self.mul(self)
+        self::impsquare::square(self)
     }
 
-    pub fn inv(&self) -> FieldElem {
+    // Return self^(2^k) i.e. self squared k times.
+    fn square_times(&self, k: uint) -> FieldElem {
         let mut r = self.clone();
-
-        for i in range(0u, 413).rev() {
+        for _ in range(0u, k) {
             r = r.square();
-            if i != 1 && i != 4 {
-                r = r.mul(self);
-            }
         }
         r
     }
 
-    // i ** ((P - 1) / 2) = i ** (2 ** 413 - 9)
-    pub fn pow4139(&self) -> FieldElem {
-        let mut r = self.clone();
+    // Return (self^(2^409 - 1), self^3, self^7) which are the common
+    // building blocks of the addition chains used below. Each step uses
+    // i^(2^(a+b) - 1) = (i^(2^a - 1))^(2^b) * i^(2^b - 1).
+    fn pow_chain_409(&self) -> (FieldElem, FieldElem, FieldElem) {
+        let t2 = self.square() * *self;
+        let t3 = t2.square() * *self;
+        let t4 = t2.square_times(2) * t2;
+        let t8 = t4.square_times(4) * t4;
+        let t16 = t8.square_times(8) * t8;
+        let t32 = t16.square_times(16) * t16;
+        let t64 = t32.square_times(32) * t32;
+        let t128 = t64.square_times(64) * t64;
+        let t256 = t128.square_times(128) * t128;
+        let t384 = t256.square_times(128) * t128;
+        let t400 = t384.square_times(16) * t16;
+        let t408 = t400.square_times(8) * t8;
+        let t409 = t408.square() * *self;
+        (t409, t2, t3)
+    }
 
-        for i in range(0u, 412).rev() {
+    /// Return `self^e` where `e` is a little-endian exponent. All the bits
+    /// of `e` are processed with the same operations.
+    pub fn pow(&self, e: &[u8]) -> FieldElem {
+        let mut r = FieldElem::one();
+
+        for i in range(0u, 8 * e.len()).rev() {
             r = r.square();
-            if i != 3 {
-                r = r.mul(self);
-            }
+            let mut t = r * *self;
+            let bit = ((e[i >> 3] >> (i & 7)) & 1) as i64;
+            r.cswap(bit, &mut t);
         }
         r
     }
 
+    /// Return the multiplicative inverse of this element, the inverse of
+    /// `0` is `0`.
+    // i ** (P - 2) = i ** (2 ** 414 - 19)
+    pub fn inv(&self) -> FieldElem {
+        let (t409, t2, _) = self.pow_chain_409();
+        let t13 = t2.square_times(2) * *self;
+        t409.square_times(5) * t13
+    }
+
+    // i ** ((P - 1) / 2) = i ** (2 ** 413 - 9)
+    #[doc(hidden)]
+    pub fn pow4139(&self) -> FieldElem {
+        let (t409, _, t3) = self.pow_chain_409();
+        t409.square_times(4) * t3
+    }
+
     // i ** ((P - 3) / 4) = i ** (2 ** 412 - 5)
+    #[doc(hidden)]
     pub fn pow4125(&self) -> FieldElem {
-        let mut r = self.clone();
-
-        for i in range(0u, 411).rev() {
-            r = r.square();
-            if i != 2 {
-                r = r.mul(self);
-            }
-        }
-        r
+        let (t409, t2, _) = self.pow_chain_409();
+        t409.square_times(3) * t2
     }
 
     // i ** ((P + 1) / 4) = i ** (2 ** 412 - 4)
+    #[doc(hidden)]
     pub fn pow4124(&self) -> FieldElem {
-        let mut r = self.clone();
+        let (t409, _, _) = self.pow_chain_409();
+        let t410 = t409.square() * *self;
+        t410.square_times(2)
+    }
 
-        for i in range(0u, 411).rev() {
-            r = r.square();
-            if i != 0 && i != 1 {
-                r = r.mul(self);
-            }
+    /// Return the Legendre symbol of this element: `0` if it is `0`, `1`
+    /// if it is a non-zero square and `-1` otherwise. Computed as
+    /// `self^((P-1)/2)` in constant time.
+    pub fn legendre(&self) -> i8 {
+        let chi = self.pow4139();
+        let one = (chi == FieldElem::one()) as i8;
+        let zero = (chi == FieldElem::zero()) as i8;
+        2 * one + zero - 1
+    }
+
+    /// Return a true choice iff this element is a square (`0` included),
+    /// see `legendre()`.
+    pub fn is_square(&self) -> Choice {
+        Choice::from_u8(((self.legendre() as u8) >> 7) ^ 1)
+    }
+
+    /// Constant-time equality comparison, `==` is the same test returning
+    /// a `bool`.
+    pub fn ct_eq(&self, other: &FieldElem) -> Choice {
+        self.pack().ct_eq(&other.pack())
+    }
+
+    /// Return a true choice iff this element is `0 mod P`. Always executes
+    /// the same operations.
+    pub fn is_zero(&self) -> Choice {
+        self.ct_eq(&FieldElem::zero())
+    }
+
+    /// Return the inverses of all the elements of `elems` using a single
+    /// inversion (Montgomery's trick). If any of the elements is `0` then
+    /// the returned values are all set to `0`.
+    pub fn batch_inv(elems: &[FieldElem]) -> Vec<FieldElem> {
+        // prods[i] = elems[0] * ... * elems[i - 1]
+        let mut prods = Vec::with_capacity(elems.len());
+        let mut acc = FieldElem::one();
+        for e in elems.iter() {
+            prods.push(acc.clone());
+            acc = acc * *e;
+        }
+
+        let mut inv = acc.inv();
+        let mut r = Vec::from_fn(elems.len(), |_| FieldElem::zero());
+        for i in range(0u, elems.len()).rev() {
+            *r.get_mut(i) = inv * *prods.get(i);
+            inv = inv * elems[i];
         }
         r
     }
+
+    /// Interpret the canonical value of this field element as an integer
+    /// and return it reduced `mod L`.
+    pub fn to_scalar_elem_reduced(&self) -> ScalarElem {
+        let p = self.pack();
+        let mut b: B512 = Bytes::new_zero();
+        for (db, sb) in b.as_mut_bytes().mut_iter().zip(p.as_bytes().iter()) {
+            *db = *sb;
+        }
+        ScalarElem::unpack(&b).unwrap()
+    }
+
+    /// Interpret the canonical value of this field element as a scalar
+    /// value. Return `None` if this value is not in `[0, L-1]`.
+    pub fn to_scalar_elem_strict(&self) -> Option<ScalarElem> {
+        let b = self.pack();
+        match ScalarElem::is_canonical(&b).unwrap_u8() {
+            1 => ScalarElem::unpack(&b),
+            _ => None
+        }
+    }
+
+    /// Return the field element whose value is the integer of little-endian
+    /// 64 bits limbs `limbs`. Return `Err(InvalidLength)` unless there are
+    /// 7 limbs and `Err(NonCanonical)` if the value is not in `[0, P-1]`.
+    pub fn from_u64_limbs(limbs: &[u64]) -> result::Result<FieldElem, Error> {
+        if limbs.len() != 7 {
+            return Err(error::InvalidLength(7, limbs.len()));
+        }
+        match utils::b416_from_u64_limbs(limbs) {
+            Some(b) => FieldElem::from_bytes_strict(b.as_bytes()),
+            None => Err(NonCanonical)
+        }
+    }
+
+    /// Return the 7 little-endian 64 bits limbs of the canonical value of
+    /// this field element, see `from_u64_limbs()`.
+    pub fn to_u64_limbs(&self) -> [u64, ..7] {
+        utils::b416_to_u64_limbs(&self.pack())
+    }
+
+    /// Return the field element of same value as the canonical value of the
+    /// scalar `n`. As `L < P` this conversion never needs any reduction.
+    pub fn from_scalar_elem(n: &ScalarElem) -> FieldElem {
+        FieldElem::unpack(n.pack().get_ref())
+    }
 }
 
 impl Add<FieldElem, FieldElem> for FieldElem {
@@ -250,6 +439,49 @@ impl Mul<FieldElem, FieldElem> for FieldElem {
     }
 }
 
+// Portable backend: squaring is a plain multiplication.
+#[cfg(not(feature = "backend_fast"))]
+mod impsquare {
+    use super::FieldElem;
+
+
+    pub fn square(n: &FieldElem) -> FieldElem {
+        *n * *n
+    }
+}
+
+// Fast backend: dedicated squaring, each cross product a_j * a_k with
+// j != k is computed once and doubled.
+#[cfg(feature = "backend_fast")]
+mod impsquare {
+    use super::FieldElem;
+
+
+    pub fn square(n: &FieldElem) -> FieldElem {
+        let mut u: i64;
+        let mut r = FieldElem::new();
+
+        for i in range(0u, 26) {
+            u = 0;
+            for j in range(0u, (i + 1) / 2) {
+                u += 2 * *n.get(j) * *n.get(i - j);
+            }
+            if i % 2 == 0 {
+                u += *n.get(i / 2) * *n.get(i / 2);
+            }
+            for j in range(i + 1, (i + 27) / 2) {
+                u += 136 * *n.get(j) * *n.get(i + 26 - j);
+            }
+            if i % 2 == 0 {
+                u += 68 * *n.get(i / 2 + 13) * *n.get(i / 2 + 13);
+            }
+            *r.get_mut(i) = u;
+        }
+
+        r.carry().carry()
+    }
+}
+
 impl Default for FieldElem {
     fn default() -> FieldElem {
         FieldElem::new()
@@ -268,6 +500,12 @@ impl ToHex for FieldElem {
     }
 }
 
+impl ConditionallySelectable for FieldElem {
+    fn conditional_swap(&mut self, other: &mut FieldElem, choice: Choice) {
+        self.cswap(choice.unwrap_u8() as i64, other);
+    }
+}
+
 impl PartialEq for FieldElem {
     fn eq(&self, other: &FieldElem) -> bool {
         self.pack() == other.pack()
@@ -282,3 +520,171 @@ impl Collection for FieldElem {
         self.elem.len()
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use bytes::{B416, B512, B912, Bytes};
+    use constants;
+    use error::{InvalidLength, NonCanonical};
+    use fe::FieldElem;
+    use sc::ScalarElem;
+
+
+    // Little-endian 52-bytes exponent made of 0xff bytes except for its
+    // first byte low and its top byte high.
+    fn exponent(low: u8, high: u8) -> [u8, ..52] {
+        let mut e = [0xffu8, ..52];
+        e[0] = low;
+        e[51] = high;
+        e
+    }
+
+    #[test]
+    fn test_scalar_conversions() {
+        let n = ScalarElem::new_rand();
+        let x = FieldElem::from_scalar_elem(&n);
+        assert!(x.to_scalar_elem_strict().unwrap() == n);
+        assert!(x.to_scalar_elem_reduced() == n);
+
+        // P - 1 > L
+        let m = FieldElem::zero() - FieldElem::one();
+        assert!(m.to_scalar_elem_strict().is_none());
+        let r = m.to_scalar_elem_reduced();
+        assert!(FieldElem::from_scalar_elem(&r) != m);
+    }
+
+    #[test]
+    fn test_bytes_strict() {
+        let x = FieldElem::new_rand();
+        let b = x.to_bytes();
+        assert!(FieldElem::from_bytes_strict(b.as_bytes()).unwrap() == x);
+        assert!(FieldElem::from_bytes_strict(b.as_bytes().slice_to(51))
+                .err() == Some(InvalidLength(52, 51)));
+
+        // P - 1 is the largest accepted value, P is rejected.
+        let pm1: B416 = Bytes::from_bytes(constants::P_MINUS_ONE).unwrap();
+        assert!(FieldElem::from_bytes_strict(pm1.as_bytes()).is_ok());
+        let mut p = pm1.clone();
+        *p.get_mut(0) = 0xef;
+        assert!(FieldElem::from_bytes_strict(p.as_bytes()).err() ==
+                Some(NonCanonical));
+
+        // Top bits are never part of the encoding.
+        for &bit in [0x40u8, 0x80].iter() {
+            let mut t = b.clone();
+            *t.get_mut(51) |= bit;
+            assert!(FieldElem::from_bytes_strict(t.as_bytes()).err() ==
+                    Some(NonCanonical));
+        }
+        assert!(FieldElem::zero().to_bytes() == Bytes::new_zero());
+    }
+
+    #[test]
+    fn test_reduce_weak_from_bytes() {
+        // 2^416 = 68 and 2^832 = 68^2 mod P.
+        let mut n: B912 = Bytes::new_zero();
+        *n.get_mut(0) = 1;
+        *n.get_mut(52) = 1;
+        *n.get_mut(104) = 1;
+        // 1 + 68 + 68^2 = 0x1255
+        let mut e: B416 = Bytes::new_zero();
+        *e.get_mut(0) = 0x55;
+        *e.get_mut(1) = 0x12;
+        assert!(FieldElem::reduce_weak_from_bytes(&n) ==
+                FieldElem::unpack(&e));
+
+        let r: B512 = Bytes::new_rand();
+        let mut w: B912 = Bytes::new_zero();
+        for i in range(0u, 64) {
+            *w.get_mut(i) = *r.get(i);
+        }
+        assert!(FieldElem::reduce_weak_from_bytes(&w) ==
+                FieldElem::reduce_weak_from_bytes(&r));
+    }
+
+    #[test]
+    fn test_batch_inv() {
+        let elems = Vec::from_fn(5, |_| {
+            let b: B416 = Bytes::new_rand();
+            FieldElem::unpack(&b)
+        });
+        let invs = FieldElem::batch_inv(elems.as_slice());
+
+        for (e, i) in elems.iter().zip(invs.iter()) {
+            assert!(*i == e.inv());
+        }
+    }
+
+    #[test]
+    fn test_u64_limbs() {
+        let x = FieldElem::new_rand();
+        assert!(FieldElem::from_u64_limbs(x.to_u64_limbs()).unwrap() == x);
+
+        // P = 2^414 - 17 and P - 1.
+        let mut p = [0xffffffffffffffffu64, ..7];
+        p[0] -= 16;
+        p[6] = 0x3fffffff;
+        assert!(FieldElem::from_u64_limbs(p) == Err(NonCanonical));
+        p[0] -= 1;
+        let pm1 = FieldElem::zero() - FieldElem::one();
+        assert!(FieldElem::from_u64_limbs(p).unwrap() == pm1);
+        assert!(pm1.to_u64_limbs() == p);
+        assert!(FieldElem::from_u64_limbs([]) == Err(InvalidLength(7, 0)));
+    }
+
+    #[test]
+    fn test_square() {
+        // square() is the backend's squaring, x * x the portable product.
+        for _ in range(0u, 256) {
+            let x = FieldElem::new_rand();
+            assert!(x.square() == x * x);
+        }
+
+        let pm1: B416 = Bytes::from_bytes(constants::P_MINUS_ONE).unwrap();
+        let m = FieldElem::unpack(&pm1);
+        assert!(m == FieldElem::zero() - FieldElem::one());
+        assert!(m.square() == FieldElem::one());
+
+        // All-ones bytes unpack to 2^414 - 1 (not reduced), max has every
+        // limb at 0xffff, the largest unpacked limb.
+        let ones: B416 = Bytes::from_bytes([0xffu8, ..52]).unwrap();
+        let mut max = FieldElem::new();
+        for l in max.elem.mut_iter() {
+            *l = 0xffff;
+        }
+        for x in [FieldElem::zero(), FieldElem::one(), m,
+                  FieldElem::unpack(&ones), -max, max].iter() {
+            assert!(x.square() == *x * *x);
+        }
+    }
+
+    #[test]
+    fn test_legendre() {
+        let x = FieldElem::new_rand();
+        assert!(x.square().legendre() == 1);
+        assert!(x.square().is_square().unwrap_u8() == 1);
+        assert!(FieldElem::zero().legendre() == 0);
+        assert!(FieldElem::zero().is_square().unwrap_u8() == 1);
+        assert!(FieldElem::zero().is_zero().unwrap_u8() == 1);
+        assert!(x.ct_eq(&(x + FieldElem::zero())).unwrap_u8() == 1);
+        assert!(x.ct_eq(&(x + FieldElem::one())).unwrap_u8() == 0);
+
+        // P = 3 mod 4, -1 is not a square.
+        let m1 = -FieldElem::one();
+        assert!(m1.legendre() == -1 && m1.is_square().unwrap_u8() == 0);
+        assert!((m1 * x.square()).legendre() == -1);
+    }
+
+    #[test]
+    fn test_pow_chains() {
+        let b: B416 = Bytes::new_rand();
+        let x = FieldElem::unpack(&b);
+
+        assert!(x.inv() == x.pow(exponent(0xed, 0x3f)));
+        assert!(x.pow4139() == x.pow(exponent(0xf7, 0x1f)));
+        assert!(x.pow4125() == x.pow(exponent(0xfb, 0x0f)));
+        assert!(x.pow4124() == x.pow(exponent(0xfc, 0x0f)));
+        assert!(x * x.inv() == FieldElem::one());
+    }
+}
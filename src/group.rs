@@ -0,0 +1,199 @@
+//! Generic group interface
+//!
+//! Traits for writing protocols generically over a group and its scalars,
+//! so that they may be instantiated with Curve41417: `GroupScalar` for the
+//! scalars modulo the group order, `Group` for the group elements and the
+//! marker `PrimeGroup` for groups of prime order. `GroupElem` implements
+//! `Group` with `ScalarElem` as scalars; it has a cofactor of `8` and is
+//! not a `PrimeGroup`, protocols requiring a prime-order group should
+//! check `cofactor()` or clear it with `mul_cofactor()`.
+use std::rand::{Rand, Rng};
+use std::result;
+
+use choice::Choice;
+use ed::GroupElem;
+use error::Error;
+use sc::ScalarElem;
+
+
+/// Scalars of a group: integers modulo its (prime) order.
+pub trait GroupScalar: Clone + PartialEq + Add<Self, Self> + Sub<Self, Self>
+                       + Mul<Self, Self> + Neg<Self> {
+    /// Return the scalar `0`.
+    fn zero() -> Self;
+
+    /// Return the scalar `1`.
+    fn one() -> Self;
+
+    /// Return a uniformly random scalar generated from `rng`.
+    fn random<R: Rng>(rng: &mut R) -> Self;
+
+    /// Return the inverse of this scalar, or `None` if it is zero.
+    fn invert(&self) -> Option<Self>;
+
+    /// Return the canonical encoding of this scalar.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Decode a scalar, rejecting non-canonical encodings.
+    fn decode(bytes: &[u8]) -> result::Result<Self, Error>;
+}
+
+/// Group of elements written additively, with scalars `S`.
+pub trait Group<S: GroupScalar>: Clone + PartialEq + Add<Self, Self>
+                                 + Sub<Self, Self> + Neg<Self> {
+    /// Return the identity element.
+    fn identity() -> Self;
+
+    /// Return the fixed generator of the group.
+    fn generator() -> Self;
+
+    /// Return the cofactor of the group of this element, `1` for
+    /// prime-order groups.
+    fn cofactor(&self) -> uint;
+
+    /// Return a true choice iff this element is the identity.
+    fn is_identity(&self) -> Choice;
+
+    /// Return `2.self`.
+    fn double(&self) -> Self;
+
+    /// Return `n.self`.
+    fn scalar_mul(&self, n: &S) -> Self;
+
+    /// Return `cofactor().self`.
+    fn mul_cofactor(&self) -> Self;
+
+    /// Return a uniformly random element of the subgroup generated by
+    /// `generator()`, generated from `rng`.
+    fn random<R: Rng>(rng: &mut R) -> Self;
+
+    /// Return the canonical encoding of this element.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Decode an element, rejecting non-canonical encodings.
+    fn decode(bytes: &[u8]) -> result::Result<Self, Error>;
+}
+
+/// Marker of groups of prime order: every element but the identity is a
+/// generator, and `cofactor()` is `1`.
+pub trait PrimeGroup<S: GroupScalar>: Group<S> {
+}
+
+impl GroupScalar for ScalarElem {
+    fn zero() -> ScalarElem {
+        ScalarElem::zero()
+    }
+
+    fn one() -> ScalarElem {
+        FromPrimitive::from_u64(1).unwrap()
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> ScalarElem {
+        Rand::rand(rng)
+    }
+
+    fn invert(&self) -> Option<ScalarElem> {
+        match self.is_zero().unwrap_u8() {
+            0 => Some(self.inv()),
+            _ => None
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        Vec::from_slice(self.pack().get_ref().as_bytes())
+    }
+
+    /// See `ScalarElem::decode()`.
+    fn decode(bytes: &[u8]) -> result::Result<ScalarElem, Error> {
+        ScalarElem::decode(bytes)
+    }
+}
+
+impl Group<ScalarElem> for GroupElem {
+    fn identity() -> GroupElem {
+        GroupElem::identity()
+    }
+
+    /// Return the base point.
+    fn generator() -> GroupElem {
+        GroupElem::base()
+    }
+
+    fn cofactor(&self) -> uint {
+        8
+    }
+
+    fn is_identity(&self) -> Choice {
+        self.is_identity()
+    }
+
+    fn double(&self) -> GroupElem {
+        self.dbl()
+    }
+
+    fn scalar_mul(&self, n: &ScalarElem) -> GroupElem {
+        *self * *n
+    }
+
+    fn mul_cofactor(&self) -> GroupElem {
+        self.scalar_mult_cofactor()
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> GroupElem {
+        Rand::rand(rng)
+    }
+
+    /// Return the 52 bytes packed point, see `GroupElem::pack()`.
+    fn encode(&self) -> Vec<u8> {
+        Vec::from_slice(self.pack().get_ref().as_bytes())
+    }
+
+    /// See `GroupElem::decode_canonical()`, points of small order are
+    /// accepted.
+    fn decode(bytes: &[u8]) -> result::Result<GroupElem, Error> {
+        GroupElem::decode_canonical(bytes)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use choice::Choice;
+use ed::GroupElem;
+    use group::{Group, GroupScalar};
+    use sc::ScalarElem;
+    use utils;
+
+
+    // Diffie-Hellman written against the traits only.
+    fn dh<S: GroupScalar, G: Group<S>>() {
+        let mut rng = utils::urandom_rng();
+        let (a, b): (S, S) = (GroupScalar::random(&mut rng),
+                              GroupScalar::random(&mut rng));
+        let g: G = Group::generator();
+        let (pa, pb) = (g.scalar_mul(&a), g.scalar_mul(&b));
+        let pa: G = Group::decode(pa.encode().as_slice()).unwrap();
+        assert!(pa.scalar_mul(&b) == pb.scalar_mul(&a));
+        assert!(g.scalar_mul(&(a * b)) == pa.scalar_mul(&b));
+
+        let one: S = GroupScalar::one();
+        assert!(a.invert().unwrap() * a == one);
+        let zero: S = GroupScalar::zero();
+        assert!(zero.invert().is_none());
+        assert!(g.scalar_mul(&zero).is_identity().unwrap_u8() == 1);
+        let id: G = Group::identity();
+        assert!(g.double() == g + g && g - g == id);
+        let s: S = GroupScalar::decode(a.encode().as_slice()).unwrap();
+        assert!(s == a);
+    }
+
+    #[test]
+    fn test_group() {
+        dh::<ScalarElem, GroupElem>();
+        let g: GroupElem = Group::generator();
+        assert!(g.cofactor() == 8);
+        assert!(g.mul_cofactor() == g.scalar_mult_cofactor());
+        let eight: ScalarElem = FromPrimitive::from_u64(8).unwrap();
+        assert!(g.mul_cofactor() == g.scalar_mul(&eight));
+    }
+}
@@ -0,0 +1,33 @@
+// Task-local hooks of the `audit` and `vectors` modules. A hook is a boxed
+// trait object installed for the current task; it is only compiled in when
+// the crate is built with the feature of its module, otherwise reporting
+// compiles to nothing and installing a hook has no effect.
+#![macro_escape]
+
+
+// Declare the task-local slot $key of a boxed $hook, with $set installing
+// it (returning true) and $take removing it.
+macro_rules! task_hook(($key:ident, $hook:ident, $set:ident, $take:ident) => (
+    local_data_key!($key: ::std::cell::RefCell<Box<$hook>>)
+
+    pub fn $set(hook: Box<$hook>) -> bool {
+        $key.replace(Some(::std::cell::RefCell::new(hook)));
+        true
+    }
+
+    pub fn $take() -> Option<Box<$hook>> {
+        $key.replace(None).map(|h| h.unwrap())
+    }
+))
+
+// Declare $set and $take of a hook which is not compiled in: $set returns
+// false and $take None.
+macro_rules! no_task_hook(($hook:ident, $set:ident, $take:ident) => (
+    pub fn $set(_: Box<$hook>) -> bool {
+        false
+    }
+
+    pub fn $take() -> Option<Box<$hook>> {
+        None
+    }
+))
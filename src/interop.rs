@@ -0,0 +1,289 @@
+//! Ecosystem adapters
+//!
+//! Small traits for bridging the crate to the random generators,
+//! serialization frameworks and wiping conventions of other libraries,
+//! without the crate depending on any of them. A downstream crate writes
+//! one impl of each trait for the external trait it uses, e.g.
+//! `ByteSource` for its generator type or a generic serializer over
+//! `ByteEncoding`, and every type of this crate follows:
+//!
+//! * `ByteSource` is a source of random bytes, `RngFromSource` turns it
+//!   into the `std::rand::Rng` taken by the crate's generators (e.g.
+//!   `KeyPair::generate_from_rng()`).
+//! * `ByteEncoding` is implemented by the public types with a canonical
+//!   byte encoding, decoding uses the crate's checked decoding functions.
+//! * `Wipe` wipes a secret immediately instead of when it is dropped.
+//!
+//! Only available with the `interop` feature.
+use std::rand::Rng;
+use std::result;
+
+use bytes::{B416, B512, B832, Bytes, EdPoint, MontPoint, Scalar};
+use ed::GroupElem;
+use error::Error;
+use keys::{DhSecretKey, PublicKey, SecretKey, Seed};
+use mont;
+use sc::ScalarElem;
+use sign::Signature;
+
+
+/// Source of random bytes.
+pub trait ByteSource {
+    /// Fill `dest` with random bytes.
+    fn fill(&mut self, dest: &mut [u8]);
+}
+
+/// `Rng` drawing its output from a `ByteSource`, integers are decoded
+/// from little-endian bytes.
+pub struct RngFromSource<S> {
+    source: S
+}
+
+impl<S: ByteSource> RngFromSource<S> {
+    /// Wrap `source`.
+    pub fn new(source: S) -> RngFromSource<S> {
+        RngFromSource {
+            source: source
+        }
+    }
+
+    /// Return the wrapped source.
+    pub fn unwrap(self) -> S {
+        self.source
+    }
+}
+
+impl<S: ByteSource> Rng for RngFromSource<S> {
+    fn next_u32(&mut self) -> u32 {
+        let mut b = [0u8, ..4];
+        self.source.fill(b);
+        b.iter().rev().fold(0u32, |r, x| (r << 8) | *x as u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut b = [0u8, ..8];
+        self.source.fill(b);
+        b.iter().rev().fold(0u64, |r, x| (r << 8) | *x as u64)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.source.fill(dest);
+    }
+}
+
+
+/// Types with a canonical byte encoding.
+pub trait ByteEncoding {
+    /// Return the encoding of this value.
+    fn to_encoded_bytes(&self) -> Vec<u8>;
+
+    /// Decode a value, rejecting the inputs its checked decoding function
+    /// rejects.
+    fn from_encoded_bytes(bytes: &[u8]) -> result::Result<Self, Error>;
+}
+
+impl ByteEncoding for PublicKey {
+    fn to_encoded_bytes(&self) -> Vec<u8> {
+        Vec::from_slice(self.as_point().get_ref().as_bytes())
+    }
+
+    /// See `PublicKey::decode()`.
+    fn from_encoded_bytes(bytes: &[u8]) -> result::Result<PublicKey, Error> {
+        PublicKey::decode(bytes)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_encoded_bytes(&self) -> Vec<u8> {
+        Vec::from_slice(self.to_bytes().as_bytes())
+    }
+
+    /// See `Signature::from_bytes()`.
+    fn from_encoded_bytes(bytes: &[u8]) -> result::Result<Signature, Error> {
+        Signature::from_bytes(bytes)
+    }
+}
+
+impl ByteEncoding for GroupElem {
+    fn to_encoded_bytes(&self) -> Vec<u8> {
+        Vec::from_slice(self.pack().get_ref().as_bytes())
+    }
+
+    /// See `GroupElem::decode_canonical()`, points of small order such as
+    /// the identity are accepted.
+    fn from_encoded_bytes(bytes: &[u8]) -> result::Result<GroupElem, Error> {
+        GroupElem::decode_canonical(bytes)
+    }
+}
+
+impl ByteEncoding for MontPoint {
+    fn to_encoded_bytes(&self) -> Vec<u8> {
+        Vec::from_slice(self.get_ref().as_bytes())
+    }
+
+    /// See `mont::decode_point()`.
+    fn from_encoded_bytes(bytes: &[u8]) -> result::Result<MontPoint, Error> {
+        mont::decode_point(bytes)
+    }
+}
+
+impl ByteEncoding for ScalarElem {
+    fn to_encoded_bytes(&self) -> Vec<u8> {
+        Vec::from_slice(self.pack().get_ref().as_bytes())
+    }
+
+    /// See `ScalarElem::decode()`.
+    fn from_encoded_bytes(bytes: &[u8])
+                          -> result::Result<ScalarElem, Error> {
+        ScalarElem::decode(bytes)
+    }
+}
+
+
+/// Values which can be wiped before being dropped. Wiped values are left
+/// zeroed (a zero secret key, an empty-valued container) and should no
+/// longer be used.
+pub trait Wipe {
+    /// Overwrite the secret bytes of this value now.
+    fn wipe(&mut self);
+}
+
+// The previous values are dropped, so their secure buffers are wiped.
+macro_rules! wipe_bytes_impl(($name:ident) => (
+    impl Wipe for $name {
+        fn wipe(&mut self) {
+            *self = Bytes::new_zero();
+        }
+    }
+))
+
+wipe_bytes_impl!(B416)
+wipe_bytes_impl!(B512)
+wipe_bytes_impl!(B832)
+
+impl Wipe for Scalar {
+    fn wipe(&mut self) {
+        *self = Scalar(Bytes::new_zero());
+    }
+}
+
+impl Wipe for EdPoint {
+    fn wipe(&mut self) {
+        *self = EdPoint(Bytes::new_zero());
+    }
+}
+
+impl Wipe for SecretKey {
+    fn wipe(&mut self) {
+        *self = SecretKey::unsafe_from_scalar(Scalar(Bytes::new_zero()));
+    }
+}
+
+impl Wipe for DhSecretKey {
+    fn wipe(&mut self) {
+        *self = DhSecretKey::unsafe_from_scalar(Scalar(Bytes::new_zero()));
+    }
+}
+
+impl Wipe for Seed {
+    /// The zeroed seed keeps the length of this one.
+    fn wipe(&mut self) {
+        let zero = Vec::from_elem(self.as_bytes().len(), 0u8);
+        *self = Seed::new(zero.as_slice()).unwrap();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::rand::Rng;
+    use std::result;
+
+    use bytes::{B416, Bytes, MontPoint};
+    use digest::sha256::Sha256;
+    use ed::GroupElem;
+    use error::{Error, NonCanonical};
+    use interop::{ByteEncoding, ByteSource, RngFromSource, Wipe};
+    use keys::{DhSecretKey, KeyPair, PublicKey, Seed};
+    use sc::ScalarElem;
+
+
+    // External generator of consecutive bytes.
+    struct Counter {
+        next: u8
+    }
+
+    impl ByteSource for Counter {
+        fn fill(&mut self, dest: &mut [u8]) {
+            for d in dest.mut_iter() {
+                *d = self.next;
+                self.next += 1;
+            }
+        }
+    }
+
+    fn round_trip<T: ByteEncoding + PartialEq>(v: T) {
+        let b = v.to_encoded_bytes();
+        let w: T = ByteEncoding::from_encoded_bytes(b.as_slice()).unwrap();
+        assert!(w == v);
+    }
+
+    #[test]
+    fn test_rng_from_source() {
+        let mut r = RngFromSource::new(Counter { next: 1 });
+        assert!(r.next_u32() == 0x04030201);
+        assert!(r.next_u64() == 0x0c0b0a0908070605);
+        let mut b = [0u8, ..2];
+        r.fill_bytes(b);
+        assert!(b == [13, 14]);
+        assert!(r.unwrap().next == 15);
+
+        let mut r = RngFromSource::new(Counter { next: 0 });
+        let kp = KeyPair::generate_from_rng(&mut r);
+        assert!(kp.validate().is_valid());
+    }
+
+    #[test]
+    fn test_byte_encoding() {
+        let kp = KeyPair::generate();
+        round_trip(kp.public().clone());
+        round_trip(GroupElem::new_rand());
+        round_trip(GroupElem::identity());
+        round_trip(ScalarElem::new_rand());
+        round_trip(DhSecretKey::generate().public_key());
+        round_trip(kp.sign(&mut Sha256::new(), []).unwrap());
+
+        let l = Vec::from_slice(ScalarElem::order().as_bytes());
+        let r: result::Result<ScalarElem, Error> =
+            ByteEncoding::from_encoded_bytes(l.as_slice());
+        assert!(r.err() == Some(NonCanonical));
+        let r: result::Result<PublicKey, Error> =
+            ByteEncoding::from_encoded_bytes([]);
+        assert!(r.is_err());
+        let r: result::Result<MontPoint, Error> =
+            ByteEncoding::from_encoded_bytes([]);
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_wipe() {
+        let zero: B416 = Bytes::new_zero();
+        let mut b: B416 = Bytes::new_rand();
+        b.wipe();
+        assert!(b == zero);
+
+        let mut sk = KeyPair::generate().secret().clone();
+        sk.wipe();
+        assert!(*sk.unsafe_expose_scalar().get_ref() == zero);
+        let mut dk = DhSecretKey::generate();
+        dk.wipe();
+        assert!(*dk.unsafe_expose_scalar().get_ref() == zero);
+
+        for &len in [32u, 40, 52].iter() {
+            let mut seed = Seed::new(Vec::from_elem(len, 7u8).as_slice())
+                .unwrap();
+            seed.wipe();
+            assert!(seed.as_bytes() == Vec::from_elem(len, 0u8).as_slice());
+        }
+    }
+}
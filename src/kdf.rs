@@ -0,0 +1,288 @@
+//! Key derivation functions
+//!
+//! HMAC-based extract-and-expand key derivation (HKDF, RFC 5869) built
+//! over a caller-supplied `Digest`. Its main purpose is to turn raw
+//! shared secrets (e.g. Diffie-Hellman x-coordinates) into labeled
+//! symmetric keys instead of using them directly as keys.
+use std::cmp;
+use std::rand::Rng;
+
+use bytes::{B832, Bytes};
+use digest::Digest;
+use sbuf::{DefaultAllocator, SBuf};
+use sc::ScalarElem;
+use utils;
+
+
+/// HMAC: compute `HMAC(key, data[0] || data[1] || ...)` and write the
+/// result into `out` which must be at least `digest.output_bytes()` long.
+pub fn hmac<D: Digest>(digest: &mut D, key: &[u8], data: &[&[u8]],
+                       out: &mut [u8]) {
+    let bs = digest.block_size();
+    let hs = digest.output_bytes();
+    assert!(out.len() >= hs);
+
+    let mut k: SBuf<DefaultAllocator, u8> = SBuf::new_zero(bs);
+    if key.len() > bs {
+        digest.reset();
+        digest.input(key);
+        digest.result(k.mut_slice_to(hs));
+    } else {
+        for i in range(0u, key.len()) {
+            *k.get_mut(i) = key[i];
+        }
+    }
+
+    let mut pad: SBuf<DefaultAllocator, u8> = SBuf::new_zero(bs);
+    for i in range(0u, bs) {
+        *pad.get_mut(i) = *k.get(i) ^ 0x36;
+    }
+    let mut inner: SBuf<DefaultAllocator, u8> = SBuf::new_zero(hs);
+    digest.reset();
+    digest.input(pad.as_slice());
+    for d in data.iter() {
+        digest.input(*d);
+    }
+    digest.result(inner.as_mut_slice());
+
+    for i in range(0u, bs) {
+        *pad.get_mut(i) = *k.get(i) ^ 0x5c;
+    }
+    digest.reset();
+    digest.input(pad.as_slice());
+    digest.input(inner.as_slice());
+    digest.result(out);
+    digest.reset();
+}
+
+/// HKDF-Extract: derive the pseudorandom key `prk` from the input keying
+/// material `ikm` and the optional `salt` (may be empty). `prk` must be
+/// `digest.output_bytes()` long.
+pub fn extract<D: Digest>(digest: &mut D, salt: &[u8], ikm: &[u8],
+                          prk: &mut [u8]) {
+    assert!(prk.len() == digest.output_bytes());
+    hmac(digest, salt, [ikm], prk);
+}
+
+/// HKDF-Expand: expand the pseudorandom key `prk` into `okm.len()` bytes
+/// of output keying material bound to `info`. Fails if `okm` is longer
+/// than `255 * digest.output_bytes()`.
+pub fn expand<D: Digest>(digest: &mut D, prk: &[u8], info: &[u8],
+                         okm: &mut [u8]) {
+    let hs = digest.output_bytes();
+    assert!(okm.len() <= 255 * hs);
+
+    let mut t: SBuf<DefaultAllocator, u8> = SBuf::new_zero(hs);
+    let mut tlen = 0u;
+    let mut pos = 0u;
+    let mut ctr = 1u8;
+
+    while pos < okm.len() {
+        let prev: SBuf<DefaultAllocator, u8> =
+            SBuf::from_slice(t.slice_to(tlen));
+        let c = [ctr];
+        hmac(digest, prk, [prev.as_slice(), info, c.as_slice()],
+             t.as_mut_slice());
+        tlen = hs;
+
+        let n = cmp::min(hs, okm.len() - pos);
+        for i in range(0u, n) {
+            okm[pos + i] = *t.get(i);
+        }
+        pos += n;
+        ctr += 1;
+    }
+}
+
+/// Extract-then-expand: derive `okm.len()` bytes of keying material from
+/// `ikm`, `salt` and `info`. This is equivalent to calling `extract()`
+/// followed by `expand()`.
+pub fn derive<D: Digest>(digest: &mut D, salt: &[u8], ikm: &[u8],
+                         info: &[u8], okm: &mut [u8]) {
+    let mut prk: SBuf<DefaultAllocator, u8> =
+        SBuf::new_zero(digest.output_bytes());
+    extract(digest, salt, ikm, prk.as_mut_slice());
+    expand(digest, prk.as_slice(), info, okm);
+}
+
+/// Derive a scalar from `label` and `parts` framed as
+/// `len(label) || label || len(parts[0]) || parts[0] || ...`, each length
+/// on 8 little-endian bytes, so that distinct lists of parts never give
+/// the same input: 104 bytes derived from the framed input with
+/// `derive()` (empty salt, `label` as info) reduced `mod L`, whose
+/// distribution is statistically close to uniform. The framed input is
+/// held in a wiped buffer, parts may be secret.
+pub fn derive_scalar<D: Digest>(digest: &mut D, label: &[u8],
+                                parts: &[&[u8]]) -> ScalarElem {
+    let len = parts.iter().fold(8 + label.len(), |l, p| l + 8 + p.len());
+    let mut ikm: SBuf<DefaultAllocator, u8> = SBuf::new_zero(len);
+    let mut pos = 0u;
+    let head = [label];
+    for p in head.iter().chain(parts.iter()) {
+        let n = utils::le64(p.len());
+        for b in n.iter().chain(p.iter()) {
+            *ikm.get_mut(pos) = *b;
+            pos += 1;
+        }
+    }
+
+    let mut h: B832 = Bytes::new_zero();
+    derive(digest, [], ikm.as_slice(), label, h.as_mut_bytes());
+    ScalarElem::unpack(&h).unwrap()
+}
+
+
+/// Deterministic generator expanding a seed
+///
+/// The pseudorandom key `PRK = extract(salt = "", ikm = seed)` is computed
+/// once, then output block `i` (starting from `0`) is
+/// `HMAC(PRK, info || i)` with `i` encoded as 8 little-endian bytes. Output
+/// bytes are returned in order, `next_u32()` and `next_u64()` decode them
+/// as little-endian integers. Its output is fully determined by `seed`
+/// and `info`: it is meant for reproducible test vectors or derivations
+/// from a secret high-entropy seed, not as a general purpose PRNG.
+pub struct KdfRng<D> {
+    digest: D,
+    prk: SBuf<DefaultAllocator, u8>,
+    info: Vec<u8>,
+    block: SBuf<DefaultAllocator, u8>,
+    pos: uint,
+    ctr: u64
+}
+
+impl<D: Digest> KdfRng<D> {
+    /// Return a new generator seeded with `seed` and bound to `info`.
+    pub fn new(mut digest: D, seed: &[u8], info: &[u8]) -> KdfRng<D> {
+        let hs = digest.output_bytes();
+        let mut prk: SBuf<DefaultAllocator, u8> = SBuf::new_zero(hs);
+        extract(&mut digest, [], seed, prk.as_mut_slice());
+
+        KdfRng {
+            digest: digest,
+            prk: prk,
+            info: Vec::from_slice(info),
+            block: SBuf::new_zero(hs),
+            pos: hs,
+            ctr: 0
+        }
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        if self.pos == self.block.len() {
+            let mut c = [0u8, ..8];
+            for i in range(0u, 8) {
+                c[i] = (self.ctr >> (8 * i)) as u8;
+            }
+            hmac(&mut self.digest, self.prk.as_slice(),
+                 [self.info.as_slice(), c.as_slice()],
+                 self.block.as_mut_slice());
+            self.ctr += 1;
+            self.pos = 0;
+        }
+        self.pos += 1;
+        *self.block.get(self.pos - 1)
+    }
+}
+
+impl<D: Digest> Rng for KdfRng<D> {
+    fn next_u32(&mut self) -> u32 {
+        let mut r = 0u32;
+        for i in range(0u, 4) {
+            r |= self.next_byte() as u32 << (8 * i);
+        }
+        r
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut r = 0u64;
+        for i in range(0u, 8) {
+            r |= self.next_byte() as u64 << (8 * i);
+        }
+        r
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for d in dest.mut_iter() {
+            *d = self.next_byte();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::{FromHex, ToHex};
+
+    use std::rand::Rng;
+
+    use bytes::{B832, Bytes};
+    use digest::sha256::Sha256;
+    use kdf;
+    use kdf::KdfRng;
+    use sc::ScalarElem;
+
+
+    // RFC 5869, test case 1.
+    #[test]
+    fn test_hkdf_ref() {
+        let ikm = [0x0bu8, ..22];
+        let salt = "000102030405060708090a0b0c".from_hex().unwrap();
+        let info = "f0f1f2f3f4f5f6f7f8f9".from_hex().unwrap();
+        let mut h = Sha256::new();
+
+        let mut prk = [0u8, ..32];
+        kdf::extract(&mut h, salt.as_slice(), ikm, prk);
+        assert!(prk.to_hex().as_slice() ==
+                "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5");
+
+        let mut okm = [0u8, ..42];
+        kdf::expand(&mut h, prk, info.as_slice(), okm);
+        assert!(okm.to_hex().as_slice() ==
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c\
+                 5db02d56ecc4c5bf34007208d5b887185865");
+
+        let mut okm2 = [0u8, ..42];
+        kdf::derive(&mut h, salt.as_slice(), ikm, info.as_slice(), okm2);
+        assert!(okm == okm2);
+    }
+
+    #[test]
+    fn test_derive_scalar() {
+        let mut h = Sha256::new();
+        let mut okm: B832 = Bytes::new_zero();
+        let ikm = b"\x05\0\0\0\0\0\0\0label\x02\0\0\0\0\0\0\0ab\
+                    \0\0\0\0\0\0\0\0\x04\0\0\0\0\0\0\0cdef";
+        kdf::derive(&mut h, [], ikm, b"label", okm.as_mut_bytes());
+        let s = kdf::derive_scalar(&mut h, b"label", [b"ab", b"", b"cdef"]);
+        assert!(s == ScalarElem::unpack(&okm).unwrap());
+        assert!(s != kdf::derive_scalar(&mut h, b"other", [b"ab", b"",
+                                                           b"cdef"]));
+
+        // Same concatenations, different framings.
+        assert!(s != kdf::derive_scalar(&mut h, b"label", [b"abcdef"]));
+        assert!(s != kdf::derive_scalar(&mut h, b"label", [b"ab", b"cdef"]));
+        assert!(kdf::derive_scalar(&mut h, b"lab", [b"elx"]) !=
+                kdf::derive_scalar(&mut h, b"label", [b"x"]));
+    }
+
+    #[test]
+    fn test_kdf_rng() {
+        let mut r1 = KdfRng::new(Sha256::new(), "seed".as_bytes(), []);
+        let mut r2 = KdfRng::new(Sha256::new(), "seed".as_bytes(), []);
+
+        let mut a = [0u8, ..100];
+        r1.fill_bytes(a);
+        assert!(r2.next_u32() as u8 == a[0]);
+        let mut b = [0u8, ..96];
+        r2.fill_bytes(b);
+        assert!(a.slice_from(4) == b.as_slice());
+
+        // First block is HMAC(PRK, le64(0)).
+        let mut h = Sha256::new();
+        let mut prk = [0u8, ..32];
+        kdf::extract(&mut h, [], "seed".as_bytes(), prk);
+        let mut blk = [0u8, ..32];
+        super::hmac(&mut h, prk, [[0u8, ..8].as_slice()], blk);
+        assert!(a.slice_to(32) == blk.as_slice());
+    }
+}
@@ -0,0 +1,1335 @@
+//! Key pairs
+//!
+//! Typed secret and public keys in Edwards representation, as used by
+//! `sign`. A secret key is a clamped scalar `sk` (see
+//! `B416::clamp_41417()`) and its public key the packed point `sk.BP`.
+//! Keys loaded from storage may be checked with `KeyPair::validate()`,
+//! public keys may also be recomputed from their secret keys. Sets of
+//! public keys may be aggregated into a single key for multi-signatures
+//! with `aggregate_public_keys()`. Keys may be tweaked additively or
+//! multiplicatively by a public scalar, e.g. for committing to data in a
+//! key (`tweak_add()`, `tweak_mul()`). Secret keys are stored encrypted
+//! under a password with `SecretKey::seal_with_password()`. Independent
+//! signing and Diffie-Hellman keys may be derived from a single `Seed`.
+//!
+//! Signing secret keys (`SecretKey`) and Diffie-Hellman secret keys
+//! (`DhSecretKey`) are distinct types, so that a key isn't used for both
+//! purposes by accident. Using a key for both is a deliberate choice made
+//! with `SecretKey::unsafe_reinterpret_as_dh()` or
+//! `DhSecretKey::unsafe_reinterpret_as_signing()`. Keys sign with
+//! `SecretKey::sign()` and agree on shared secrets with
+//! `DhSecretKey::shared_secret()`; the raw scalars returned by
+//! `unsafe_expose_scalar()` and wrapped by `unsafe_from_scalar()` carry no
+//! tag and are only meant for the low-level functions of `sign` and `mont`,
+//! which can't tell a signing scalar from a Diffie-Hellman one.
+//! Per-message ephemeral keys (`EphemeralSecret`) are consumed by their
+//! single Diffie-Hellman.
+use serialize::hex::ToHex;
+use std::cell::RefCell;
+use std::fmt::{Show, Formatter};
+use std::fmt;
+use std::hash::{Hash, Writer};
+use std::rand::Rng;
+use std::result;
+
+use aead::Aead;
+use bytes;
+use bytes::{B416, Bytes, EdPoint, MontPoint, Scalar};
+use digest::Digest;
+use ed::{GroupElem, PointTable};
+use error;
+use error::{AuthenticationFailed, DuplicateKey, Error, InvalidLength};
+use error::{Malformed, NotClamped, OffCurve, SmallOrder};
+use kdf;
+use mont;
+use mont::DhPolicy;
+use sbuf::{DefaultAllocator, SBuf};
+use sc::ScalarElem;
+use sign;
+use sign::Signature;
+use utils;
+
+
+/// Clamping policy of `SecretKey::from_raw_bytes()`.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub enum Clamping {
+    /// Clamp the imported scalar, any 52 bytes value is accepted.
+    ClampApply,
+    /// Keep the imported scalar as is but reject it if it is not clamped.
+    ClampVerify
+}
+
+/// Adjustments made by `SecretKey::from_external_bytes()` for clamping an
+/// imported scalar. These bits are fixed in clamped scalars, reporting
+/// them reveals nothing about the imported key.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub struct ClampReport {
+    /// Some of the low 3 bits were set and have been cleared.
+    pub low_bits_cleared: bool,
+    /// Some of the unused top bits 414 and 415 were set and have been
+    /// cleared.
+    pub top_bits_cleared: bool,
+    /// Bit 413 was not set and has been set.
+    pub high_bit_set: bool
+}
+
+impl ClampReport {
+    /// Return `true` iff no adjustment was made: the imported scalar was
+    /// already clamped.
+    pub fn was_clamped(&self) -> bool {
+        !self.low_bits_cleared && !self.top_bits_cleared && !self.high_bit_set
+    }
+}
+
+
+/// Password-based key derivation function supplied by the caller, e.g.
+/// scrypt or Argon2, used for sealing secret keys.
+pub trait PasswordKdf {
+    /// Derive `out.len()` bytes from `password` and `salt` with the cost
+    /// parameters `params`, whose encoding is defined by the
+    /// implementation. Stored envelopes hand back the `params` they were
+    /// sealed with.
+    fn derive(&mut self, password: &[u8], salt: &[u8], params: &[u8],
+              out: &mut [u8]);
+}
+
+static SEALED_KEY_MAGIC: &'static [u8] = b"C4SK\x01";
+static SEALED_KEY_SALT_BYTES: uint = 32;
+
+
+/// Signing secret key, a clamped secret scalar. Its bytes are wiped on
+/// drop.
+#[deriving(Clone)]
+pub struct SecretKey {
+    sk: Scalar
+}
+
+impl SecretKey {
+    fn new(sk: Scalar) -> SecretKey {
+        SecretKey {
+            sk: sk
+        }
+    }
+
+    /// Wrap the secret scalar `sk` as is as a signing key, e.g. returned
+    /// by `GroupElem::keypair()`. The scalar carries no tag, it must not
+    /// also be used for Diffie-Hellman unless deliberately, see
+    /// `unsafe_reinterpret_as_dh()`.
+    pub fn unsafe_from_scalar(sk: Scalar) -> SecretKey {
+        SecretKey::new(sk)
+    }
+
+    /// Import a secret key from the raw 52 bytes little-endian encoding of
+    /// its secret scalar, e.g. generated by another Curve41417
+    /// implementation. With `ClampApply` the scalar is clamped, its public
+    /// key may then differ from the one computed by the original
+    /// implementation if the latter didn't clamp it. With `ClampVerify`
+    /// return `Err(NotClamped)` if the scalar is not clamped. Also return
+    /// `Err(InvalidLength)` if `bytes` isn't 52 bytes long.
+    pub fn from_raw_bytes(bytes: &[u8], clamping: Clamping)
+                          -> result::Result<SecretKey, Error> {
+        try!(error::check_length(bytes, 52));
+        let mut b: B416 = Bytes::from_bytes(bytes).unwrap();
+        match clamping {
+            ClampApply => b.clamp_41417(),
+            ClampVerify => ()
+        }
+
+        let sk = SecretKey::new(Scalar(b));
+        match sk.is_clamped() {
+            true => Ok(sk),
+            false => Err(NotClamped)
+        }
+    }
+
+    /// Import a secret key from a raw 52 bytes scalar which may be clamped,
+    /// partially clamped or not clamped at all, e.g. read from a hardware
+    /// token which may or may not clamp its keys. The scalar is clamped in
+    /// constant time and the adjustments made are returned along with the
+    /// key; no branch depends on the imported bytes. Return
+    /// `Err(InvalidLength)` if `bytes` isn't 52 bytes long.
+    pub fn from_external_bytes(bytes: &[u8])
+                               -> result::Result<(SecretKey, ClampReport),
+                                                 Error> {
+        try!(error::check_length(bytes, 52));
+        let mut b: B416 = Bytes::from_bytes(bytes).unwrap();
+        // Each flag is 1 iff the masked bits are not already clamped.
+        let low = ((*b.get(0) & 7) + 7) >> 3;
+        let top = ((*b.get(51) >> 6) + 3) >> 2;
+        let high = ((*b.get(51) >> 5) & 1) ^ 1;
+        b.clamp_41417();
+        let report = ClampReport {
+            low_bits_cleared: low == 1,
+            top_bits_cleared: top == 1,
+            high_bit_set: high == 1
+        };
+        Ok((SecretKey::new(Scalar(b)), report))
+    }
+
+    /// Return the raw 52 bytes little-endian encoding of the secret scalar,
+    /// `from_raw_bytes()` restores it.
+    pub fn to_raw_bytes(&self) -> B416 {
+        self.sk.get_ref().clone()
+    }
+
+    /// Return the raw secret scalar, for the low-level functions of `sign`.
+    /// It no longer carries the signing tag of this key: nothing prevents
+    /// passing it to the functions of `mont`, see `unsafe_from_scalar()`.
+    pub fn unsafe_expose_scalar<'a>(&'a self) -> &'a Scalar {
+        &self.sk
+    }
+
+    /// Sign `msg` with this key, see `sign::sign()`. The public key is
+    /// recomputed, `KeyPair::sign()` uses the stored one.
+    pub fn sign<D: Digest>(&self, digest: &mut D, msg: &[u8]) -> Signature {
+        let pk = GroupElem::scalar_mult_base(&self.sk);
+        sign::sign(digest, &self.sk, &pk, msg)
+    }
+
+    /// Return `true` iff the secret scalar is clamped.
+    pub fn is_clamped(&self) -> bool {
+        let mut c = self.sk.get_ref().clone();
+        c.clamp_41417();
+        c == *self.sk.get_ref()
+    }
+
+    /// Return the secret key `sk + t mod L`, whose public key is
+    /// `PublicKey::tweak_add(t)` of this key's. Return `None` if the
+    /// tweaked scalar is zero. Tweaked scalars are reduced `mod L` and
+    /// are no longer clamped: `is_clamped()` returns `false` for them,
+    /// `validate()` of a `KeyPair` holding one reports a tweaked secret
+    /// instead, and they sign like any other secret key. Don't convert
+    /// them with `unsafe_reinterpret_as_dh()`: Diffie-Hellman re-clamps
+    /// the scalar, which becomes a different key than the tweaked one.
+    pub fn tweak_add(&self, t: &ScalarElem) -> Option<SecretKey> {
+        SecretKey::from_tweaked(self.to_scalar_elem() + *t)
+    }
+
+    /// Return the secret key `sk.t mod L`, whose public key is
+    /// `PublicKey::tweak_mul(t)` of this key's. Same as `tweak_add()`
+    /// otherwise.
+    pub fn tweak_mul(&self, t: &ScalarElem) -> Option<SecretKey> {
+        SecretKey::from_tweaked(self.to_scalar_elem() * *t)
+    }
+
+    /// Encrypt this secret key under `password` for storage and return
+    /// its envelope: the magic `"C4SK"`, the format version `1`, a random
+    /// 32 bytes salt, the length of `params` on 2 little-endian bytes and
+    /// `params`, a random nonce and the encrypted secret scalar followed
+    /// by its tag. The encryption key is derived with `kdf` from
+    /// `password`, the salt and `params` (the cost parameters of `kdf`),
+    /// and used once with `aead`. Tampering with any part of the envelope
+    /// makes `open_with_password()` fail. Fails if `params` is longer than
+    /// 65535 bytes, return the error of `aead` if it rejects its inputs.
+    pub fn seal_with_password<K: PasswordKdf, A: Aead>(
+        &self, kdf: &mut K, aead: &mut A, password: &[u8], params: &[u8])
+        -> result::Result<Vec<u8>, Error> {
+        assert!(params.len() <= 0xffff);
+        let mut rng = utils::urandom_rng();
+        let mut salt = [0u8, ..SEALED_KEY_SALT_BYTES];
+        rng.fill_bytes(salt.as_mut_slice());
+        let mut nonce = Vec::from_elem(aead.nonce_bytes(), 0u8);
+        rng.fill_bytes(nonce.as_mut_slice());
+
+        let mut key: SBuf<DefaultAllocator, u8> =
+            SBuf::new_zero(aead.key_bytes());
+        kdf.derive(password, salt.as_slice(), params, key.as_mut_slice());
+        let mut ct = Vec::from_elem(52 + aead.tag_bytes(), 0u8);
+        try!(aead.seal(key.as_slice(), nonce.as_slice(),
+                       self.sk.get_ref().as_bytes(), ct.as_mut_slice()));
+
+        let mut r = Vec::from_slice(SEALED_KEY_MAGIC);
+        r.push_all(salt.as_slice());
+        r.push_all([params.len() as u8, (params.len() >> 8) as u8]);
+        r.push_all(params);
+        r.push_all(nonce.as_slice());
+        r.push_all(ct.as_slice());
+        Ok(r)
+    }
+
+    /// Decrypt the envelope `sealed` returned by `seal_with_password()`
+    /// with `password`, `kdf` and `aead` must be the ones it was sealed
+    /// with. Return `Err(Malformed)` if `sealed` isn't an envelope of the
+    /// expected format and sizes, and `Err(AuthenticationFailed)` if the
+    /// password is wrong or the envelope was tampered with.
+    pub fn open_with_password<K: PasswordKdf, A: Aead>(kdf: &mut K,
+                                                       aead: &mut A,
+                                                       password: &[u8],
+                                                       sealed: &[u8])
+                                                       -> result::Result<
+                                                           SecretKey,
+                                                           Error> {
+        let head = SEALED_KEY_MAGIC.len() + SEALED_KEY_SALT_BYTES + 2;
+        if sealed.len() < head ||
+            sealed.slice_to(SEALED_KEY_MAGIC.len()) != SEALED_KEY_MAGIC {
+            return Err(Malformed);
+        }
+        let salt = sealed.slice(SEALED_KEY_MAGIC.len(), head - 2);
+        let plen = sealed[head - 2] as uint | (sealed[head - 1] as uint << 8);
+        let (nb, cb) = (aead.nonce_bytes(), 52 + aead.tag_bytes());
+        if sealed.len() != head + plen + nb + cb {
+            return Err(Malformed);
+        }
+        let params = sealed.slice(head, head + plen);
+        let nonce = sealed.slice(head + plen, head + plen + nb);
+        let ct = sealed.slice_from(head + plen + nb);
+
+        let mut key: SBuf<DefaultAllocator, u8> =
+            SBuf::new_zero(aead.key_bytes());
+        kdf.derive(password, salt, params, key.as_mut_slice());
+        let mut b: B416 = Bytes::new_zero();
+        match aead.open(key.as_slice(), nonce, ct, b.as_mut_bytes()) {
+            true => Ok(SecretKey::new(Scalar(b))),
+            false => Err(AuthenticationFailed)
+        }
+    }
+
+    /// Return this key as a Diffie-Hellman secret key with the same secret
+    /// scalar. A key used for both signing and Diffie-Hellman must only be
+    /// used with protocols designed for such a dual use, e.g. XEdDSA-like
+    /// signatures.
+    pub fn unsafe_reinterpret_as_dh(self) -> DhSecretKey {
+        DhSecretKey::new(self.sk)
+    }
+
+    fn to_scalar_elem(&self) -> ScalarElem {
+        ScalarElem::unpack(self.sk.get_ref()).unwrap()
+    }
+
+    // Return true iff the secret scalar is a non-zero scalar reduced mod L,
+    // the form of tweaked secrets. Clamped scalars have their bit 413 set,
+    // they are never reduced.
+    fn is_tweaked(&self) -> bool {
+        let reduced = ScalarElem::is_canonical(self.sk.get_ref());
+        (reduced & !self.to_scalar_elem().is_zero()).unwrap_u8() == 1
+    }
+
+    fn from_tweaked(n: ScalarElem) -> Option<SecretKey> {
+        match n.is_zero().unwrap_u8() {
+            0 => Some(SecretKey::new(n.pack())),
+            _ => None
+        }
+    }
+}
+
+
+/// Diffie-Hellman secret key, a clamped secret scalar used with `mont`.
+/// Its bytes are wiped on drop.
+#[deriving(Clone)]
+pub struct DhSecretKey {
+    sk: Scalar
+}
+
+impl DhSecretKey {
+    fn new(sk: Scalar) -> DhSecretKey {
+        DhSecretKey {
+            sk: sk
+        }
+    }
+
+    /// Wrap the secret scalar `sk` as is as a Diffie-Hellman key, e.g.
+    /// returned by `mont::keypair()`. Same as
+    /// `SecretKey::unsafe_from_scalar()` otherwise.
+    pub fn unsafe_from_scalar(sk: Scalar) -> DhSecretKey {
+        DhSecretKey::new(sk)
+    }
+
+    /// Generate a new key, see `mont::keypair()`.
+    pub fn generate() -> DhSecretKey {
+        DhSecretKey::generate_from_rng(&mut utils::urandom_rng())
+    }
+
+    /// Same as `generate()` but the secret key is generated from `rng`,
+    /// see `mont::keypair_from_rng()`.
+    pub fn generate_from_rng<R: Rng>(rng: &mut R) -> DhSecretKey {
+        let (_, sk) = mont::keypair_from_rng(rng);
+        DhSecretKey::new(sk)
+    }
+
+    /// Return the raw secret scalar, for the low-level functions of
+    /// `mont`. Same as `SecretKey::unsafe_expose_scalar()` otherwise.
+    pub fn unsafe_expose_scalar<'a>(&'a self) -> &'a Scalar {
+        &self.sk
+    }
+
+    /// Return the public key `sk.BP` of this key.
+    pub fn public_key(&self) -> MontPoint {
+        mont::scalar_mult_base(&self.sk)
+    }
+
+    /// Return the shared secret between this key and the peer's public key
+    /// `pk`, see `mont::shared_secret()`.
+    pub fn shared_secret(&self, pk: &MontPoint, policy: DhPolicy)
+                         -> result::Result<MontPoint, Error> {
+        mont::shared_secret(&self.sk, pk, policy)
+    }
+
+    /// Sign `msg` with this key, the signature verifies against its
+    /// Montgomery public key, see `sign::sign_xeddsa()`. This is the
+    /// deliberate dual use of a Diffie-Hellman key for signing.
+    pub fn sign_xeddsa<D: Digest>(&self, digest: &mut D, msg: &[u8])
+                                  -> Signature {
+        sign::sign_xeddsa(digest, &self.sk, msg)
+    }
+
+    /// Return this key as a signing secret key with the same secret
+    /// scalar, see `SecretKey::unsafe_reinterpret_as_dh()`.
+    pub fn unsafe_reinterpret_as_signing(self) -> SecretKey {
+        SecretKey::new(self.sk)
+    }
+}
+
+
+/// Ephemeral Diffie-Hellman secret key, used for exactly one shared
+/// secret: `diffie_hellman()` takes it by value and it is wiped when
+/// dropped at the end of the call. It cannot be cloned nor its scalar
+/// accessed, so it can't be reused by accident.
+pub struct EphemeralSecret {
+    sk: Scalar
+}
+
+impl EphemeralSecret {
+    /// Generate a new ephemeral key, see `mont::keypair()`.
+    pub fn generate() -> EphemeralSecret {
+        EphemeralSecret::generate_from_rng(&mut utils::urandom_rng())
+    }
+
+    /// Same as `generate()` but the secret key is generated from `rng`.
+    pub fn generate_from_rng<R: Rng>(rng: &mut R) -> EphemeralSecret {
+        let (_, sk) = mont::keypair_from_rng(rng);
+        EphemeralSecret {
+            sk: sk
+        }
+    }
+
+    /// Return the public key `sk.BP` of this key, to be sent to the peer.
+    pub fn public_key(&self) -> MontPoint {
+        mont::scalar_mult_base(&self.sk)
+    }
+
+    /// Consume this key and return its shared secret with the peer's
+    /// public key `pk`, see `mont::shared_secret()`.
+    pub fn diffie_hellman(self, pk: &MontPoint, policy: DhPolicy)
+                          -> result::Result<MontPoint, Error> {
+        mont::shared_secret(&self.sk, pk, policy)
+    }
+}
+
+
+/// Public key, a packed Edwards point. It is formatted by `Show`,
+/// `LowerHex` and `UpperHex` as the hex-string of its packed point.
+/// Secret keys don't implement any formatting trait.
+///
+/// The unpacked point is cached on first use, and so is its table of
+/// multiples once built by `precompute()`, repeated verifications with
+/// the same key then skip the decompression of the key. Public keys
+/// compare equal iff their packed points are equal.
+#[deriving(Clone)]
+pub struct PublicKey {
+    pk: EdPoint,
+    point: RefCell<Option<GroupElem>>,
+    table: RefCell<Option<PointTable>>
+}
+
+impl PublicKey {
+    /// Wrap the packed point `pk` as is, no check is made.
+    pub fn new(pk: EdPoint) -> PublicKey {
+        PublicKey {
+            pk: pk,
+            point: RefCell::new(None),
+            table: RefCell::new(None)
+        }
+    }
+
+    /// Recompute the public key `sk.BP` of the secret key `sk`, e.g. for
+    /// restoring a key pair from its stored secret key only.
+    pub fn from_secret(sk: &SecretKey) -> PublicKey {
+        PublicKey::new(GroupElem::scalar_mult_base(&sk.sk).pack())
+    }
+
+    /// Decode a public key from its 52 bytes encoding, see
+    /// `GroupElem::decode()`.
+    pub fn decode(bytes: &[u8]) -> result::Result<PublicKey, Error> {
+        let p = try!(GroupElem::decode(bytes));
+        Ok(PublicKey::new(p.pack()))
+    }
+
+    /// Return the packed point.
+    pub fn as_point<'a>(&'a self) -> &'a EdPoint {
+        &self.pk
+    }
+
+    /// Return the unpacked point or `None` if it is not on the curve. The
+    /// point is unpacked on the first call only.
+    pub fn to_group_elem(&self) -> Option<GroupElem> {
+        if self.point.borrow().is_none() {
+            *self.point.borrow_mut() = GroupElem::unpack(&self.pk);
+        }
+        (*self.point.borrow()).clone()
+    }
+
+    /// Build and cache the table of multiples of the point (see
+    /// `PointTable::new()`) used by `verify()`: 16 points with the default
+    /// window, about 13KB. Return `false` if the point is not on the
+    /// curve.
+    pub fn precompute(&self) -> bool {
+        if self.table.borrow().is_some() {
+            return true;
+        }
+        match self.to_group_elem() {
+            Some(p) => {
+                *self.table.borrow_mut() = Some(PointTable::new(&p));
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Return the public key `pk + t.BP`, the public key of the secret key
+    /// tweaked by `SecretKey::tweak_add(t)`. Return `Err(OffCurve)` if the
+    /// point of this key is not on the curve and `Err(SmallOrder)` if the
+    /// tweaked point is the neutral element. A torsion-free key remains
+    /// torsion-free, and a `KeyPair` of tweaked keys passes `validate()`
+    /// with `secret_tweaked` set.
+    pub fn tweak_add(&self, t: &ScalarElem)
+                     -> result::Result<PublicKey, Error> {
+        let p = match self.to_group_elem() {
+            Some(p) => p,
+            None => return Err(OffCurve)
+        };
+        PublicKey::from_tweaked(p + GroupElem::scalar_mult_base(&t.pack()))
+    }
+
+    /// Return the public key `t.pk`, the public key of the secret key
+    /// tweaked by `SecretKey::tweak_mul(t)`. Errors are the same as those
+    /// of `tweak_add()`.
+    pub fn tweak_mul(&self, t: &ScalarElem)
+                     -> result::Result<PublicKey, Error> {
+        let p = match self.to_group_elem() {
+            Some(p) => p,
+            None => return Err(OffCurve)
+        };
+        PublicKey::from_tweaked(p.scalar_mult(&t.pack()))
+    }
+
+    fn from_tweaked(p: GroupElem) -> result::Result<PublicKey, Error> {
+        match p.is_identity().unwrap_u8() {
+            1 => Err(SmallOrder),
+            _ => Ok(PublicKey::new(p.pack()))
+        }
+    }
+
+    /// Return `true` iff `precompute()` built the table of this key.
+    pub fn is_precomputed(&self) -> bool {
+        self.table.borrow().is_some()
+    }
+
+    /// Verify the signature `sig` of `msg` with this key, see
+    /// `sign::verify()`. Use the cached point and the table if built by
+    /// `precompute()`. Return `false` if the point is not on the curve.
+    pub fn verify<D: Digest>(&self, digest: &mut D, msg: &[u8],
+                             sig: &Signature) -> bool {
+        let p = match self.to_group_elem() {
+            Some(p) => p,
+            None => return false
+        };
+        match *self.table.borrow() {
+            Some(ref t) => sign::verify_with_table(digest, &p, t, msg, sig),
+            None => sign::verify(digest, &p, msg, sig)
+        }
+    }
+}
+
+impl PartialEq for PublicKey {
+    fn eq(&self, other: &PublicKey) -> bool {
+        self.pk == other.pk
+    }
+}
+
+impl Eq for PublicKey {
+}
+
+impl PartialOrd for PublicKey {
+    fn partial_cmp(&self, other: &PublicKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PublicKey {
+    /// Order of the packed points, see `EdPoint`. Secret keys are not
+    /// ordered.
+    fn cmp(&self, other: &PublicKey) -> Ordering {
+        self.pk.cmp(&other.pk)
+    }
+}
+
+impl<S: Writer> Hash<S> for PublicKey {
+    /// Hash the packed point, consistently with `==`.
+    fn hash(&self, state: &mut S) {
+        self.pk.hash(state)
+    }
+}
+
+impl Show for PublicKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.pk.to_hex())
+    }
+}
+
+impl fmt::LowerHex for PublicKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        bytes::fmt_hex(self.pk.get_ref().as_bytes(), false, f)
+    }
+}
+
+impl fmt::UpperHex for PublicKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        bytes::fmt_hex(self.pk.get_ref().as_bytes(), true, f)
+    }
+}
+
+
+static KEY_LIST_LABEL: &'static [u8] = b"curve41417 key aggregation list";
+static KEY_COEFFICIENT_LABEL: &'static [u8] =
+    b"curve41417 key aggregation coefficient";
+
+
+/// Aggregated public key, see `aggregate_public_keys()`.
+pub struct AggregatePublicKey {
+    key: PublicKey,
+    keys: Vec<PublicKey>,
+    coefficients: Vec<ScalarElem>
+}
+
+impl AggregatePublicKey {
+    /// Return the aggregated key.
+    pub fn key<'a>(&'a self) -> &'a PublicKey {
+        &self.key
+    }
+
+    /// Return the aggregated keys sorted in ascending order.
+    pub fn keys<'a>(&'a self) -> &'a [PublicKey] {
+        self.keys.as_slice()
+    }
+
+    /// Return the coefficients of the keys, in the same order as `keys()`.
+    pub fn coefficients<'a>(&'a self) -> &'a [ScalarElem] {
+        self.coefficients.as_slice()
+    }
+
+    /// Return the coefficient of `pk`, `None` if `pk` is not one of the
+    /// aggregated keys. A signer multiplies its secret scalar by its
+    /// coefficient.
+    pub fn coefficient<'a>(&'a self, pk: &PublicKey)
+                           -> Option<&'a ScalarElem> {
+        self.keys.iter().position(|k| k == pk)
+            .map(|i| self.coefficients.get(i))
+    }
+}
+
+/// Aggregate public keys
+///
+/// MuSig-like aggregation of the public keys `pks` (given in any order):
+/// keys are sorted, then `c = H1(pk1 || ... || pkn)` commits to the whole
+/// set and each key `pki` gets the coefficient `ai = H2(pki, c)`, the
+/// aggregated key is `a1.pk1 + ... + an.pkn`. `H1` is `kdf::derive()` and
+/// `H2` is `GroupElem::hash_encoded_with_domain()`, with distinct labels.
+/// The coefficients prevent rogue-key attacks, the result doesn't depend
+/// on the order of `pks`. Return `Err(Malformed)` if `pks` is empty and
+/// `Err(DuplicateKey)` if a key appears twice. Every key is decoded with
+/// `GroupElem::decode()`, so that a key can't be given twice under two
+/// encodings: its errors (`Err(NonCanonical)`, `Err(OffCurve)`,
+/// `Err(SmallOrder)`) are returned for the first key failing it.
+pub fn aggregate_public_keys<D: Digest>(digest: &mut D, pks: &[PublicKey])
+                                        -> result::Result<AggregatePublicKey,
+                                                          Error> {
+    if pks.len() == 0 {
+        return Err(Malformed);
+    }
+    let mut keys = Vec::from_slice(pks);
+    keys.sort();
+    for i in range(1u, keys.len()) {
+        if keys.get(i - 1) == keys.get(i) {
+            return Err(DuplicateKey);
+        }
+    }
+
+    let mut list = Vec::with_capacity(52 * keys.len());
+    for k in keys.iter() {
+        list.push_all(k.as_point().get_ref().as_bytes());
+    }
+    let mut c = [0u8, ..64];
+    kdf::derive(digest, [], list.as_slice(), KEY_LIST_LABEL, c);
+
+    let mut coefficients = Vec::with_capacity(keys.len());
+    let mut agg = GroupElem::neutral();
+    for k in keys.iter() {
+        let p = try!(GroupElem::decode(k.as_point().get_ref().as_bytes()));
+        let a = GroupElem::hash_encoded_with_domain(digest,
+                                                    KEY_COEFFICIENT_LABEL,
+                                                    k.as_point(),
+                                                    [c.as_slice()]);
+        agg = agg + p * a;
+        coefficients.push(a);
+    }
+
+    Ok(AggregatePublicKey {
+        key: PublicKey::new(agg.pack()),
+        keys: keys,
+        coefficients: coefficients
+    })
+}
+
+
+/// Result of `KeyPair::validate()`, each field reports one check.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub struct KeyPairDiagnostics {
+    /// Result of decoding the public key with
+    /// `GroupElem::decode_canonical()`: `Err(NonCanonical)` or
+    /// `Err(OffCurve)` if it is not the canonical encoding of a point.
+    pub public_encoding: result::Result<(), Error>,
+    /// `true` iff the public key is in the subgroup of order `L`.
+    pub public_torsion_free: bool,
+    /// `true` iff the secret scalar is clamped.
+    pub secret_clamped: bool,
+    /// `true` iff the secret scalar is not clamped but a non-zero scalar
+    /// reduced `mod L`, as returned by `SecretKey::tweak_add()` and
+    /// `SecretKey::tweak_mul()`.
+    pub secret_tweaked: bool,
+    /// `true` iff the public key is `sk.BP` where `sk` is the secret scalar.
+    pub public_matches_secret: bool
+}
+
+impl KeyPairDiagnostics {
+    /// Return `true` iff all the checks passed, the secret scalar being
+    /// either clamped or tweaked.
+    pub fn is_valid(&self) -> bool {
+        self.public_encoding.is_ok() && self.public_torsion_free &&
+            (self.secret_clamped || self.secret_tweaked) &&
+            self.public_matches_secret
+    }
+}
+
+
+/// Secret key and its public key.
+#[deriving(Clone)]
+pub struct KeyPair {
+    secret: SecretKey,
+    public: PublicKey
+}
+
+impl KeyPair {
+    /// Generate a new key pair, see `GroupElem::keypair()`.
+    pub fn generate() -> KeyPair {
+        KeyPair::generate_from_rng(&mut utils::urandom_rng())
+    }
+
+    /// Same as `generate()` but the secret key is generated from `rng`,
+    /// see `GroupElem::keypair_from_rng()`.
+    pub fn generate_from_rng<R: Rng>(rng: &mut R) -> KeyPair {
+        let (pk, sk) = GroupElem::keypair_from_rng(rng);
+        KeyPair::from_parts(SecretKey::new(sk), PublicKey::new(pk.pack()))
+    }
+
+    /// Return the key pair of `secret`, its public key is recomputed with
+    /// `PublicKey::from_secret()`.
+    pub fn from_secret(secret: SecretKey) -> KeyPair {
+        let public = PublicKey::from_secret(&secret);
+        KeyPair::from_parts(secret, public)
+    }
+
+    /// Pair `secret` with `public` as is, e.g. keys loaded from storage.
+    /// Use `validate()` for checking their consistency.
+    pub fn from_parts(secret: SecretKey, public: PublicKey) -> KeyPair {
+        KeyPair {
+            secret: secret,
+            public: public
+        }
+    }
+
+    /// Return the secret key.
+    pub fn secret<'a>(&'a self) -> &'a SecretKey {
+        &self.secret
+    }
+
+    /// Return the public key.
+    pub fn public<'a>(&'a self) -> &'a PublicKey {
+        &self.public
+    }
+
+    /// Sign `msg` with the secret key for the public key, see
+    /// `sign::sign()`. Return `Err(OffCurve)` if the public key is not on
+    /// the curve.
+    pub fn sign<D: Digest>(&self, digest: &mut D, msg: &[u8])
+                           -> result::Result<Signature, Error> {
+        match self.public.to_group_elem() {
+            Some(pk) => Ok(sign::sign(digest, &self.secret.sk, &pk, msg)),
+            None => Err(OffCurve)
+        }
+    }
+
+    /// Check this key pair: the public key must be the canonical encoding
+    /// of a point of the curve, torsion-free, and equal to `sk.BP` with the
+    /// secret scalar `sk` which must be clamped, or reduced `mod L` for the
+    /// secrets returned by `SecretKey::tweak_add()` and
+    /// `SecretKey::tweak_mul()`. Every check is run, see
+    /// `KeyPairDiagnostics::is_valid()` for the overall result.
+    pub fn validate(&self) -> KeyPairDiagnostics {
+        let bytes = self.public.as_point().get_ref().as_bytes();
+        let (encoding, torsion_free) =
+            match GroupElem::decode_canonical(bytes) {
+                Ok(p) => (Ok(()), p.is_torsion_free().unwrap_u8() == 1),
+                Err(e) => {
+                    // Non-canonical encodings of points may still unpack.
+                    let t = match GroupElem::unpack(self.public.as_point()) {
+                        Some(p) => p.is_torsion_free().unwrap_u8() == 1,
+                        None => false
+                    };
+                    (Err(e), t)
+                }
+            };
+
+        let expected = PublicKey::from_secret(&self.secret);
+        KeyPairDiagnostics {
+            public_encoding: encoding,
+            public_torsion_free: torsion_free,
+            secret_clamped: self.secret.is_clamped(),
+            secret_tweaked: self.secret.is_tweaked(),
+            public_matches_secret: expected == self.public
+        }
+    }
+}
+
+
+static SEED_SIGNING_LABEL: &'static [u8] = b"curve41417 seed signing key:";
+static SEED_DH_LABEL: &'static [u8] = b"curve41417 seed dh key:";
+static SEED_MIN_BYTES: uint = 32;
+
+/// Master secret from which keys are derived, held in a wiped buffer.
+pub struct Seed {
+    bytes: SBuf<DefaultAllocator, u8>
+}
+
+impl Seed {
+    /// Generate a new random seed of 52 bytes.
+    pub fn generate() -> Seed {
+        Seed {
+            bytes: SBuf::new_rand(52)
+        }
+    }
+
+    /// Copy the seed `bytes`, e.g. decoded from a backup with
+    /// `mnemonic::decode()`. Return `Err(InvalidLength(32, bytes.len()))`
+    /// if `bytes` is shorter than 32 bytes.
+    pub fn new(bytes: &[u8]) -> result::Result<Seed, Error> {
+        if bytes.len() < SEED_MIN_BYTES {
+            return Err(InvalidLength(SEED_MIN_BYTES, bytes.len()));
+        }
+        Ok(Seed {
+            bytes: SBuf::from_slice(bytes)
+        })
+    }
+
+    /// Return the bytes of this seed, e.g. for backing it up.
+    pub fn as_bytes<'a>(&'a self) -> &'a [u8] {
+        self.bytes.as_slice()
+    }
+}
+
+// Return the clamped scalar derived from seed for purpose and label.
+fn derive_seed_scalar<D: Digest>(digest: &mut D, seed: &Seed,
+                                 purpose: &[u8], label: &str) -> Scalar {
+    let mut info = Vec::from_slice(purpose);
+    info.push_all(label.as_bytes());
+    let mut sk: B416 = Bytes::new_zero();
+    kdf::derive(digest, [], seed.as_bytes(), info.as_slice(),
+                sk.as_mut_bytes());
+    sk.clamp_41417();
+    Scalar(sk)
+}
+
+/// Derive the signing key pair of `seed` for `label`: its secret scalar is
+/// 52 bytes derived with `kdf::derive()` from `seed`, bound to a signing
+/// specific label followed by `label`, then clamped. Distinct labels yield
+/// independent keys, and keys derived by `derive_dh_keypair()` are
+/// independent of signing keys whatever their labels.
+pub fn derive_signing_keypair<D: Digest>(digest: &mut D, seed: &Seed,
+                                         label: &str) -> KeyPair {
+    let sk = SecretKey::new(derive_seed_scalar(digest, seed,
+                                               SEED_SIGNING_LABEL, label));
+    KeyPair::from_secret(sk)
+}
+
+/// Derive the Diffie-Hellman key pair `(pk, sk)` of `seed` for `label`.
+/// Same as `derive_signing_keypair()` but bound to a Diffie-Hellman
+/// specific label.
+pub fn derive_dh_keypair<D: Digest>(digest: &mut D, seed: &Seed,
+                                    label: &str) -> (MontPoint, DhSecretKey) {
+    let sk = DhSecretKey::new(derive_seed_scalar(digest, seed, SEED_DH_LABEL,
+                                                 label));
+    (sk.public_key(), sk)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::ToHex;
+    use std::ascii::StrAsciiExt;
+    use std::collections::HashMap;
+
+    use aead::HmacStream;
+    use bytes::{B416, Bytes, EdPoint, Scalar};
+    use constants;
+    use digest::sha256::Sha256;
+    use ed::GroupElem;
+    use error::{AuthenticationFailed, DuplicateKey, InvalidLength, Malformed};
+    use error::{NonCanonical, NotClamped, OffCurve, SmallOrder};
+    use kdf;
+    use keys;
+    use keys::{ClampApply, ClampReport, ClampVerify, KeyPair, PasswordKdf};
+    use keys::PublicKey;
+    use keys::{DhSecretKey, EphemeralSecret, Seed, SecretKey};
+    use mont;
+    use mont::{Contributory, Raw};
+    use sc::ScalarElem;
+    use sign;
+
+
+    #[test]
+    fn test_validate() {
+        let kp = KeyPair::generate();
+        let d = kp.validate();
+        assert!(d.is_valid() && !d.secret_tweaked);
+        assert!(d.public_encoding == Ok(()));
+
+        // Mismatching public key.
+        let other = KeyPair::generate();
+        let d = KeyPair::from_parts(kp.secret().clone(),
+                                    other.public().clone()).validate();
+        assert!(!d.is_valid() && !d.public_matches_secret);
+        assert!(d.secret_clamped && d.public_torsion_free);
+
+        // Unclamped secret scalar.
+        let mut s = kp.secret().unsafe_expose_scalar().get_ref().clone();
+        *s.get_mut(0) |= 1;
+        let d = KeyPair::from_secret(SecretKey::new(Scalar(s))).validate();
+        assert!(!d.is_valid() && !d.secret_clamped && !d.secret_tweaked);
+        assert!(d.public_matches_secret);
+
+        // Tweaked secret scalars are reported as such, zero is not one.
+        let t = ScalarElem::new_rand();
+        let tk = KeyPair::from_secret(kp.secret().tweak_add(&t).unwrap());
+        let d = tk.validate();
+        assert!(d.is_valid() && d.secret_tweaked && !d.secret_clamped);
+        let d = KeyPair::from_parts(tk.secret().clone(),
+                                    kp.public().clone()).validate();
+        assert!(!d.is_valid() && d.secret_tweaked);
+        let zero = SecretKey::new(ScalarElem::zero().pack());
+        assert!(!KeyPair::from_secret(zero).validate().secret_tweaked);
+
+        // Non-canonical public key encoding (unused bit 414 set).
+        let mut b = kp.public().as_point().get_ref().clone();
+        *b.get_mut(51) |= 0x40;
+        let d = KeyPair::from_parts(kp.secret().clone(),
+                                    PublicKey::new(EdPoint(b))).validate();
+        assert!(d.public_encoding == Err(NonCanonical));
+        assert!(!d.is_valid());
+
+        // Public key with a torsion component, (0, -1) has order 2.
+        let y: B416 = Bytes::from_bytes(constants::P_MINUS_ONE).unwrap();
+        let t = GroupElem::unpack(&EdPoint(y)).unwrap();
+        let pkt = kp.public().to_group_elem().unwrap() + t;
+        let d = KeyPair::from_parts(kp.secret().clone(),
+                                    PublicKey::new(pkt.pack())).validate();
+        assert!(d.public_encoding == Ok(()) && !d.public_torsion_free);
+
+        // Off-curve encodings are reported too.
+        for i in range(0u8, 255) {
+            let mut b: B416 = Bytes::new_zero();
+            *b.get_mut(0) = i;
+            if GroupElem::unpack(&EdPoint(b.clone())).is_none() {
+                let d = KeyPair::from_parts(kp.secret().clone(),
+                                            PublicKey::new(EdPoint(b)))
+                    .validate();
+                assert!(d.public_encoding == Err(OffCurve));
+                assert!(!d.public_torsion_free && !d.public_matches_secret);
+                return;
+            }
+        }
+        fail!("no off-curve encoding found");
+    }
+
+    #[test]
+    fn test_from_secret() {
+        let (pk, sk) = GroupElem::keypair();
+        let secret = SecretKey::unsafe_from_scalar(sk);
+        assert!(*PublicKey::from_secret(&secret).as_point() == pk.pack());
+
+        let kp = KeyPair::generate();
+        let restored = KeyPair::from_secret(kp.secret().clone());
+        assert!(restored.public() == kp.public());
+        assert!(restored.validate().is_valid());
+    }
+
+    #[test]
+    fn test_sign() {
+        let mut h = Sha256::new();
+        let kp = KeyPair::generate();
+        let msg = "message".as_bytes();
+        let sig = kp.sign(&mut h, msg).unwrap();
+        assert!(kp.public().verify(&mut h, msg, &sig));
+        assert!(kp.secret().sign(&mut h, msg) == sig);
+
+        let mut b: B416 = Bytes::new_zero();
+        for i in range(0u8, 255) {
+            *b.get_mut(0) = i;
+            if GroupElem::unpack(&EdPoint(b.clone())).is_none() {
+                break;
+            }
+        }
+        let off = KeyPair::from_parts(kp.secret().clone(),
+                                      PublicKey::new(EdPoint(b)));
+        assert!(off.sign(&mut h, msg).err() == Some(OffCurve));
+    }
+
+    #[test]
+    fn test_tweak() {
+        let mut h = Sha256::new();
+        let kp = KeyPair::generate();
+        let t = ScalarElem::new_rand();
+        let msg = "message".as_bytes();
+
+        let secrets = [kp.secret().tweak_add(&t).unwrap(),
+                       kp.secret().tweak_mul(&t).unwrap()];
+        let publics = [kp.public().tweak_add(&t).unwrap(),
+                       kp.public().tweak_mul(&t).unwrap()];
+        for (sk, pk) in secrets.iter().zip(publics.iter()) {
+            assert!(PublicKey::from_secret(sk) == *pk);
+            assert!(*pk != *kp.public() && !sk.is_clamped());
+            let p = pk.to_group_elem().unwrap();
+            assert!(p.is_torsion_free().unwrap_u8() == 1);
+            let sig = sk.sign(&mut h, msg);
+            assert!(pk.verify(&mut h, msg, &sig));
+        }
+
+        // Tweaks cancelling the key.
+        let s = kp.secret().unsafe_expose_scalar();
+        let sk = ScalarElem::unpack(s.get_ref()).unwrap();
+        assert!(kp.secret().tweak_add(&-sk).is_none());
+        assert!(kp.public().tweak_add(&-sk).err() == Some(SmallOrder));
+        let zero = ScalarElem::zero();
+        assert!(kp.secret().tweak_mul(&zero).is_none());
+        assert!(kp.public().tweak_mul(&zero).err() == Some(SmallOrder));
+
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        assert!(kp.public().tweak_mul(&one).unwrap() == *kp.public());
+    }
+
+    #[test]
+    fn test_raw_bytes() {
+        let kp = KeyPair::generate();
+        let raw = kp.secret().to_raw_bytes();
+        for c in [ClampApply, ClampVerify].iter() {
+            let sk = SecretKey::from_raw_bytes(raw.as_bytes(), *c).unwrap();
+            assert!(sk.to_raw_bytes() == raw);
+            assert!(PublicKey::from_secret(&sk) == *kp.public());
+        }
+
+        let mut b = raw.clone();
+        *b.get_mut(0) |= 7;
+        *b.get_mut(51) |= 0xc0;
+        assert!(SecretKey::from_raw_bytes(b.as_bytes(), ClampVerify).err() ==
+                Some(NotClamped));
+        let sk = SecretKey::from_raw_bytes(b.as_bytes(), ClampApply).unwrap();
+        assert!(sk.to_raw_bytes() == raw);
+
+        assert!(SecretKey::from_raw_bytes(raw.as_bytes().slice_to(51),
+                                          ClampApply).err() ==
+                Some(InvalidLength(52, 51)));
+    }
+
+    #[test]
+    fn test_external_bytes() {
+        let kp = KeyPair::generate();
+        let raw = kp.secret().to_raw_bytes();
+        let (sk, report) = SecretKey::from_external_bytes(raw.as_bytes())
+            .unwrap();
+        assert!(sk.to_raw_bytes() == raw && report.was_clamped());
+
+        // Flip clamped bits of raw: (byte, mask, expected adjustments).
+        let cases = [(0u, 0x01u8, (true, false, false)),
+                     (0, 0x05, (true, false, false)),
+                     (51, 0x40, (false, true, false)),
+                     (51, 0x80, (false, true, false)),
+                     (51, 0x20, (false, false, true)),
+                     (51, 0xe0, (false, true, true))];
+        for &(i, mask, (low, top, high)) in cases.iter() {
+            let mut b = raw.clone();
+            *b.get_mut(i) ^= mask;
+            let (sk, report) = SecretKey::from_external_bytes(b.as_bytes())
+                .unwrap();
+            assert!(sk.to_raw_bytes() == raw && sk.is_clamped());
+            assert!(report == ClampReport {
+                low_bits_cleared: low,
+                top_bits_cleared: top,
+                high_bit_set: high
+            });
+            assert!(!report.was_clamped());
+        }
+
+        assert!(SecretKey::from_external_bytes([0u8, ..51]).err() ==
+                Some(InvalidLength(52, 51)));
+    }
+
+    #[test]
+    fn test_cached_verify() {
+        let mut h = Sha256::new();
+        let kp = KeyPair::generate();
+        let msg = "message".as_bytes();
+        let pk = kp.public().to_group_elem().unwrap();
+        let sig = sign::sign(&mut h, kp.secret().unsafe_expose_scalar(), &pk,
+                             msg);
+
+        let public = PublicKey::new(kp.public().as_point().clone());
+        assert!(!public.is_precomputed());
+        assert!(public.verify(&mut h, msg, &sig));
+        assert!(public.to_group_elem().unwrap() == pk);
+        assert!(public.precompute() && public.is_precomputed());
+        assert!(public.verify(&mut h, msg, &sig));
+        assert!(!public.verify(&mut h, "other".as_bytes(), &sig));
+        assert!(public == *kp.public());
+
+        let mut b: B416 = Bytes::new_zero();
+        for i in range(0u8, 255) {
+            *b.get_mut(0) = i;
+            if GroupElem::unpack(&EdPoint(b.clone())).is_none() {
+                break;
+            }
+        }
+        let off = PublicKey::new(EdPoint(b));
+        assert!(!off.precompute() && !off.verify(&mut h, msg, &sig));
+    }
+
+    #[test]
+    fn test_aggregate_public_keys() {
+        let mut h = Sha256::new();
+        let kps = Vec::from_fn(3, |_| KeyPair::generate());
+        let pks: Vec<PublicKey> =
+            kps.iter().map(|kp| kp.public().clone()).collect();
+        let agg = keys::aggregate_public_keys(&mut h, pks.as_slice())
+            .unwrap();
+        assert!(agg.keys().len() == 3 && agg.coefficients().len() == 3);
+        assert!(agg.keys()[0] < agg.keys()[1]);
+        assert!(agg.keys()[1] < agg.keys()[2]);
+
+        // Independent of the order of the keys.
+        let rev: Vec<PublicKey> = pks.iter().rev().map(|pk| pk.clone())
+            .collect();
+        let agg2 = keys::aggregate_public_keys(&mut h, rev.as_slice())
+            .unwrap();
+        assert!(agg2.key() == agg.key());
+
+        // The aggregated secret scalar is the sum of the weighted secrets.
+        let mut sk = ScalarElem::zero();
+        for kp in kps.iter() {
+            let b = kp.secret().unsafe_expose_scalar().get_ref();
+            let s = ScalarElem::unpack(b).unwrap();
+            sk = sk + s * *agg.coefficient(kp.public()).unwrap();
+        }
+        let expected = GroupElem::scalar_mult_base(&sk.pack());
+        assert!(*agg.key().as_point() == expected.pack());
+        assert!(agg.coefficient(KeyPair::generate().public()).is_none());
+
+        // A different set gives a different key and coefficients.
+        let agg3 = keys::aggregate_public_keys(&mut h, pks.slice_to(2))
+            .unwrap();
+        assert!(agg3.key() != agg.key());
+
+        let mut dup = pks.clone();
+        dup.push(pks.get(1).clone());
+        assert!(keys::aggregate_public_keys(&mut h, dup.as_slice()).err() ==
+                Some(DuplicateKey));
+
+        let mut b: B416 = Bytes::new_zero();
+        for i in range(0u8, 255) {
+            *b.get_mut(0) = i;
+            if GroupElem::unpack(&EdPoint(b.clone())).is_none() {
+                break;
+            }
+        }
+        let mut off = pks.clone();
+        off.push(PublicKey::new(EdPoint(b)));
+        assert!(keys::aggregate_public_keys(&mut h, off.as_slice()).err() ==
+                Some(OffCurve));
+
+        // The same key under the alias with bit 414 set, and the neutral
+        // element.
+        let mut alias = pks.get(0).as_point().get_ref().clone();
+        *alias.get_mut(51) |= 0x40;
+        let mut aliased = pks.clone();
+        aliased.push(PublicKey::new(EdPoint(alias)));
+        assert!(keys::aggregate_public_keys(&mut h, aliased.as_slice())
+                .err() == Some(NonCanonical));
+        let mut id: B416 = Bytes::new_zero();
+        *id.get_mut(0) = 1;
+        let mut small = pks.clone();
+        small.push(PublicKey::new(EdPoint(id)));
+        assert!(keys::aggregate_public_keys(&mut h, small.as_slice())
+                .err() == Some(SmallOrder));
+        assert!(keys::aggregate_public_keys(&mut h, []).err() ==
+                Some(Malformed));
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut v = Vec::from_fn(4, |_| KeyPair::generate().public().clone());
+        let dup = v.get(1).clone();
+        v.push(dup);
+        v.sort();
+        v.dedup();
+        assert!(v.len() == 4);
+        for i in range(1u, v.len()) {
+            assert!(v.get(i - 1) < v.get(i));
+            assert!(v.get(i - 1).as_point() < v.get(i).as_point());
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        let kp = KeyPair::generate();
+        let mut map = HashMap::new();
+        map.insert(kp.public().clone(), "peer");
+        kp.public().precompute();
+        let pk = PublicKey::new(kp.public().as_point().clone());
+        assert!(map.find(&pk) == Some(&"peer"));
+        assert!(map.find(KeyPair::generate().public()).is_none());
+    }
+
+    #[test]
+    fn test_fmt() {
+        let pk = KeyPair::generate().public().clone();
+        let hex = pk.as_point().to_hex();
+        assert!(format!("{}", pk) == hex);
+        assert!(format!("{:x}", pk) == hex);
+        assert!(format!("{:X}", pk) == hex.as_slice().to_ascii_upper());
+        assert!(format!("{:#x}", pk) == format!("0x{}", hex));
+        assert!(format!("{:>106x}", pk) == format!("  {}", hex));
+    }
+
+    // Stand-in for a real password hash: one HKDF call.
+    struct TestKdf;
+
+    impl PasswordKdf for TestKdf {
+        fn derive(&mut self, password: &[u8], salt: &[u8], params: &[u8],
+                  out: &mut [u8]) {
+            let mut ikm = Vec::from_slice(password);
+            ikm.push_all(params);
+            kdf::derive(&mut Sha256::new(), salt, ikm.as_slice(),
+                        b"test kdf", out);
+        }
+    }
+
+    #[test]
+    fn test_seal_with_password() {
+        let (mut k, mut a) = (TestKdf, HmacStream::new(Sha256::new()));
+        let sk = KeyPair::generate().secret().clone();
+        let sealed = sk.seal_with_password(&mut k, &mut a, b"hunter2",
+                                           b"n=1").unwrap();
+        assert!(sealed.len() == 5 + 32 + 2 + 3 + 24 + 52 + 32);
+        assert!(sealed.slice_to(5) == b"C4SK\x01");
+        let other = sk.seal_with_password(&mut k, &mut a, b"hunter2",
+                                          b"n=1").unwrap();
+        assert!(other != sealed);
+
+        let opened = SecretKey::open_with_password(&mut k, &mut a,
+                                                   b"hunter2",
+                                                   sealed.as_slice());
+        assert!(opened.unwrap().unsafe_expose_scalar() ==
+                sk.unsafe_expose_scalar());
+        let r = SecretKey::open_with_password(&mut k, &mut a, b"hunter3",
+                                              sealed.as_slice());
+        assert!(r.err() == Some(AuthenticationFailed));
+
+        // Tampering with the salt, the parameters, the nonce or the
+        // ciphertext.
+        for i in [5u, 40, 42, 70, sealed.len() - 1].iter() {
+            let mut t = sealed.clone();
+            *t.get_mut(*i) ^= 1;
+            let r = SecretKey::open_with_password(&mut k, &mut a, b"hunter2",
+                                                  t.as_slice());
+            assert!(r.err() == Some(AuthenticationFailed));
+        }
+
+        let bad = [sealed.slice_to(sealed.len() - 1), sealed.slice_to(20),
+                   sealed.slice_from(1)];
+        for b in bad.iter() {
+            let r = SecretKey::open_with_password(&mut k, &mut a, b"hunter2",
+                                                  *b);
+            assert!(r.err() == Some(Malformed));
+        }
+        let mut t = sealed.clone();
+        *t.get_mut(38) ^= 1;
+        let r = SecretKey::open_with_password(&mut k, &mut a, b"hunter2",
+                                              t.as_slice());
+        assert!(r.err() == Some(Malformed));
+    }
+
+    #[test]
+    fn test_seed() {
+        let mut h = Sha256::new();
+        assert!(Seed::new([1u8, ..31]).err() == Some(InvalidLength(32, 31)));
+        let seed = Seed::new([1u8, ..32]).unwrap();
+        assert!(Seed::generate().as_bytes().len() == 52);
+
+        let kp = keys::derive_signing_keypair(&mut h, &seed, "main");
+        assert!(kp.validate().is_valid());
+        let again = keys::derive_signing_keypair(&mut h, &seed, "main");
+        assert!(again.public() == kp.public());
+        let other = keys::derive_signing_keypair(&mut h, &seed, "backup");
+        assert!(other.public() != kp.public());
+        let other_seed = Seed::new([2u8, ..32]).unwrap();
+        let other = keys::derive_signing_keypair(&mut h, &other_seed, "main");
+        assert!(other.public() != kp.public());
+
+        let (pk, sk) = keys::derive_dh_keypair(&mut h, &seed, "main");
+        assert!(pk == mont::scalar_mult_base(sk.unsafe_expose_scalar()));
+        assert!(sk.unsafe_expose_scalar() !=
+                kp.secret().unsafe_expose_scalar());
+        let (_, again) = keys::derive_dh_keypair(&mut h, &seed, "main");
+        assert!(again.unsafe_expose_scalar() == sk.unsafe_expose_scalar());
+        let (_, other) = keys::derive_dh_keypair(&mut h, &seed, "backup");
+        assert!(other.unsafe_expose_scalar() != sk.unsafe_expose_scalar());
+    }
+
+    #[test]
+    fn test_dh_secret_key() {
+        let a = DhSecretKey::generate();
+        let b = DhSecretKey::generate();
+        let pa = a.public_key();
+        assert!(pa == mont::scalar_mult_base(a.unsafe_expose_scalar()));
+        let s = a.shared_secret(&b.public_key(), Contributory).unwrap();
+        assert!(s == b.shared_secret(&pa, Raw).unwrap());
+        let mut h = Sha256::new();
+        let sig = a.sign_xeddsa(&mut h, "message".as_bytes());
+        assert!(sign::verify_xeddsa(&mut h, &pa, "message".as_bytes(), &sig));
+
+        let kp = KeyPair::generate();
+        let sk = kp.secret().clone().unsafe_reinterpret_as_dh();
+        assert!(sk.unsafe_expose_scalar() ==
+                kp.secret().unsafe_expose_scalar());
+        let back = sk.unsafe_reinterpret_as_signing();
+        assert!(back.unsafe_expose_scalar() ==
+                kp.secret().unsafe_expose_scalar());
+    }
+
+    #[test]
+    fn test_ephemeral_secret() {
+        let e = EphemeralSecret::generate();
+        let peer = DhSecretKey::generate();
+        let epk = e.public_key();
+        let s = e.diffie_hellman(&peer.public_key(), Contributory).unwrap();
+        assert!(s == peer.shared_secret(&epk, Contributory).unwrap());
+        let e = EphemeralSecret::generate();
+        assert!(e.public_key() != epk);
+    }
+}
@@ -20,12 +20,57 @@
 #[cfg(test)] extern crate debug;
 extern crate alloc;
 extern crate libc;
+#[cfg(feature = "bigint")] extern crate num;
 extern crate serialize;
+#[cfg(feature = "bench")] extern crate time;
 
 mod utils;
+mod limbs;
 mod sbuf;
+mod hooks;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(not(feature = "audit"))]
+mod audit;
+#[cfg(feature = "vectors")]
+pub mod vectors;
+#[cfg(not(feature = "vectors"))]
+mod vectors;
+pub mod choice;
 pub mod bytes;
-mod fe;
+pub mod constants;
+pub mod fe;
 pub mod mont;
 pub mod ed;
 pub mod sc;
+pub mod group;
+pub mod poly;
+pub mod digest;
+pub mod kdf;
+pub mod aead;
+pub mod box_;
+pub mod error;
+pub mod os_random;
+pub mod fast_rng;
+pub mod keys;
+pub mod transcript;
+pub mod sign;
+pub mod pki;
+pub mod sigfile;
+pub mod mnemonic;
+pub mod prelude;
+pub mod testing;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "reference")]
+pub mod reference;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "bigint")]
+pub mod bigint;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "interop")]
+pub mod interop;
+
+pub use sbuf::{Stats, stats};
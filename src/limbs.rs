@@ -0,0 +1,91 @@
+// Carry propagation and folding of signed limbs.
+//
+// Scalars and field elements are stored as little-endian i64 limbs of
+// radix 2^bits (2^8 for scalars, 2^16 for field elements) holding values
+// modulo 2^(bits.len) - F where the reduction polynomial F is given by its
+// limbs fold[k] of weight 2^(bits.(at + k)). A carry out of the top limb,
+// of weight 2^(bits.len) = F, is then reduced by adding carry.fold[k] to
+// limb at + k.
+
+
+// Propagate the carries of n from limb 0 to its top limb, the top carry
+// is folded back with fold at limb at. Limbs are biased by 2^bits before
+// being split so that negative limbs are handled like positive ones. On
+// output every limb is in [0, 2^bits - 1] but those the top carry was
+// folded into, whose magnitude is bounded by that of the top carry times
+// the largest fold limb; a second call brings them close to canonical.
+// Requirements: at + foldlen <= nlen, bits < 32
+pub fn carry(n: &mut [i64], bits: uint, fold: &[i64], at: uint) {
+    assert!(at + fold.len() <= n.len());
+    let top = n.len() - 1;
+    let mut c: i64;
+
+    for i in range(0u, top) {
+        n[i] += 1_i64 << bits;
+        c = n[i] >> bits;
+        n[i + 1] += c - 1;
+        n[i] -= c << bits;
+    }
+
+    n[top] += 1_i64 << bits;
+    c = n[top] >> bits;
+    n[top] -= c << bits;
+    mul_add(n.mut_slice(at, at + fold.len()), c - 1, fold);
+}
+
+// Add h.fold[k] to n[k] for every limb of fold, e.g. for folding a limb
+// of weight 2^(bits.len) into lower limbs.
+// Requirement: nlen >= foldlen
+#[inline]
+pub fn mul_add(n: &mut [i64], h: i64, fold: &[i64]) {
+    assert!(n.len() >= fold.len());
+    for (d, f) in n.mut_iter().zip(fold.iter()) {
+        *d += h * *f;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use limbs;
+
+
+    // Return the value of the limbs n of radix 2^bits mod m.
+    fn value(n: &[i64], bits: uint, m: i64) -> i64 {
+        let mut v = 0i64;
+        for l in n.iter().rev() {
+            v = ((v << bits) + *l) % m;
+        }
+        (v + m) % m
+    }
+
+    #[test]
+    fn test_carry() {
+        // Radix 2^4, 3 limbs: values mod 2^12 - 3.
+        let m = (1i64 << 12) - 3;
+        let inputs: [[i64, ..3], ..4] = [
+            [17, 300, -5], [0, 0, 16], [-1, -1, -1], [4095, 4095, 4095]];
+        for n in inputs.iter() {
+            let mut r = *n;
+            limbs::carry(r, 4, [3], 0);
+            limbs::carry(r, 4, [3], 0);
+            assert!(value(r, 4, m) == value(*n, 4, m));
+            assert!(r.slice_from(1).iter().all(|l| *l >= 0 && *l < 16));
+        }
+
+        // Radix 2^8, fold of 2 limbs at limb 1: values mod 2^24 - 2^8.5.
+        let m = (1i64 << 24) - (5 << 8);
+        let mut r = [1000i64, -77, 12345];
+        let v = value(r, 8, m);
+        limbs::carry(r, 8, [5, 0], 1);
+        assert!(value(r, 8, m) == v);
+        assert!(r[0] >= 0 && r[0] < 256);
+    }
+
+    #[test]
+    fn test_mul_add() {
+        let mut n = [1i64, 2, 3, 4];
+        limbs::mul_add(n.mut_slice_from(1), -2, [1, 10]);
+        assert!(n == [1, 0, -17, 4]);
+    }
+}
@@ -0,0 +1,274 @@
+//! Mnemonic encoding of seeds
+//!
+//! Seeds of 32 to 52 bytes, e.g. the secret scalar of a key or a seed it
+//! is derived from, are encoded as a sequence of words from a wordlist of
+//! 2048 words for backups written down by hand. The encoded bits are the
+//! length of the seed on one byte, the seed and the leading bits of the
+//! digest of both as a checksum, at least 8 bits and as many as needed for
+//! filling the last word; each word encodes 11 bits, most significant
+//! first. Wordlists are supplied by the caller (see `Wordlist`), the
+//! mnemonic of a seed depends on its wordlist and on its digest.
+//!
+//! Intermediate buffers are wiped, but the mnemonic returned by `encode()`
+//! is a plain `String` and the caller is responsible for its lifetime.
+use std::result;
+
+use digest::Digest;
+use error::{BadChecksum, Error, InvalidLength, Malformed, UnsupportedLength};
+use sbuf::{DefaultAllocator, SBuf};
+use utils;
+
+
+/// Number of words of a wordlist.
+pub static WORDLIST_LEN: uint = 2048;
+
+static MIN_SEED_BYTES: uint = 32;
+static MAX_SEED_BYTES: uint = 52;
+static SEED_LENGTHS: &'static [uint] = &[32, 33, 34, 35, 36, 37, 38, 39, 40,
+                                         41, 42, 43, 44, 45, 46, 47, 48, 49,
+                                         50, 51, 52];
+
+
+/// List of `WORDLIST_LEN` distinct words.
+pub trait Wordlist {
+    /// Return the word of index `index`, in `[0, WORDLIST_LEN - 1]`.
+    fn word<'a>(&'a self, index: uint) -> &'a str;
+
+    /// Return the index of `word`, or `None` if it isn't in this list.
+    fn index_of(&self, word: &str) -> Option<uint>;
+}
+
+/// Wordlist backed by a slice of words, e.g. the English BIP-39 list.
+pub struct SliceWordlist<'a> {
+    words: &'a [&'a str]
+}
+
+impl<'a> SliceWordlist<'a> {
+    /// Wrap `words`. Fails unless it holds `WORDLIST_LEN` words, which
+    /// must be distinct.
+    pub fn new(words: &'a [&'a str]) -> SliceWordlist<'a> {
+        assert!(words.len() == WORDLIST_LEN);
+        SliceWordlist {
+            words: words
+        }
+    }
+}
+
+impl<'a> Wordlist for SliceWordlist<'a> {
+    fn word<'b>(&'b self, index: uint) -> &'b str {
+        self.words[index]
+    }
+
+    /// Every word of the list is compared to `word`, whatever its index.
+    fn index_of(&self, word: &str) -> Option<uint> {
+        let mut r = None;
+        for (i, w) in self.words.iter().enumerate() {
+            if utils::bytes_eq(w.as_bytes(), word.as_bytes()) {
+                r = Some(i);
+            }
+        }
+        r
+    }
+}
+
+// Return the number of words of the mnemonic of a seed of len bytes: the
+// least number of words holding its length byte, len bytes and 8 bits.
+fn word_count(len: uint) -> uint {
+    (8 * len + 16 + 10) / 11
+}
+
+// Return the bits encoded by the mnemonic of seed, padded to whole bytes
+// with zero bits.
+fn payload<D: Digest>(digest: &mut D, seed: &[u8])
+                      -> SBuf<DefaultAllocator, u8> {
+    let (n, bits) = (seed.len(), 11 * word_count(seed.len()));
+    let mut p: SBuf<DefaultAllocator, u8> = SBuf::new_zero((bits + 7) / 8);
+    *p.get_mut(0) = n as u8;
+    for (d, s) in p.as_mut_slice().mut_slice(1, n + 1).mut_iter()
+        .zip(seed.iter()) {
+        *d = *s;
+    }
+
+    assert!(digest.output_bytes() >= 3);
+    let mut h: SBuf<DefaultAllocator, u8> =
+        SBuf::new_zero(digest.output_bytes());
+    digest.reset();
+    digest.input(p.as_slice().slice_to(n + 1));
+    digest.result(h.as_mut_slice());
+    digest.reset();
+    for (d, s) in p.as_mut_slice().mut_slice_from(n + 1).mut_iter()
+        .zip(h.as_slice().iter()) {
+        *d = *s;
+    }
+    if bits % 8 != 0 {
+        let last = p.len() - 1;
+        *p.get_mut(last) &= 0xff << (8 - bits % 8);
+    }
+    p
+}
+
+// Return the 11 bits of word i of b.
+fn read_index(b: &[u8], i: uint) -> uint {
+    let mut r = 0u;
+    for k in range(0u, 11) {
+        let bit = 11 * i + k;
+        r = (r << 1) | ((b[bit / 8] >> (7 - bit % 8)) & 1) as uint;
+    }
+    r
+}
+
+// Or the 11 bits of index into word i of b.
+fn write_index(b: &mut [u8], i: uint, index: uint) {
+    for k in range(0u, 11) {
+        let bit = 11 * i + k;
+        b[bit / 8] |= (((index >> (10 - k)) & 1) as u8) << (7 - bit % 8);
+    }
+}
+
+/// Return the mnemonic of `seed`, its words from `wordlist` separated by
+/// single spaces. `digest` computes the checksum. Return
+/// `Err(UnsupportedLength)` unless `seed` is 32 to 52 bytes long.
+pub fn encode<D: Digest, W: Wordlist>(digest: &mut D, wordlist: &W,
+                                      seed: &[u8])
+                                      -> result::Result<String, Error> {
+    if seed.len() < MIN_SEED_BYTES || seed.len() > MAX_SEED_BYTES {
+        return Err(UnsupportedLength(SEED_LENGTHS, seed.len()));
+    }
+    let p = payload(digest, seed);
+    let mut s = String::new();
+    for i in range(0u, word_count(seed.len())) {
+        if i > 0 {
+            s.push_char(' ');
+        }
+        s.push_str(wordlist.word(read_index(p.as_slice(), i)));
+    }
+    Ok(s)
+}
+
+/// Decode `mnemonic`, returned by `encode()` with the same digest and
+/// wordlist, into `out` and return the length of the seed. Words may be
+/// separated by any whitespace but must match the words of `wordlist`
+/// exactly. Return `Err(Malformed)` if a word isn't in `wordlist` or if
+/// the number of words doesn't match the encoded seed length, and
+/// `Err(BadChecksum)` if the checksum doesn't match, e.g. after a word was
+/// misspelled into another one. Return `Err(InvalidLength(52, len))` if
+/// `out` is shorter than 52 bytes.
+pub fn decode<D: Digest, W: Wordlist>(digest: &mut D, wordlist: &W,
+                                      mnemonic: &str, out: &mut [u8])
+                                      -> result::Result<uint, Error> {
+    if out.len() < MAX_SEED_BYTES {
+        return Err(InvalidLength(MAX_SEED_BYTES, out.len()));
+    }
+    let words: Vec<&str> = mnemonic.words().collect();
+    let w = words.len();
+    if w < word_count(MIN_SEED_BYTES) || w > word_count(MAX_SEED_BYTES) {
+        return Err(Malformed);
+    }
+
+    let mut p: SBuf<DefaultAllocator, u8> = SBuf::new_zero((11 * w + 7) / 8);
+    for (i, word) in words.iter().enumerate() {
+        match wordlist.index_of(*word) {
+            Some(index) if index < WORDLIST_LEN =>
+                write_index(p.as_mut_slice(), i, index),
+            _ => return Err(Malformed)
+        }
+    }
+    let n = *p.get(0) as uint;
+    if n < MIN_SEED_BYTES || n > MAX_SEED_BYTES || word_count(n) != w {
+        return Err(Malformed);
+    }
+
+    let seed = p.as_slice().slice(1, n + 1);
+    if !utils::bytes_eq(payload(digest, seed).as_slice(), p.as_slice()) {
+        return Err(BadChecksum);
+    }
+    for (d, s) in out.mut_iter().zip(seed.iter()) {
+        *d = *s;
+    }
+    Ok(n)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use digest::sha256::Sha256;
+    use error::{BadChecksum, InvalidLength, Malformed, UnsupportedLength};
+    use mnemonic;
+    use mnemonic::SliceWordlist;
+
+
+    fn words() -> Vec<String> {
+        Vec::from_fn(mnemonic::WORDLIST_LEN, |i| format!("w{}", i))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let owned = words();
+        let list: Vec<&str> = owned.iter().map(|w| w.as_slice()).collect();
+        let wl = SliceWordlist::new(list.as_slice());
+        let mut h = Sha256::new();
+        for &(len, count) in [(32u, 25u), (41, 32), (52, 40)].iter() {
+            let seed = Vec::from_fn(len, |i| (i * 7 + 1) as u8);
+            let m = mnemonic::encode(&mut h, &wl, seed.as_slice()).unwrap();
+            assert!(m.as_slice().words().count() == count);
+            // Length byte followed by the first 3 bits of the seed.
+            let first = format!("w{}", len << 3);
+            assert!(m.as_slice().words().next() == Some(first.as_slice()));
+
+            let mut out = [0u8, ..52];
+            let spaced = m.as_slice().replace(" ", " \n  ");
+            for s in [m.clone(), spaced].iter() {
+                let n = mnemonic::decode(&mut h, &wl, s.as_slice(),
+                                         out.as_mut_slice());
+                assert!(n == Ok(len));
+                assert!(out.slice_to(len) == seed.as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let owned = words();
+        let list: Vec<&str> = owned.iter().map(|w| w.as_slice()).collect();
+        let wl = SliceWordlist::new(list.as_slice());
+        let mut h = Sha256::new();
+        let seed = [0x5au8, ..32];
+        let m = mnemonic::encode(&mut h, &wl, seed).unwrap();
+        let ws: Vec<&str> = m.as_slice().words().collect();
+        let mut out = [0u8, ..52];
+
+        let mut unknown: Vec<&str> = ws.clone();
+        *unknown.get_mut(3) = "nope";
+        let unknown = unknown.connect(" ");
+        let short = ws.slice_to(24).connect(" ");
+        let long = format!("{} w0", m);
+        let empty = String::new();
+        for s in [unknown, short, long, empty].iter() {
+            assert!(mnemonic::decode(&mut h, &wl, s.as_slice(),
+                                     out.as_mut_slice()) == Err(Malformed));
+        }
+
+        // Another valid word: the seed or the checksum no longer match.
+        let mut bad: Vec<&str> = ws.clone();
+        *bad.get_mut(7) = if *ws.get(7) == "w1" { "w2" } else { "w1" };
+        let s = bad.connect(" ");
+        assert!(mnemonic::decode(&mut h, &wl, s.as_slice(),
+                                 out.as_mut_slice()) == Err(BadChecksum));
+        let mut bad: Vec<&str> = ws.clone();
+        *bad.get_mut(24) = if *ws.get(24) == "w1" { "w2" } else { "w1" };
+        let s = bad.connect(" ");
+        assert!(mnemonic::decode(&mut h, &wl, s.as_slice(),
+                                 out.as_mut_slice()) == Err(BadChecksum));
+
+        assert!(mnemonic::decode(&mut h, &wl, m.as_slice(),
+                                 out.mut_slice_to(51)) ==
+                Err(InvalidLength(52, 51)));
+        for &len in [0u, 31, 53].iter() {
+            let seed = Vec::from_elem(len, 1u8);
+            match mnemonic::encode(&mut h, &wl, seed.as_slice()) {
+                Err(UnsupportedLength(_, l)) => assert!(l == len),
+                _ => fail!()
+            }
+        }
+    }
+}
@@ -1,20 +1,27 @@
 //! Montgomery-form Curve41417 representation
 //!
 //! Generate public and private keys in Montgomery's representation
-//! and handle scalar multiplications.
+//! and handle scalar multiplications. Diffie-Hellman shared secrets may be
+//! computed with or without contributory checks (see `DhPolicy`).
+//! `LadderState` may also run a scalar multiplication incrementally.
+use std::cmp;
+use std::rand::{Rand, Rng};
+use std::result;
+
+use audit;
 use bytes::{B416, Bytes, MontPoint, Scalar};
+use constants;
+use digest::Digest;
+use ed::GroupElem;
+use error;
+use error::{Error, NonCanonical, OffCurve, SmallOrder, ZeroSharedSecret};
 use fe::FieldElem;
+use kdf;
+use sbuf::{DefaultAllocator, SBuf};
+use utils;
+use vectors;
 
 
-static BASEX: [u8, ..52] = [
-    0x0e, 0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0,
-    0xc1, 0x07, 0x1f, 0x7c, 0xf0, 0xc1, 0x07, 0x1f,
-    0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0, 0xc1,
-    0x07, 0x1f, 0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c,
-    0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0, 0xc1, 0x07,
-    0x1f, 0x7c, 0xf0, 0xc1, 0x07, 0x1f, 0x7c, 0xf0,
-    0xc1, 0x07, 0x1f, 0x3c];
-
 static A24: [u8, ..52] = [
     0x54, 0x36, 0x68, 0xf2, 0x65, 0x83, 0x26, 0x5f,
     0x36, 0x68, 0xf2, 0x65, 0x83, 0x26, 0x5f, 0x36,
@@ -25,7 +32,7 @@ static A24: [u8, ..52] = [
     0x26, 0x5f, 0x36, 0x26];
 
 fn basex() -> MontPoint {
-    MontPoint(Bytes::from_bytes(BASEX).unwrap())
+    constants::base_point_mont()
 }
 
 fn a24() -> FieldElem {
@@ -34,64 +41,324 @@ fn a24() -> FieldElem {
 }
 
 
+// Montgomery ladder: return (x2, z2, x3, z3) the projective x-coordinates
+// of n.p and (n+1).p where p is given by its affine x-coordinate pe. n is
+// clamped before the scalar multiplication.
+fn ladder(n: &Scalar, pe: &FieldElem) -> (FieldElem, FieldElem,
+                                          FieldElem, FieldElem) {
+    let mut z = n.get_ref().clone();
+    z.clamp_41417();
+    ladder_clamped(&z, pe)
+}
+
+// Return the initial projective coordinates (x, z) of the input point of
+// affine x-coordinate pe.
+#[cfg(not(feature = "backend_hardened"))]
+fn ladder_input(pe: &FieldElem) -> (FieldElem, FieldElem) {
+    (pe.clone(), FieldElem::one())
+}
+
+// Hardened backend: randomize the projective coordinates of the input
+// point so that the intermediate values of the ladder are unpredictable.
+#[cfg(feature = "backend_hardened")]
+fn ladder_input(pe: &FieldElem) -> (FieldElem, FieldElem) {
+    let r = FieldElem::new_rand();
+    (*pe * r, r)
+}
+
+// Same as ladder() but z must already be clamped.
+fn ladder_clamped(z: &B416, pe: &FieldElem) -> (FieldElem, FieldElem,
+                                                FieldElem, FieldElem) {
+    let mut state = LadderState::from_clamped(z.clone(), pe.clone());
+    state.step(LADDER_ITERATIONS);
+    let LadderState { x2, z2, x3, z3, .. } = state;
+    (x2, z2, x3, z3)
+}
+
+// Number of iterations of the ladder, one per bit of clamped scalars.
+static LADDER_ITERATIONS: uint = 414;
+
+/// Resumable scalar multiplication
+///
+/// Computes `scalar_mult(n, p)` over several calls to `step()`, each one
+/// running a bounded number of the 414 iterations of the Montgomery
+/// ladder, so that environments without threads (event loops, WASM) may
+/// spread a scalar multiplication over scheduler ticks instead of
+/// blocking. Every iteration executes the same operations whatever the
+/// scalar. The clamped scalar and the intermediate coordinates are stored
+/// in wiped buffers: dropping a state before its completion wipes them.
+pub struct LadderState {
+    z: B416,
+    pe: FieldElem,
+    x2: FieldElem,
+    z2: FieldElem,
+    x3: FieldElem,
+    z3: FieldElem,
+    remaining: uint
+}
+
+impl LadderState {
+    /// Start the multiplication of point `p` by scalar `n`, `n` is clamped
+    /// like in `scalar_mult()`. No iteration is run.
+    pub fn new(n: &Scalar, p: &MontPoint) -> LadderState {
+        let mut z = n.get_ref().clone();
+        z.clamp_41417();
+        LadderState::from_clamped(z, FieldElem::unpack(p.get_ref()))
+    }
+
+    fn from_clamped(z: B416, pe: FieldElem) -> LadderState {
+        let (x3, z3) = ladder_input(&pe);
+        LadderState {
+            z: z,
+            pe: pe,
+            x2: FieldElem::one(),
+            z2: FieldElem::zero(),
+            x3: x3,
+            z3: z3,
+            remaining: LADDER_ITERATIONS
+        }
+    }
+
+    /// Run at most `iterations` ladder iterations, return `true` iff the
+    /// multiplication is complete. Once complete calls do nothing.
+    pub fn step(&mut self, iterations: uint) -> bool {
+        let mut e: FieldElem;
+        let mut f: FieldElem;
+        let mut r: u8;
+        let end = self.remaining - cmp::min(iterations, self.remaining);
+
+        for i in range(end, self.remaining).rev() {
+            let (a, c, b, d) = (&mut self.x2, &mut self.z2, &mut self.x3,
+                                &mut self.z3);
+            audit::access("mont::ladder",
+                          self.z.as_bytes().slice(i >> 3, (i >> 3) + 1));
+            r = (*self.z.get(i >> 3) >> (i & 7)) & 1;
+            a.cswap(r as i64, b);
+            c.cswap(r as i64, d);
+            e = *a + *c;
+            *a = *a - *c;
+            *c = *b + *d;
+            *b = *b - *d;
+            *d = e.square();
+            f = a.square();
+            *a = *c * *a;
+            *c = *b * e;
+            e = *a + *c;
+            *a = *a - *c;
+            *b = a.square();
+            *c = *d - f;
+            *a = *c * a24();
+            *a = *a + *d;
+            *c = *c * *a;
+            *a = *d * f;
+            *d = *b * self.pe;
+            *b = e.square();
+            a.cswap(r as i64, b);
+            c.cswap(r as i64, d);
+        }
+        self.remaining = end;
+        self.is_done()
+    }
+
+    /// Return the number of iterations left.
+    pub fn remaining(&self) -> uint {
+        self.remaining
+    }
+
+    /// Return `true` iff all the iterations were run.
+    pub fn is_done(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Return the packed point `n.p` once the multiplication is complete,
+    /// `None` otherwise.
+    pub fn result(&self) -> Option<MontPoint> {
+        match self.is_done() {
+            true => Some(MontPoint((self.x2 * self.z2.inv()).pack())),
+            false => None
+        }
+    }
+}
+
 /// Compute scalar multiplication
 ///
 /// Return a packed point `q` such that `q=n.p`, `n` is a scalar and `p` is
 /// a point. On input, `n` is clamped by this function before performing
 /// its scalar multiplication.
 pub fn scalar_mult(n: &Scalar, p: &MontPoint) -> MontPoint {
-    let mut z: B416;
-    let mut a: FieldElem;
-    let mut b: FieldElem;
-    let mut c: FieldElem;
-    let mut d: FieldElem;
-    let mut e: FieldElem;
-    let mut f: FieldElem;
-    let mut pe: FieldElem;
-    let mut r: u8;
-
     // Unpack p, top 2 bits are discarded in FieldElem::unpack().
-    pe = FieldElem::unpack(p.get_ref());
-    a = FieldElem::new();
-    b = pe.clone();
-    c = FieldElem::new();
-    d = FieldElem::new();
+    let pe = FieldElem::unpack(p.get_ref());
+    let (x, z, _, _) = ladder(n, &pe);
+    MontPoint((x * z.inv()).pack())
+}
 
-    z = n.get_ref().clone();
+/// Decode a point
+///
+/// Decode a point from the 52 bytes encoding of its x-coordinate and
+/// reject malformed inputs: `Err(InvalidLength)` on wrong length,
+/// `Err(NonCanonical)` if one of the top 2 bits is set or if `x >= P`,
+/// `Err(OffCurve)` if `x` is the coordinate of a point of the twist
+/// instead of a point of the curve and `Err(SmallOrder)` for points of
+/// small order.
+pub fn decode_point(bytes: &[u8]) -> result::Result<MontPoint, Error> {
+    try!(error::check_length(bytes, 52));
+    let b: B416 = Bytes::from_bytes(bytes).unwrap();
+
+    let u = FieldElem::unpack(&b);
+    if u.pack() != b {
+        return Err(NonCanonical);
+    }
+
+    // u is on the curve iff (u^3 + A.u^2 + u).B is a square.
+    let a = a24().muli(4) + FieldElem::one().muli(2);
+    let b2 = a + FieldElem::one().muli(2);
+    let w = (u.square() * u + a * u.square() + u) * b2;
+    if w.pow4139() == FieldElem::zero() - FieldElem::one() {
+        return Err(OffCurve);
+    }
+
+    // 8.u is at infinity iff u has a small order.
+    let mut eight: B416 = Bytes::new_zero();
+    *eight.get_mut(0) = 8;
+    let (_, z, _, _) = ladder_clamped(&eight, &u);
+    if z == FieldElem::zero() {
+        return Err(SmallOrder);
+    }
+    Ok(MontPoint(b))
+}
+
+/// Policy of `shared_secret()` toward degenerate peer keys.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub enum DhPolicy {
+    /// Require the peer's key to contribute to the shared secret: reject
+    /// the peer public keys rejected by `decode_point()` (notably points
+    /// of small order) and zero shared secrets. Protocols relying on both
+    /// parties contributing to the key (e.g. channel binding) need this.
+    Contributory,
+    /// Accept any peer public key and return the shared secret as is, like
+    /// `scalar_mult()`, possibly zero. This is the behavior of X25519-like
+    /// functions, for protocols which check the peer keys by other means
+    /// or don't need contributory behavior.
+    Raw
+}
+
+/// Compute a shared secret
+///
+/// Return the shared secret `n.p` between the secret key `n` and the
+/// peer's public key `p` like `scalar_mult()` would, checked according
+/// to `policy`. With `Contributory` return `Err(ZeroSharedSecret)` if the
+/// shared secret is zero or the error of `decode_point()` if `p` is not a
+/// valid public key. With `Raw` never fail.
+pub fn shared_secret(n: &Scalar, p: &MontPoint, policy: DhPolicy)
+                     -> result::Result<MontPoint, Error> {
+    if policy == Contributory {
+        try!(decode_point(p.get_ref().as_bytes()));
+    }
+    let s = scalar_mult(n, p);
+    vectors::record("mont::shared_secret", "shared_secret",
+                    s.get_ref().as_bytes());
+    let zero: B416 = Bytes::new_zero();
+    match policy == Contributory && *s.get_ref() == zero {
+        true => Err(ZeroSharedSecret),
+        false => Ok(s)
+    }
+}
+
+/// Compute a checked shared secret
+///
+/// Same as `shared_secret()` with the `Contributory` policy for the peer's
+/// public key encoded as `p`, also return `Err(InvalidLength)` if `p` is
+/// not 52 bytes long.
+pub fn shared_secret_checked(n: &Scalar, p: &[u8])
+                             -> result::Result<MontPoint, Error> {
+    try!(error::check_length(p, 52));
+    let pk = MontPoint(Bytes::from_bytes(p).unwrap());
+    shared_secret(n, &pk, Contributory)
+}
+
+/// Compute scalar multiplications with the same scalar
+///
+/// Return the packed points `[n.p1, n.p2, ...]` where `points = [p1, p2,
+/// ...]`, this is typically used for computing many Diffie-Hellman shared
+/// secrets with the same secret key `n`. Results are the same than those
+/// of `scalar_mult()` but `n` is clamped only once and a single field
+/// inversion is shared between all points. Nothing else is shared: each
+/// point still takes a full ladder, no table is precomputed for the fixed
+/// scalar `n`, so this is about as costly as as many `scalar_mult()`
+/// calls minus their inversions.
+pub fn shared_secrets(n: &Scalar, points: &[MontPoint]) -> Vec<MontPoint> {
+    let mut z = n.get_ref().clone();
     z.clamp_41417();
 
-    *a.get_mut(0) = 1;
-    *d.get_mut(0) = 1;
-
-    for i in range(0u, 414).rev() {
-        r = (*z.get(i >> 3) >> (i & 7)) & 1;
-        a.cswap(r as i64, &mut b);
-        c.cswap(r as i64, &mut d);
-        e = a + c;
-        a = a - c;
-        c = b + d;
-        b = b - d;
-        d = e.square();
-        f = a.square();
-        a = c * a;
-        c = b * e;
-        e = a + c;
-        a = a - c;
-        b = a.square();
-        c = d - f;
-        a = c * a24();
-        a = a + d;
-        c = c * a;
-        a = d * f;
-        d = b * pe;
-        b = e.square();
-        a.cswap(r as i64, &mut b);
-        c.cswap(r as i64, &mut d);
-    }
-
-    c = c.inv();
-    a = a * c;
-    MontPoint(a.pack())
+    let mut xs = Vec::with_capacity(points.len());
+    let mut zs = Vec::with_capacity(points.len());
+    for p in points.iter() {
+        let pe = FieldElem::unpack(p.get_ref());
+        let (mut x, mut zz, _, _) = ladder_clamped(&z, &pe);
+
+        // Results at infinity are returned as 0 like in scalar_mult(),
+        // their z is set to 1 so that they don't nullify the batch
+        // inversion.
+        let inf = (zz == FieldElem::zero()) as i64;
+        let mut zero = FieldElem::zero();
+        let mut one = FieldElem::one();
+        x.cswap(inf, &mut zero);
+        zz.cswap(inf, &mut one);
+
+        xs.push(x);
+        zs.push(zz);
+    }
+
+    let zis = FieldElem::batch_inv(zs.as_slice());
+    xs.iter().zip(zis.iter()).map(|(x, zi)| {
+        MontPoint((*x * *zi).pack())
+    }).collect()
+}
+
+/// Compute scalar multiplication with full point recovery
+///
+/// Return the point `q` such that `q=n.p` computed with the Montgomery
+/// ladder, whose y-coordinate is recovered from its final state (Okeya-
+/// Sakurai). Contrary to `scalar_mult()` both input and output points
+/// are in Edwards representation hence the result may also be used in
+/// Edwards operations, whereas `q.to_mont()` gives the same result than
+/// `scalar_mult()`. On input, `n` is clamped by this function. `p` must
+/// not be a small-order point.
+pub fn scalar_mult_full(n: &Scalar, p: &GroupElem) -> GroupElem {
+    // Convert p to Montgomery coordinates u = (1+y)/(1-y), v = u/x, on
+    // curve B.v^2 = u^3 + A.u^2 + u with A = 4.a24 + 2 and B = A + 2.
+    let px = p.x();
+    let py = p.y();
+    let u = (FieldElem::one() + py) * (FieldElem::one() - py).inv();
+    let v = u * px.inv();
+
+    let (x1, z1, x2, z2) = ladder(n, &u);
+
+    // y = ((x1.u + 1)(x1 + u + 2A) - 2A - (x1 - u)^2.x2) / (2B.v), with
+    // x1 and x2 in projective coordinates.
+    let a2 = a24().muli(8) + FieldElem::one().muli(4);
+    let b2 = a2 + FieldElem::one().muli(4);
+    let uz1 = u * z1;
+    let mut t = x1 + uz1 + a2 * z1;
+    t = t * (x1 * u + z1);
+    t = t - a2 * z1.square();
+    t = t * z2;
+    let y = t - (x1 - uz1).square() * x2;
+    let d = b2 * v * z1 * z2;
+    let x = d * x1;
+    let z = d * z1;
+
+    GroupElem::from_mont_projective(&x, &y, &z)
+}
+
+/// Compute scalar multiplication with base point and full point recovery
+///
+/// Return the point `q` such that `q=n.BP` in Edwards representation,
+/// see `scalar_mult_full()`. This is useful to use the same key for both
+/// Diffie-Hellman and Edwards operations without computing two scalar
+/// multiplications.
+pub fn scalar_mult_base_full(n: &Scalar) -> GroupElem {
+    scalar_mult_full(n, &GroupElem::base())
 }
 
 /// Compute scalar multiplication with base point
@@ -102,6 +369,64 @@ pub fn scalar_mult_base(n: &Scalar) -> MontPoint {
     scalar_mult(n, &basex())
 }
 
+/// Compute a shared key
+///
+/// Compute the shared secret `n.p` and derive from its x-coordinate
+/// `key.len()` bytes of keying material bound to `label` (see
+/// `kdf::derive()`). Prefer this function over `scalar_mult()` when the
+/// result is meant to be used as a symmetric key.
+pub fn shared_key<D: Digest>(digest: &mut D, n: &Scalar, p: &MontPoint,
+                             label: &[u8], key: &mut [u8]) {
+    let shared = scalar_mult(n, p);
+    vectors::record("mont::shared_key", "shared_secret",
+                    shared.get_ref().as_bytes());
+    kdf::derive(digest, [], shared.get_ref().as_bytes(), label, key);
+}
+
+// Derive key from the concatenation DH(es) || DH(ss).
+fn dual_key<D: Digest>(digest: &mut D, es: &MontPoint, ss: &MontPoint,
+                       label: &[u8], key: &mut [u8]) {
+    let mut ikm: SBuf<DefaultAllocator, u8> = SBuf::new_zero(104);
+    for (d, s) in ikm.mut_iter().zip(es.get_ref().as_bytes().iter()
+                                     .chain(ss.get_ref().as_bytes().iter())) {
+        *d = *s;
+    }
+    vectors::record("mont::dual_key", "ikm", ikm.as_slice());
+    kdf::derive(digest, [], ikm.as_slice(), label, key);
+}
+
+/// Compute a dual shared key, sender side
+///
+/// Derive a shared key from `DH(es) || DH(ss)` where `DH(es)` is the
+/// shared secret between the sender's ephemeral key `eph_sk` and the
+/// recipient's static public key `peer_pk`, and `DH(ss)` the shared secret
+/// between the sender's static key `static_sk` and `peer_pk`. The key is
+/// derived with `kdf::derive()` bound to `label`. The recipient gets the
+/// same key from `dual_shared_key_recipient()`.
+pub fn dual_shared_key_sender<D: Digest>(digest: &mut D, eph_sk: &Scalar,
+                                         static_sk: &Scalar,
+                                         peer_pk: &MontPoint,
+                                         label: &[u8], key: &mut [u8]) {
+    let es = scalar_mult(eph_sk, peer_pk);
+    let ss = scalar_mult(static_sk, peer_pk);
+    dual_key(digest, &es, &ss, label, key);
+}
+
+/// Compute a dual shared key, recipient side
+///
+/// Derive the same key as `dual_shared_key_sender()` from the
+/// recipient's static key `static_sk`, the sender's ephemeral public key
+/// `peer_eph_pk` and the sender's static public key `peer_static_pk`.
+pub fn dual_shared_key_recipient<D: Digest>(digest: &mut D,
+                                            static_sk: &Scalar,
+                                            peer_eph_pk: &MontPoint,
+                                            peer_static_pk: &MontPoint,
+                                            label: &[u8], key: &mut [u8]) {
+    let es = scalar_mult(static_sk, peer_eph_pk);
+    let ss = scalar_mult(static_sk, peer_static_pk);
+    dual_key(digest, &es, &ss, label, key);
+}
+
 /// Generate a new key pair
 ///
 /// A new key pair `(pk, sk)` is generated. `sk` is a secret key randomly
@@ -110,7 +435,17 @@ pub fn scalar_mult_base(n: &Scalar) -> MontPoint {
 /// `B414::clamp_41417()`). The scalar value is returned wrapped in
 /// `Scalar` and the public key is wrapped in `MontPoint`.
 pub fn keypair() -> (MontPoint, Scalar) {
-    let mut sk: B416 = Bytes::new_rand();
+    keypair_from_rng(&mut utils::urandom_rng())
+}
+
+/// Generate a new key pair from `rng`
+///
+/// Same as `keypair()` but the secret key is generated from `rng`. Be sure
+/// to use a secure PRNG, a deterministic generator such as `kdf::KdfRng`
+/// should only be used for reproducible test vectors or for keys derived
+/// from a secret seed.
+pub fn keypair_from_rng<R: Rng>(rng: &mut R) -> (MontPoint, Scalar) {
+    let mut sk: B416 = Rand::rand(rng);
     sk.clamp_41417();
     let sk_val = Scalar(sk);
     let pk_val = scalar_mult_base(&sk_val);
@@ -123,8 +458,13 @@ mod tests {
     extern crate test;
     use self::test::Bencher;
 
-    use bytes::{B416, Bytes, Scalar};
+    use bytes::{B416, Bytes, MontPoint, Scalar};
+    use digest::sha256::Sha256;
+    use ed::GroupElem;
+    use error::{InvalidLength, NonCanonical, OffCurve, SmallOrder};
+    use kdf::KdfRng;
     use mont;
+    use mont::{Contributory, LadderState, Raw};
 
 
     #[test]
@@ -139,6 +479,27 @@ mod tests {
         assert!(ssk1w.unwrap() == ssk2w.unwrap());
     }
 
+    #[test]
+    fn test_ladder_state() {
+        let (pk, _) = mont::keypair();
+        let (_, sk) = mont::keypair();
+        let mut state = LadderState::new(&sk, &pk);
+        assert!(state.remaining() == 414 && state.result().is_none());
+        assert!(!state.step(0) && state.remaining() == 414);
+        let mut steps = 0u;
+        while !state.step(100) {
+            steps += 1;
+            assert!(state.result().is_none());
+        }
+        assert!(steps == 4 && state.is_done());
+        assert!(state.result().unwrap() == mont::scalar_mult(&sk, &pk));
+        assert!(state.step(1) && state.remaining() == 0);
+
+        let mut state = LadderState::new(&sk, &pk);
+        assert!(state.step(1000));
+        assert!(state.result().unwrap() == mont::scalar_mult(&sk, &pk));
+    }
+
     #[test]
     fn test_dh_ref() {
         let n: [u8, ..52] = [
@@ -165,6 +526,138 @@ mod tests {
         assert!(scr == scrr);
     }
 
+    #[test]
+    fn test_scalar_mult_full() {
+        let n: B416 = Bytes::new_rand();
+        let mut nc = n.clone();
+        nc.clamp_41417();
+
+        let q = mont::scalar_mult_base_full(&Scalar(n.clone()));
+        assert!(q == GroupElem::base() * Scalar(nc.clone()));
+        assert!(q.to_mont() == mont::scalar_mult_base(&Scalar(n.clone())));
+
+        let p = GroupElem::new_rand();
+        let r = mont::scalar_mult_full(&Scalar(n), &p);
+        assert!(r == p * Scalar(nc));
+    }
+
+    #[test]
+    fn test_shared_secrets() {
+        let (_, sk) = mont::keypair();
+        let mut pks = Vec::new();
+        for _ in range(0u, 4) {
+            let (pk, _) = mont::keypair();
+            pks.push(pk);
+        }
+        // Low-order point 0.
+        pks.push(MontPoint(Bytes::new_zero()));
+
+        let ss = mont::shared_secrets(&sk, pks.as_slice());
+        assert!(ss.len() == pks.len());
+        for (s, pk) in ss.iter().zip(pks.iter()) {
+            assert!(*s == mont::scalar_mult(&sk, pk));
+        }
+    }
+
+    #[test]
+    fn test_shared_key() {
+        let (pk1, sk1) = mont::keypair();
+        let (pk2, sk2) = mont::keypair();
+        let mut h = Sha256::new();
+
+        let mut k1 = [0u8, ..32];
+        let mut k2 = [0u8, ..32];
+        mont::shared_key(&mut h, &sk1, &pk2, "test".as_bytes(), k1);
+        mont::shared_key(&mut h, &sk2, &pk1, "test".as_bytes(), k2);
+        assert!(k1 == k2);
+
+        mont::shared_key(&mut h, &sk2, &pk1, "other".as_bytes(), k2);
+        assert!(k1 != k2);
+    }
+
+    #[test]
+    fn test_keypair_from_rng() {
+        let seed = "seed".as_bytes();
+        let (pk1, sk1) = mont::keypair_from_rng(&mut KdfRng::new(
+            Sha256::new(), seed, []));
+        let (pk2, sk2) = mont::keypair_from_rng(&mut KdfRng::new(
+            Sha256::new(), seed, []));
+        assert!(pk1 == pk2 && sk1 == sk2);
+        assert!(pk1 == mont::scalar_mult_base(&sk1));
+
+        let (pk3, _) = mont::keypair_from_rng(&mut KdfRng::new(
+            Sha256::new(), seed, "other".as_bytes()));
+        assert!(pk1 != pk3);
+    }
+
+    #[test]
+    fn test_decode_point() {
+        let (pk, sk) = mont::keypair();
+        let b = pk.get_ref().as_bytes();
+        assert!(mont::decode_point(b).unwrap() == pk);
+        assert!(mont::decode_point(b.slice_to(51)) ==
+                Err(InvalidLength(52, 51)));
+
+        let mut nc = pk.get_ref().clone();
+        *nc.get_mut(51) |= 0x80;
+        assert!(mont::decode_point(nc.as_bytes()) == Err(NonCanonical));
+
+        let zero: B416 = Bytes::new_zero();
+        assert!(mont::decode_point(zero.as_bytes()) == Err(SmallOrder));
+        assert!(mont::shared_secret_checked(&sk, zero.as_bytes()) ==
+                Err(SmallOrder));
+
+        // Find some x on the twist.
+        let mut x: B416 = Bytes::new_zero();
+        loop {
+            *x.get_mut(0) += 1;
+            match mont::decode_point(x.as_bytes()) {
+                Err(OffCurve) => break,
+                _ => ()
+            }
+        }
+
+        let (pk2, sk2) = mont::keypair();
+        let s1 = mont::shared_secret_checked(&sk, pk2.get_ref().as_bytes());
+        assert!(s1.unwrap() == mont::scalar_mult(&sk2, &pk));
+    }
+
+    #[test]
+    fn test_shared_secret_policy() {
+        let (pk1, sk1) = mont::keypair();
+        let (pk2, sk2) = mont::keypair();
+        let s1 = mont::shared_secret(&sk1, &pk2, Contributory).unwrap();
+        assert!(s1 == mont::shared_secret(&sk2, &pk1, Raw).unwrap());
+
+        // Low-order points are rejected or give a zero shared secret.
+        let zero = MontPoint(Bytes::new_zero());
+        assert!(mont::shared_secret(&sk1, &zero, Contributory) ==
+                Err(SmallOrder));
+        assert!(mont::shared_secret(&sk1, &zero, Raw).unwrap() == zero);
+        assert!(mont::shared_secret_checked(&sk1, pk2.get_ref().as_bytes()
+                                            .slice_to(51)) ==
+                Err(InvalidLength(52, 51)));
+    }
+
+    #[test]
+    fn test_dual_shared_key() {
+        let (epk, esk) = mont::keypair();
+        let (spk, ssk) = mont::keypair();
+        let (rpk, rsk) = mont::keypair();
+        let mut h = Sha256::new();
+        let label = "dual".as_bytes();
+
+        let mut k1 = [0u8, ..32];
+        let mut k2 = [0u8, ..32];
+        mont::dual_shared_key_sender(&mut h, &esk, &ssk, &rpk, label, k1);
+        mont::dual_shared_key_recipient(&mut h, &rsk, &epk, &spk, label, k2);
+        assert!(k1 == k2);
+
+        // Swapping static and ephemeral keys gives another key.
+        mont::dual_shared_key_recipient(&mut h, &rsk, &spk, &epk, label, k2);
+        assert!(k1 != k2);
+    }
+
     #[bench]
     fn bench_scalar_mult_base(b: &mut Bencher) {
         let n = Scalar(Bytes::new_rand());
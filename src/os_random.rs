@@ -0,0 +1,149 @@
+//! Operating system entropy
+//!
+//! Random bytes read directly from the entropy source of the operating
+//! system, independently of `std::rand::OsRng`: the `getrandom` system
+//! call on Linux when the kernel provides it (Linux 3.17 and later), which
+//! doesn't need any file descriptor, and `/dev/urandom` otherwise. Reads
+//! from `getrandom` block until the kernel's pool is initialized.
+//! `OsRandom` exposes this source as a `Rng`, it is the generator used
+//! wherever this crate generates secrets without a caller-supplied `Rng`.
+use std::io::{File, IoResult};
+use std::rand::Rng;
+
+
+#[cfg(target_os = "linux")]
+mod impgetrandom {
+    use libc::EINTR;
+    use libc::types::os::arch::c95::{c_long, c_uint, size_t};
+    use std::os;
+
+
+    #[cfg(target_arch = "x86_64")]
+    static NR_GETRANDOM: c_long = 318;
+    #[cfg(target_arch = "x86")]
+    static NR_GETRANDOM: c_long = 355;
+    #[cfg(target_arch = "arm")]
+    static NR_GETRANDOM: c_long = 384;
+    #[cfg(target_arch = "aarch64")]
+    static NR_GETRANDOM: c_long = 278;
+    #[cfg(not(target_arch = "x86_64"), not(target_arch = "x86"),
+          not(target_arch = "arm"), not(target_arch = "aarch64"))]
+    static NR_GETRANDOM: c_long = -1;
+
+    extern {
+        fn syscall(number: c_long, ...) -> c_long;
+    }
+
+    // Fill buf with the getrandom syscall, return false if it failed
+    // (e.g. ENOSYS on older kernels).
+    pub fn getrandom(buf: &mut [u8]) -> bool {
+        if NR_GETRANDOM < 0 {
+            return false;
+        }
+        let mut pos = 0u;
+        while pos < buf.len() {
+            let n = unsafe {
+                syscall(NR_GETRANDOM, buf.as_mut_ptr().offset(pos as int),
+                        (buf.len() - pos) as size_t, 0 as c_uint)
+            };
+            if n < 0 {
+                if os::errno() == EINTR as int {
+                    continue;
+                }
+                return false;
+            }
+            pos += n as uint;
+        }
+        true
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod impgetrandom {
+    pub fn getrandom(_: &mut [u8]) -> bool {
+        false
+    }
+}
+
+
+/// Fill `buf` with random bytes from the operating system. Return the I/O
+/// error of `/dev/urandom` if `getrandom` is not available and it can't be
+/// read.
+pub fn fill(buf: &mut [u8]) -> IoResult<()> {
+    if buf.len() == 0 || self::impgetrandom::getrandom(buf) {
+        return Ok(());
+    }
+    let mut f = try!(File::open(&Path::new("/dev/urandom")));
+    try!(f.read_at_least(buf.len(), buf));
+    Ok(())
+}
+
+
+/// Random generator reading from the operating system, see `fill()`.
+pub struct OsRandom {
+    // Private field, instances are built with new().
+    _priv: ()
+}
+
+impl OsRandom {
+    /// Return a new generator, or the I/O error returned when reading a
+    /// first random byte if the entropy source is not available.
+    pub fn new() -> IoResult<OsRandom> {
+        let mut probe = [0u8, ..1];
+        try!(fill(probe));
+        Ok(OsRandom {
+            _priv: ()
+        })
+    }
+}
+
+impl Rng for OsRandom {
+    fn next_u32(&mut self) -> u32 {
+        let mut b = [0u8, ..4];
+        self.fill_bytes(b);
+        b.iter().rev().fold(0u32, |r, x| (r << 8) | *x as u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut b = [0u8, ..8];
+        self.fill_bytes(b);
+        b.iter().rev().fold(0u64, |r, x| (r << 8) | *x as u64)
+    }
+
+    /// Fails if the entropy source can't be read anymore.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match fill(dest) {
+            Ok(()) => (),
+            Err(e) => fail!("os_random: reading entropy failed: {}", e)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::rand::Rng;
+
+    use os_random;
+    use os_random::OsRandom;
+
+
+    #[test]
+    fn test_fill() {
+        let mut b1 = [0u8, ..64];
+        let mut b2 = [0u8, ..64];
+        os_random::fill(b1).unwrap();
+        os_random::fill(b2).unwrap();
+        assert!(b1.as_slice() != b2.as_slice());
+        assert!(b1.iter().any(|x| *x != 0));
+        assert!(os_random::fill([]).is_ok());
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut rng = OsRandom::new().unwrap();
+        assert!(rng.next_u64() != rng.next_u64());
+        let v: Vec<u32> = rng.gen_iter().take(4).collect();
+        assert!(v.get(0) != v.get(1));
+    }
+}
@@ -0,0 +1,176 @@
+//! Multi-threaded batch operations
+//!
+//! Operations in this module shard their inputs across native tasks and
+//! combine the partial results. They compute the same values as their
+//! sequential counterparts. Only available with the `parallel` feature.
+use std::cmp;
+use std::comm;
+
+use bytes::Scalar;
+use digest::Digest;
+use ed::GroupElem;
+use sign;
+use sign::{BatchResult, BatchValid, Signature};
+
+
+// Split items into at most threads chunks of contiguous elements, apply f
+// to each chunk in its own task and return the results in chunks order.
+fn run_sharded<T: Clone + Send, R: Send>(items: &[T], threads: uint,
+                                          f: fn(Vec<T>) -> R) -> Vec<R> {
+    assert!(threads > 0);
+    if items.len() == 0 {
+        return Vec::new();
+    }
+
+    let n = cmp::min(threads, items.len());
+    let size = (items.len() + n - 1) / n;
+
+    let mut rxs = Vec::with_capacity(n);
+    for chunk in items.chunks(size) {
+        let (tx, rx) = comm::channel();
+        let values = chunk.to_vec();
+        spawn(proc() {
+            tx.send(f(values));
+        });
+        rxs.push(rx);
+    }
+
+    rxs.iter().map(|rx| rx.recv()).collect()
+}
+
+fn multiscalar_mult_chunk(pairs: Vec<(Scalar, GroupElem)>) -> GroupElem {
+    let mut q = GroupElem::neutral();
+    for &(ref n, ref p) in pairs.iter() {
+        q = q + p.scalar_mult(n);
+    }
+    q
+}
+
+fn multiscalar_mult_vartime_chunk(pairs: Vec<(Scalar, GroupElem)>)
+                                  -> GroupElem {
+    let ns: Vec<Scalar> = pairs.iter().map(|&(ref n, _)| n.clone()).collect();
+    let ps: Vec<GroupElem> = pairs.iter().map(|&(_, ref p)| *p).collect();
+    GroupElem::multiscalar_mult_vartime(ns.as_slice(), ps.as_slice())
+}
+
+/// Return point `q` such that `q=n1.p1+n2.p2+...` where `ns=[n1, n2, ...]`
+/// are scalar values and `ps=[p1, p2, ...]` are group elements. Scalar
+/// multiplications are distributed over at most `threads` tasks. This is
+/// equivalent to calling `GroupElem::multiscalar_mult(ns, ps)`. Fails if
+/// `ns` and `ps` don't have the same length or if `threads` is `0`.
+pub fn multiscalar_mult_parallel(ns: &[Scalar], ps: &[GroupElem],
+                                 threads: uint) -> GroupElem {
+    assert!(ns.len() == ps.len());
+    let pairs: Vec<(Scalar, GroupElem)> =
+        ns.iter().zip(ps.iter()).map(|(n, p)| (n.clone(), p.clone()))
+          .collect();
+
+    let partials = run_sharded(pairs.as_slice(), threads,
+                               multiscalar_mult_chunk);
+    let mut q = GroupElem::neutral();
+    for p in partials.iter() {
+        q = q + *p;
+    }
+    q
+}
+
+/// Verify a batch of signatures
+///
+/// Same as `sign::verify_batch()` but the scalar multiplications of the
+/// batch equation are distributed over at most `threads` tasks, each
+/// computing the variable-time multi-scalar multiplication of its share
+/// (see `GroupElem::multiscalar_mult_vartime()`), and their partial sums
+/// combined. The
+/// challenges are computed by the calling task with `digest`. When the
+/// batch fails the lowest index of an invalid signature is located by
+/// `sign::verify_batch()`. Fails if the three slices don't have the same
+/// length or if `threads` is `0`.
+pub fn verify_batch_parallel<D: Digest>(digest: &mut D, pks: &[GroupElem],
+                                        msgs: &[&[u8]], sigs: &[Signature],
+                                        threads: uint) -> BatchResult {
+    assert!(threads > 0);
+    match sign::batch_equation_terms(digest, pks, msgs, sigs) {
+        Some((ns, ps)) => {
+            let pairs: Vec<(Scalar, GroupElem)> =
+                ns.move_iter().zip(ps.move_iter()).collect();
+            let partials = run_sharded(pairs.as_slice(), threads,
+                                       multiscalar_mult_vartime_chunk);
+            let q = partials.iter().fold(GroupElem::neutral(),
+                                         |q, p| q + *p);
+            if q.scalar_mult_cofactor().is_identity().unwrap_u8() == 1 {
+                return BatchValid;
+            }
+        },
+        None => ()
+    }
+    sign::verify_batch(digest, pks, msgs, sigs)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use digest::sha256::Sha256;
+    use ed::GroupElem;
+    use parallel;
+    use sign;
+    use sign::{BatchInvalid, BatchValid};
+
+
+    #[test]
+    fn test_multiscalar_mult_parallel() {
+        let mut ns = Vec::new();
+        let mut ps = Vec::new();
+        for _ in range(0u, 5) {
+            let (p, n) = GroupElem::keypair();
+            ns.push(n);
+            ps.push(p);
+        }
+
+        let q = GroupElem::multiscalar_mult(ns.as_slice(), ps.as_slice());
+        for t in range(1u, 7) {
+            assert!(q == parallel::multiscalar_mult_parallel(ns.as_slice(),
+                                                             ps.as_slice(),
+                                                             t));
+        }
+        assert!(parallel::multiscalar_mult_parallel([], [], 4) ==
+                GroupElem::neutral());
+    }
+
+    #[test]
+    fn test_verify_batch_parallel() {
+        let mut h = Sha256::new();
+        let mut msgs: Vec<&[u8]> = Vec::new();
+        let mut pks = Vec::new();
+        let mut sigs = Vec::new();
+        let all = ["a", "b", "c", "d", "e"];
+        for m in all.iter() {
+            let (pk, sk) = GroupElem::keypair();
+            sigs.push(sign::sign(&mut h, &sk, &pk, m.as_bytes()));
+            pks.push(pk);
+            msgs.push(m.as_bytes());
+        }
+
+        for t in range(1u, 7) {
+            let r = parallel::verify_batch_parallel(&mut h, pks.as_slice(),
+                                                    msgs.as_slice(),
+                                                    sigs.as_slice(), t);
+            assert!(r == BatchValid);
+            assert!(r == sign::verify_batch(&mut h, pks.as_slice(),
+                                            msgs.as_slice(),
+                                            sigs.as_slice()));
+        }
+        assert!(parallel::verify_batch_parallel(&mut h, [], [], [], 3) ==
+                BatchValid);
+
+        *msgs.get_mut(3) = "x".as_bytes();
+        for t in range(1u, 7) {
+            let r = parallel::verify_batch_parallel(&mut h, pks.as_slice(),
+                                                    msgs.as_slice(),
+                                                    sigs.as_slice(), t);
+            assert!(r == BatchInvalid(3));
+            assert!(r == sign::verify_batch(&mut h, pks.as_slice(),
+                                            msgs.as_slice(),
+                                            sigs.as_slice()));
+        }
+    }
+}
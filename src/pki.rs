@@ -0,0 +1,181 @@
+//! X.509 building blocks
+//!
+//! Minimal DER encodings for placing Curve41417 keys and signatures into
+//! certificates and certification requests built by other tools: the
+//! `SubjectPublicKeyInfo` of a public key and the signed envelope of a
+//! caller-encoded `TBSCertificate` or `CertificationRequestInfo`. No
+//! object identifier is registered for Curve41417, callers supply the one
+//! of their experiment (e.g. under a private enterprise arc); algorithm
+//! identifiers have absent parameters like those of RFC 8410. Parsing and
+//! full X.509 support are out of scope.
+use std::result;
+
+use digest::Digest;
+use error::{Error, Malformed};
+use keys::{KeyPair, PublicKey};
+
+
+static SEQUENCE: u8 = 0x30;
+static BIT_STRING: u8 = 0x03;
+static OBJECT_IDENTIFIER: u8 = 0x06;
+
+
+// Append the DER element of tag tag and contents contents to out, its
+// length uses the short form below 128 and the long form otherwise.
+fn push_tlv(out: &mut Vec<u8>, tag: u8, contents: &[u8]) {
+    out.push(tag);
+    let len = contents.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let be: Vec<u8> = range(0u, 8).rev().map(|i| (len >> (8 * i)) as u8)
+            .skip_while(|b| *b == 0).collect();
+        out.push(0x80 | be.len() as u8);
+        out.push_all(be.as_slice());
+    }
+    out.push_all(contents);
+}
+
+// Append the base-128 encoding of arc to out.
+fn push_arc(out: &mut Vec<u8>, arc: u64) {
+    let mut n = 1u;
+    while n < 10 && arc >> (7 * n) != 0 {
+        n += 1;
+    }
+    for i in range(0u, n).rev() {
+        let b = ((arc >> (7 * i)) & 0x7f) as u8;
+        out.push(if i > 0 { b | 0x80 } else { b });
+    }
+}
+
+/// Return the DER `AlgorithmIdentifier` of the object identifier `oid`
+/// given by its arcs, e.g. `[1, 3, 6, 1, 4, 1, ...]`, with absent
+/// parameters. Return `Err(Malformed)` unless `oid` has at least two arcs
+/// with a first arc in `[0, 2]`, and a second arc below `40` if the first
+/// one isn't `2`.
+pub fn algorithm_identifier(oid: &[u64]) -> result::Result<Vec<u8>, Error> {
+    if oid.len() < 2 || oid[0] > 2 || (oid[0] != 2 && oid[1] >= 40) {
+        return Err(Malformed);
+    }
+    let mut arcs = Vec::new();
+    push_arc(&mut arcs, oid[0] * 40 + oid[1]);
+    for arc in oid.slice_from(2).iter() {
+        push_arc(&mut arcs, *arc);
+    }
+
+    let mut id = Vec::new();
+    push_tlv(&mut id, OBJECT_IDENTIFIER, arcs.as_slice());
+    let mut r = Vec::new();
+    push_tlv(&mut r, SEQUENCE, id.as_slice());
+    Ok(r)
+}
+
+// Append the DER BIT STRING of the bytes b (no unused bits) to out.
+fn push_bit_string(out: &mut Vec<u8>, b: &[u8]) {
+    let mut contents = vec![0u8];
+    contents.push_all(b);
+    push_tlv(out, BIT_STRING, contents.as_slice());
+}
+
+/// Return the DER `SubjectPublicKeyInfo` of `pk` for the algorithm `oid`
+/// (see `algorithm_identifier()`): its subject public key is the 52
+/// bytes packed point of `pk`. Return the error of
+/// `algorithm_identifier()` if `oid` is invalid.
+pub fn subject_public_key_info(pk: &PublicKey, oid: &[u64])
+                               -> result::Result<Vec<u8>, Error> {
+    let mut contents = try!(algorithm_identifier(oid));
+    push_bit_string(&mut contents, pk.as_point().get_ref().as_bytes());
+    let mut r = Vec::new();
+    push_tlv(&mut r, SEQUENCE, contents.as_slice());
+    Ok(r)
+}
+
+/// Sign the DER encoding `tbs` of a `TBSCertificate` or of a
+/// `CertificationRequestInfo` with `kp` and return the DER `SEQUENCE` of
+/// `tbs`, the `AlgorithmIdentifier` of `oid` and the `BIT STRING` of the
+/// 104 bytes signature of `tbs` (see `sign::sign()`), i.e. the
+/// `Certificate` or `CertificationRequest`. `tbs` is embedded as is and
+/// should carry the same algorithm identifier in its signature field.
+/// Return `Err(OffCurve)` if the public key of `kp` is not on the curve,
+/// or the error of `algorithm_identifier()` if `oid` is invalid.
+pub fn sign_tbs<D: Digest>(digest: &mut D, kp: &KeyPair, oid: &[u64],
+                           tbs: &[u8]) -> result::Result<Vec<u8>, Error> {
+    let sig = try!(kp.sign(digest, tbs));
+
+    let mut contents = Vec::from_slice(tbs);
+    contents.push_all(try!(algorithm_identifier(oid)).as_slice());
+    push_bit_string(&mut contents, sig.to_bytes().as_bytes());
+    let mut r = Vec::new();
+    push_tlv(&mut r, SEQUENCE, contents.as_slice());
+    Ok(r)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use digest::sha256::Sha256;
+    use error::Malformed;
+    use keys::KeyPair;
+    use pki;
+    use sign::Signature;
+
+
+    static OID: &'static [u64] = &[1, 3, 6, 1, 4, 1, 99999, 41417, 1];
+
+    #[test]
+    fn test_algorithm_identifier() {
+        let id = pki::algorithm_identifier(OID).unwrap();
+        assert!(id == vec![0x30u8, 0x0e, 0x06, 0x0c, 0x2b, 0x06, 0x01, 0x04,
+                           0x01, 0x86, 0x8d, 0x1f, 0x82, 0xc3, 0x49, 0x01]);
+        assert!(pki::algorithm_identifier([2, 999]) ==
+                Ok(vec![0x30u8, 0x04, 0x06, 0x02, 0x88, 0x37]));
+        let bad: [&[u64], ..4] = [&[], &[1], &[3, 1], &[1, 40]];
+        for oid in bad.iter() {
+            assert!(pki::algorithm_identifier(*oid) == Err(Malformed));
+        }
+    }
+
+    #[test]
+    fn test_spki() {
+        let kp = KeyPair::generate();
+        let spki = pki::subject_public_key_info(kp.public(), OID).unwrap();
+        let id = pki::algorithm_identifier(OID).unwrap();
+        assert!(spki.len() == 2 + id.len() + 2 + 53);
+        assert!(*spki.get(0) == 0x30);
+        assert!(*spki.get(1) as uint == spki.len() - 2);
+        assert!(spki.slice(2, 2 + id.len()) == id.as_slice());
+        assert!(spki.slice(2 + id.len(), 5 + id.len()) ==
+                [0x03u8, 53, 0].as_slice());
+        assert!(spki.slice_from(5 + id.len()) ==
+                kp.public().as_point().get_ref().as_bytes());
+        assert!(pki::subject_public_key_info(kp.public(), [1]) ==
+                Err(Malformed));
+    }
+
+    #[test]
+    fn test_sign_tbs() {
+        let mut h = Sha256::new();
+        let kp = KeyPair::generate();
+        // A TBS long enough for the envelope to use a long-form length.
+        let mut tbs = vec![0x30u8, 0x81, 200];
+        tbs.grow(200, &0x42);
+
+        let cert = pki::sign_tbs(&mut h, &kp, OID, tbs.as_slice()).unwrap();
+        let id = pki::algorithm_identifier(OID).unwrap();
+        let len = tbs.len() + id.len() + 3 + 104;
+        assert!(cert.slice_to(4) ==
+                [0x30u8, 0x82, (len >> 8) as u8, len as u8].as_slice());
+        assert!(cert.len() == 4 + len);
+        assert!(cert.slice(4, 4 + tbs.len()) == tbs.as_slice());
+        let tail = cert.slice_from(4 + tbs.len());
+        assert!(tail.slice_to(id.len()) == id.as_slice());
+        assert!(tail.slice(id.len(), id.len() + 3) ==
+                [0x03u8, 105, 0].as_slice());
+
+        let sig = Signature::from_bytes(tail.slice_from(id.len() + 3))
+            .unwrap();
+        assert!(kp.public().verify(&mut h, tbs.as_slice(), &sig));
+        assert!(pki::sign_tbs(&mut h, &kp, [], tbs.as_slice()) ==
+                Err(Malformed));
+    }
+}
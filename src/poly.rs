@@ -0,0 +1,291 @@
+//! Polynomials over scalars
+//!
+//! Polynomials with coefficients in `[0, L-1]` and arithmetic `mod L`,
+//! intended as building blocks for secret sharing and threshold schemes.
+use std::cmp;
+use std::fmt::{Show, Formatter, Result};
+
+use sc::ScalarElem;
+
+
+/// Polynomial with `ScalarElem` coefficients.
+///
+/// Coefficients are stored in increasing order of degree, the first one
+/// being the constant term.
+#[deriving(Clone)]
+pub struct ScalarPoly {
+    coeffs: Vec<ScalarElem>
+}
+
+impl ScalarPoly {
+    /// Return a new polynomial from its coefficients `coeffs`, given in
+    /// increasing order of degree. Fails if `coeffs` is empty.
+    pub fn new(coeffs: Vec<ScalarElem>) -> ScalarPoly {
+        assert!(coeffs.len() > 0);
+        ScalarPoly {
+            coeffs: coeffs
+        }
+    }
+
+    /// Return a new random polynomial of degree `degree` whose constant
+    /// term is `constant`. Other coefficients are generated with
+    /// `ScalarElem::new_rand()`.
+    pub fn new_rand(constant: &ScalarElem, degree: uint) -> ScalarPoly {
+        let mut coeffs = Vec::with_capacity(degree + 1);
+        coeffs.push(constant.clone());
+        for _ in range(0u, degree) {
+            coeffs.push(ScalarElem::new_rand());
+        }
+        ScalarPoly::new(coeffs)
+    }
+
+    /// Return the degree of this polynomial. Note that leading zero
+    /// coefficients are not trimmed (doing so would leak information on
+    /// secret coefficients), hence it is an upper bound of its actual
+    /// degree.
+    pub fn degree(&self) -> uint {
+        self.coeffs.len() - 1
+    }
+
+    /// Return a reference to the coefficient of degree `index`. Fails if
+    /// `index` is greater than `degree()`.
+    pub fn coeff<'a>(&'a self, index: uint) -> &'a ScalarElem {
+        self.coeffs.get(index)
+    }
+
+    /// Return the coefficients as a slice.
+    pub fn as_slice<'a>(&'a self) -> &'a [ScalarElem] {
+        self.coeffs.as_slice()
+    }
+
+    /// Evaluate this polynomial at `x` using Horner's method.
+    pub fn eval(&self, x: &ScalarElem) -> ScalarElem {
+        let mut r = ScalarElem::zero();
+        for c in self.coeffs.iter().rev() {
+            r = r * *x + *c;
+        }
+        r
+    }
+
+    /// Given `points = [(x1, y1), (x2, y2), ...]` evaluations of some
+    /// polynomial `p` of degree lower than `points.len()`, return `p(0)`
+    /// through Lagrange interpolation. Return `None` if `x` coordinates
+    /// are not distinct and non-zero (see `lagrange_coefficients()`).
+    pub fn interpolate_at_zero(points: &[(ScalarElem, ScalarElem)])
+                               -> Option<ScalarElem> {
+        let xs: Vec<ScalarElem> = points.iter().map(|&(ref x, _)| {
+            x.clone()
+        }).collect();
+
+        lagrange_coefficients(xs.as_slice()).map(|coeffs| {
+            let pairs: Vec<(ScalarElem, ScalarElem)> =
+                coeffs.move_iter().zip(points.iter().map(|&(_, ref y)| {
+                    y.clone()
+                })).collect();
+            ScalarElem::sum_of_products(pairs.as_slice())
+        })
+    }
+}
+
+// Return (numerators, denominators, valid) of the Lagrange coefficients
+// at zero for indices. valid is false iff an index is zero or if two
+// indices are equal. All the products are always computed whatever the
+// indices values.
+fn lagrange_terms(indices: &[ScalarElem]) -> (Vec<ScalarElem>,
+                                              Vec<ScalarElem>, bool) {
+    let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+    let mut nums = Vec::with_capacity(indices.len());
+    let mut dens = Vec::with_capacity(indices.len());
+    let mut check = one.clone();
+
+    for (i, xi) in indices.iter().enumerate() {
+        let mut num = one.clone();
+        let mut den = one.clone();
+        for (j, xj) in indices.iter().enumerate() {
+            if i != j {
+                num = num * *xj;
+                den = den * (*xj - *xi);
+            }
+        }
+        check = check * *xi * den;
+        nums.push(num);
+        dens.push(den);
+    }
+
+    let valid = check != ScalarElem::zero();
+    (nums, dens, valid)
+}
+
+/// Return the Lagrange coefficients `[l1, l2, ...]` `mod L` such that
+/// `p(0) = l1 * p(x1) + l2 * p(x2) + ...` where `indices = [x1, x2, ...]`
+/// holds distinct evaluation points of a polynomial `p` of degree lower
+/// than `indices.len()`.
+///
+/// Return `None` if any index is `0` (the point being interpolated, which
+/// may indicate indices counted from `0` instead of `1`) or if indices are
+/// not distinct. Denominators are inverted with a single inversion and the
+/// same operations are executed whatever the values of `indices`.
+pub fn lagrange_coefficients(indices: &[ScalarElem])
+                             -> Option<Vec<ScalarElem>> {
+    let (nums, dens, valid) = lagrange_terms(indices);
+    if !valid {
+        return None;
+    }
+
+    let invs = ScalarElem::batch_inv(dens.as_slice());
+    Some(nums.iter().zip(invs.iter()).map(|(n, d)| *n * *d).collect())
+}
+
+/// Return the Lagrange coefficient at zero `mod L` of the `i`-th element
+/// of `indices`. See `lagrange_coefficients()`. Fails if `i` is out of
+/// bounds.
+pub fn lagrange_coefficient(indices: &[ScalarElem], i: uint)
+                            -> Option<ScalarElem> {
+    assert!(i < indices.len());
+    let (nums, dens, valid) = lagrange_terms(indices);
+    if !valid {
+        return None;
+    }
+
+    Some(*nums.get(i) * dens.get(i).inv())
+}
+
+impl Add<ScalarPoly, ScalarPoly> for ScalarPoly {
+    /// Add polynomials.
+    fn add(&self, other: &ScalarPoly) -> ScalarPoly {
+        let l = cmp::max(self.coeffs.len(), other.coeffs.len());
+        let zero = ScalarElem::zero();
+
+        let coeffs = range(0u, l).map(|i| {
+            let a = self.coeffs.as_slice().get(i).unwrap_or(&zero);
+            let b = other.coeffs.as_slice().get(i).unwrap_or(&zero);
+            *a + *b
+        }).collect();
+        ScalarPoly::new(coeffs)
+    }
+}
+
+impl Sub<ScalarPoly, ScalarPoly> for ScalarPoly {
+    /// Substract polynomials.
+    fn sub(&self, other: &ScalarPoly) -> ScalarPoly {
+        *self + -*other
+    }
+}
+
+impl Neg<ScalarPoly> for ScalarPoly {
+    /// Negate polynomial.
+    fn neg(&self) -> ScalarPoly {
+        ScalarPoly::new(self.coeffs.iter().map(|c| -*c).collect())
+    }
+}
+
+impl Mul<ScalarPoly, ScalarPoly> for ScalarPoly {
+    /// Multiply polynomials.
+    fn mul(&self, other: &ScalarPoly) -> ScalarPoly {
+        let l = self.coeffs.len() + other.coeffs.len() - 1;
+        let mut coeffs = Vec::from_fn(l, |_| ScalarElem::zero());
+
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in other.coeffs.iter().enumerate() {
+                let c = *coeffs.get(i + j) + *a * *b;
+                *coeffs.get_mut(i + j) = c;
+            }
+        }
+        ScalarPoly::new(coeffs)
+    }
+}
+
+impl Show for ScalarPoly {
+    /// Format as list of hex-encoded coefficients.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        self.coeffs.fmt(f)
+    }
+}
+
+impl PartialEq for ScalarPoly {
+    /// Constant-time comparison of coefficients, polynomials with
+    /// different numbers of coefficients are never equal.
+    fn eq(&self, other: &ScalarPoly) -> bool {
+        if self.coeffs.len() != other.coeffs.len() {
+            return false;
+        }
+
+        let mut r = true;
+        for (a, b) in self.coeffs.iter().zip(other.coeffs.iter()) {
+            r &= *a == *b;
+        }
+        r
+    }
+}
+
+impl Eq for ScalarPoly {
+}
+
+
+#[cfg(test)]
+mod tests {
+    use poly;
+    use poly::ScalarPoly;
+    use sc::ScalarElem;
+
+
+    fn sc(n: u64) -> ScalarElem {
+        FromPrimitive::from_u64(n).unwrap()
+    }
+
+    #[test]
+    fn test_eval() {
+        // p(x) = 3 + 2x + x^2
+        let p = ScalarPoly::new(vec![sc(3), sc(2), sc(1)]);
+        assert!(p.degree() == 2);
+        assert!(p.eval(&sc(0)) == sc(3));
+        assert!(p.eval(&sc(5)) == sc(38));
+    }
+
+    #[test]
+    fn test_ops() {
+        let p = ScalarPoly::new_rand(&ScalarElem::new_rand(), 3);
+        let q = ScalarPoly::new_rand(&ScalarElem::new_rand(), 5);
+        let x = ScalarElem::new_rand();
+
+        assert!((p + q).eval(&x) == p.eval(&x) + q.eval(&x));
+        assert!((p - q).eval(&x) == p.eval(&x) - q.eval(&x));
+        assert!((p * q).eval(&x) == p.eval(&x) * q.eval(&x));
+        assert!((p * q).degree() == 8);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let secret = ScalarElem::new_rand();
+        let p = ScalarPoly::new_rand(&secret, 2);
+
+        let points: Vec<(ScalarElem, ScalarElem)> =
+            range(1u64, 4).map(|i| (sc(i), p.eval(&sc(i)))).collect();
+        let s = ScalarPoly::interpolate_at_zero(points.as_slice()).unwrap();
+        assert!(s == secret);
+
+        let dup = [(sc(1), sc(2)), (sc(1), sc(3))];
+        assert!(ScalarPoly::interpolate_at_zero(dup).is_none());
+    }
+
+    #[test]
+    fn test_lagrange_coefficients() {
+        let indices = [sc(1), sc(3), sc(4), sc(7)];
+        let coeffs = poly::lagrange_coefficients(indices).unwrap();
+
+        for i in range(0u, indices.len()) {
+            let c = poly::lagrange_coefficient(indices, i).unwrap();
+            assert!(c == *coeffs.get(i));
+        }
+
+        // Coefficients of a constant polynomial sum to one.
+        let mut s = ScalarElem::zero();
+        for c in coeffs.iter() {
+            s = s + *c;
+        }
+        assert!(s == sc(1));
+
+        assert!(poly::lagrange_coefficients([sc(0), sc(1)]).is_none());
+        assert!(poly::lagrange_coefficients([sc(2), sc(2)]).is_none());
+    }
+}
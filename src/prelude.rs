@@ -0,0 +1,47 @@
+//! Common imports
+//!
+//! Re-exports of the main types and traits of the crate: containers,
+//! scalars, points, keys, signatures, errors and the constant-time,
+//! generic group and primitive traits. This import surface is kept stable
+//! while the modules behind it get reorganized, downstream code should
+//! import from here (`use curve41417::prelude::*;` with
+//! `#![feature(globs)]`, or by name) rather than from the defining
+//! modules. Variants of `Error` are not re-exported, match them through
+//! `curve41417::error`.
+pub use aead::Aead;
+pub use bytes::{B416, B512, B832, Bytes, EdPoint, MontPoint, Scalar};
+pub use choice::{Choice, ConditionallySelectable};
+pub use digest::Digest;
+pub use ed::{BlindingPair, GroupElem, PointTable};
+pub use error::Error;
+pub use fe::FieldElem;
+pub use group::{Group, GroupScalar, PrimeGroup};
+pub use keys::{ClampApply, ClampVerify, Clamping, DhSecretKey};
+pub use keys::{EphemeralSecret, KeyPair, PublicKey, SecretKey, Seed};
+pub use mont::{Contributory, DhPolicy, Raw};
+pub use sc::ScalarElem;
+pub use sign::Signature;
+
+
+#[cfg(test)]
+mod tests {
+    use digest::sha256::Sha256;
+    use prelude::{Contributory, DhSecretKey, EphemeralSecret, KeyPair};
+    use prelude::{GroupElem, ScalarElem, Signature};
+
+
+    #[test]
+    fn test_prelude() {
+        let mut h = Sha256::new();
+        let kp = KeyPair::generate();
+        let sig: Signature = kp.sign(&mut h, []).unwrap();
+        assert!(kp.public().verify(&mut h, [], &sig));
+
+        let peer = DhSecretKey::generate();
+        let e = EphemeralSecret::generate();
+        assert!(e.diffie_hellman(&peer.public_key(), Contributory).is_ok());
+        let n = ScalarElem::new_rand();
+        assert!(GroupElem::base() * n ==
+                GroupElem::scalar_mult_base(&n.pack()));
+    }
+}
@@ -0,0 +1,214 @@
+//! Reference arithmetic
+//!
+//! Deliberately simple and slow implementation of the arithmetic `mod P`
+//! and `mod L` on little-endian byte strings: schoolbook multiplication
+//! and bit-by-bit long division. It shares no code with `fe` and `sc` and
+//! is only meant to cross-check them, see `debug_assert_consistent()`.
+//! It is not constant-time and its temporaries are not wiped, don't use
+//! it with secret values. Only available with the `reference` feature.
+use std::rand::Rng;
+
+use bytes::{B416, B512, Bytes};
+use constants::{L, P};
+use fe::FieldElem;
+use sc::ScalarElem;
+
+
+// Return the byte of index i of n, 0 past its end.
+fn byte(n: &[u8], i: uint) -> u8 {
+    if i < n.len() {
+        n[i]
+    } else {
+        0
+    }
+}
+
+// Return true iff a >= b.
+fn ge(a: &[u8], b: &[u8]) -> bool {
+    let l = if a.len() > b.len() { a.len() } else { b.len() };
+    for i in range(0u, l).rev() {
+        if byte(a, i) != byte(b, i) {
+            return byte(a, i) > byte(b, i);
+        }
+    }
+    true
+}
+
+// Return a + b.
+fn add_bytes(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let l = if a.len() > b.len() { a.len() } else { b.len() };
+    let mut r = Vec::with_capacity(l + 1);
+    let mut carry = 0u;
+    for i in range(0u, l) {
+        let s = byte(a, i) as uint + byte(b, i) as uint + carry;
+        r.push((s & 0xff) as u8);
+        carry = s >> 8;
+    }
+    r.push(carry as u8);
+    r
+}
+
+// Return a - b. Requirement: a >= b.
+fn sub_bytes(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut r = Vec::with_capacity(a.len());
+    let mut borrow = 0i;
+    for i in range(0u, a.len()) {
+        let mut d = a[i] as int - byte(b, i) as int - borrow;
+        borrow = 0;
+        if d < 0 {
+            d += 256;
+            borrow = 1;
+        }
+        r.push(d as u8);
+    }
+    assert!(borrow == 0);
+    r
+}
+
+// Return a * b.
+fn mul_bytes(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut r = Vec::from_elem(a.len() + b.len(), 0u8);
+    for i in range(0u, a.len()) {
+        let mut carry = 0u;
+        for j in range(0u, b.len()) {
+            let t = *r.get(i + j) as uint + a[i] as uint * b[j] as uint +
+                carry;
+            *r.get_mut(i + j) = (t & 0xff) as u8;
+            carry = t >> 8;
+        }
+        *r.get_mut(i + b.len()) = carry as u8;
+    }
+    r
+}
+
+// Return n mod m through long division, one bit of n at a time.
+fn reduce(n: &[u8], m: &[u8]) -> Vec<u8> {
+    let mut r = Vec::from_elem(m.len() + 1, 0u8);
+    for i in range(0u, 8 * n.len()).rev() {
+        r = add_bytes(r.as_slice(), r.as_slice());
+        *r.get_mut(0) |= (n[i / 8] >> (i % 8)) & 1;
+        if ge(r.as_slice(), m) {
+            r = sub_bytes(r.as_slice(), m);
+        }
+        r.truncate(m.len() + 1);
+    }
+    r.truncate(m.len());
+    r
+}
+
+fn to_b416(n: &[u8]) -> B416 {
+    let mut r: B416 = Bytes::new_zero();
+    for i in range(0u, 52) {
+        *r.get_mut(i) = byte(n, i);
+    }
+    r
+}
+
+// Field elements only keep the 414 low bits of their packed values.
+fn fe_input(a: &B416) -> Vec<u8> {
+    let mut t = Vec::from_slice(a.as_bytes());
+    *t.get_mut(51) &= 0x3f;
+    reduce(t.as_slice(), P)
+}
+
+/// Return `a + b mod P`, top 2 bits of `a` and `b` are ignored.
+pub fn fe_add(a: &B416, b: &B416) -> B416 {
+    let s = add_bytes(fe_input(a).as_slice(), fe_input(b).as_slice());
+    to_b416(reduce(s.as_slice(), P).as_slice())
+}
+
+/// Return `a - b mod P`, top 2 bits of `a` and `b` are ignored.
+pub fn fe_sub(a: &B416, b: &B416) -> B416 {
+    let t = add_bytes(fe_input(a).as_slice(), P);
+    let d = sub_bytes(t.as_slice(), fe_input(b).as_slice());
+    to_b416(reduce(d.as_slice(), P).as_slice())
+}
+
+/// Return `a * b mod P`, top 2 bits of `a` and `b` are ignored.
+pub fn fe_mul(a: &B416, b: &B416) -> B416 {
+    let p = mul_bytes(fe_input(a).as_slice(), fe_input(b).as_slice());
+    to_b416(reduce(p.as_slice(), P).as_slice())
+}
+
+/// Return `n mod L` for a little-endian value `n` of any length.
+pub fn sc_reduce(n: &[u8]) -> B416 {
+    to_b416(reduce(n, L).as_slice())
+}
+
+/// Return `a + b mod L`.
+pub fn sc_add(a: &B416, b: &B416) -> B416 {
+    sc_reduce(add_bytes(a.as_bytes(), b.as_bytes()).as_slice())
+}
+
+/// Return `a - b mod L`.
+pub fn sc_sub(a: &B416, b: &B416) -> B416 {
+    let ra = reduce(a.as_bytes(), L);
+    let t = add_bytes(ra.as_slice(), L);
+    let d = sub_bytes(t.as_slice(), reduce(b.as_bytes(), L).as_slice());
+    sc_reduce(d.as_slice())
+}
+
+/// Return `a * b mod L`.
+pub fn sc_mul(a: &B416, b: &B416) -> B416 {
+    sc_reduce(mul_bytes(a.as_bytes(), b.as_bytes()).as_slice())
+}
+
+/// Check `iterations` times on random inputs generated from `rng` that
+/// the field (`FieldElem`) and scalar (`ScalarElem`) arithmetic agree with
+/// this reference implementation. Fails on the first mismatch.
+pub fn debug_assert_consistent<R: Rng>(rng: &mut R, iterations: uint) {
+    for _ in range(0u, iterations) {
+        let a: B416 = rng.gen();
+        let b: B416 = rng.gen();
+        let x = FieldElem::unpack(&a);
+        let y = FieldElem::unpack(&b);
+
+        assert!((x + y).pack() == fe_add(&a, &b));
+        assert!((x - y).pack() == fe_sub(&a, &b));
+        assert!((x * y).pack() == fe_mul(&a, &b));
+
+        let c: B512 = rng.gen();
+        let d: B512 = rng.gen();
+        let m = ScalarElem::unpack(&c).unwrap();
+        let n = ScalarElem::unpack(&d).unwrap();
+        let rc = sc_reduce(c.as_bytes());
+        let rd = sc_reduce(d.as_bytes());
+
+        assert!(*m.pack().get_ref() == rc);
+        assert!(*(m + n).pack().get_ref() == sc_add(&rc, &rd));
+        assert!(*(m - n).pack().get_ref() == sc_sub(&rc, &rd));
+        assert!(*(m * n).pack().get_ref() == sc_mul(&rc, &rd));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bytes::{B416, Bytes};
+    use constants;
+    use reference;
+    use utils;
+
+
+    #[test]
+    fn test_reference() {
+        let mut one: B416 = Bytes::new_zero();
+        *one.get_mut(0) = 1;
+        let mut pm1: B416 = Bytes::from_bytes(constants::P).unwrap();
+        *pm1.get_mut(0) -= 1;
+        let p: B416 = Bytes::from_bytes(constants::P).unwrap();
+        let l: B416 = Bytes::from_bytes(constants::L).unwrap();
+        let zero: B416 = Bytes::new_zero();
+
+        assert!(reference::fe_add(&p, &one) == one);
+        assert!(reference::fe_sub(&zero, &one) == pm1);
+        assert!(reference::fe_mul(&pm1, &pm1) == one);
+        assert!(reference::sc_reduce(l.as_bytes()) == zero);
+        assert!(reference::sc_add(&l, &one) == one);
+    }
+
+    #[test]
+    fn test_debug_assert_consistent() {
+        reference::debug_assert_consistent(&mut utils::urandom_rng(), 16);
+    }
+}
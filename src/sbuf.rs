@@ -7,6 +7,7 @@ use libc::types::common::c95::c_void;
 use libc::types::os::arch::c95::size_t;
 use serialize::{Encodable, Encoder, Decodable, Decoder};
 use serialize::hex::ToHex;
+use std::cmp;
 use std::fmt;
 use std::intrinsics;
 use std::mem;
@@ -14,7 +15,7 @@ use std::os;
 use std::ptr;
 use std::rand::Rng;
 use std::raw::Slice;
-use std::slice::{Items, MutItems};
+use std::slice::{Chunks, Items, MutChunks, MutItems};
 
 use utils;
 
@@ -22,14 +23,36 @@ use utils;
 pub trait Allocator {
     fn new() -> Self;
 
+    /// Minimum alignment in bytes guaranteed for every buffer allocated by
+    /// this allocator, whatever the type of its elements.
+    fn min_align(&self) -> uint {
+        1
+    }
+
+    /// Allocate `size` bytes aligned on `align` bytes, `align` must be a
+    /// power of two.
     unsafe fn allocate(&self, size: uint, align: uint) -> *mut u8;
 
     unsafe fn deallocate(&self, ptr: *mut u8, size: uint, align: uint);
+
+    /// Make the memory of the buffer `ptr` of `size` bytes read-only iff
+    /// `read_only` is `true`, readable and writable otherwise. Return
+    /// `false` if this allocator doesn't support memory protection.
+    unsafe fn protect(&self, _: *mut u8, _: uint, _: bool) -> bool {
+        false
+    }
 }
 
 /// Default allocator used to allocate and deallocate memory for secure
 /// buffers.
+#[cfg(not(feature = "small"))]
 pub type DefaultAllocator = GuardedHeapAllocator;
+/// Default allocator used to allocate and deallocate memory for secure
+/// buffers. The `small` build profile uses the standard heap: buffers are
+/// still wiped but no longer surrounded by guarded pages, which take
+/// three pages for each buffer.
+#[cfg(feature = "small")]
+pub type DefaultAllocator = StdHeapAllocator;
 
 
 /// Standard heap allocator, use Rust's allocator (currently jemalloc),
@@ -51,6 +74,32 @@ impl Allocator for StdHeapAllocator {
 }
 
 
+/// Standard heap allocator returning buffers aligned on 64 bytes (a cache
+/// line), suitable for SIMD loads and stores. Does not implement guarded
+/// pages.
+pub struct AlignedHeapAllocator;
+
+static HEAP_ALIGN: uint = 64;
+
+impl Allocator for AlignedHeapAllocator {
+    fn new() -> AlignedHeapAllocator {
+        AlignedHeapAllocator
+    }
+
+    fn min_align(&self) -> uint {
+        HEAP_ALIGN
+    }
+
+    unsafe fn allocate(&self, size: uint, align: uint) -> *mut u8 {
+        heap::allocate(size, cmp::max(align, HEAP_ALIGN))
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, size: uint, align: uint) {
+        heap::deallocate(ptr, size, cmp::max(align, HEAP_ALIGN))
+    }
+}
+
+
 #[cfg(target_os = "freebsd")]
 mod impmap {
     use libc::consts::os::extra::MAP_NOCORE;
@@ -93,8 +142,13 @@ impl Allocator for GuardedHeapAllocator {
         GuardedHeapAllocator
     }
 
-    unsafe fn allocate(&self, size: uint, _: uint) -> *mut u8 {
+    fn min_align(&self) -> uint {
+        os::page_size()
+    }
+
+    unsafe fn allocate(&self, size: uint, align: uint) -> *mut u8 {
         let page_size = os::page_size();
+        assert!(align <= page_size);
         let full_size = round_up(size, page_size) + 2 * page_size;
 
         let null_addr: *const u8 = ptr::null();
@@ -133,6 +187,26 @@ impl Allocator for GuardedHeapAllocator {
         intrinsics::offset(ptr as *const c_void, page_size as int) as *mut u8
     }
 
+    unsafe fn protect(&self, ptr: *mut u8, size: uint, read_only: bool)
+                      -> bool {
+        // Buffers start on a page boundary and span their whole pages.
+        let full_size = round_up(size, os::page_size());
+        let prot = if read_only {
+            PROT_READ
+        } else {
+            PROT_READ | PROT_WRITE
+        };
+
+        let ret = mman::mprotect(ptr as *mut c_void, full_size as size_t,
+                                 prot);
+        if ret != 0 {
+            let errno = os::errno();
+            fail!("mprotect failed: {} ({})",
+                  os::error_string(errno as uint), errno);
+        }
+        true
+    }
+
     unsafe fn deallocate(&self, ptr: *mut u8, size: uint, _: uint) {
         let page_size = os::page_size();
         let full_size = round_up(size, page_size) + 2 * page_size;
@@ -208,6 +282,89 @@ mod impadv {
 }
 
 
+/// Secure buffers usage statistics, see `stats()`.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub struct Stats {
+    /// Number of currently allocated buffers.
+    pub live_buffers: uint,
+    /// Number of bytes currently allocated.
+    pub live_bytes: uint,
+    /// Maximum value reached by `live_bytes`.
+    pub peak_bytes: uint,
+    /// Number of bytes zeroed-out on deallocation.
+    pub wiped_bytes: uint
+}
+
+#[cfg(feature = "sbuf_stats")]
+mod impstats {
+    use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+
+    use super::Stats;
+
+
+    static mut LIVE_BUFFERS: AtomicUint = INIT_ATOMIC_UINT;
+    static mut LIVE_BYTES: AtomicUint = INIT_ATOMIC_UINT;
+    static mut PEAK_BYTES: AtomicUint = INIT_ATOMIC_UINT;
+    static mut WIPED_BYTES: AtomicUint = INIT_ATOMIC_UINT;
+
+    pub fn on_alloc(size: uint) {
+        unsafe {
+            LIVE_BUFFERS.fetch_add(1, SeqCst);
+            let live = LIVE_BYTES.fetch_add(size, SeqCst) + size;
+            loop {
+                let peak = PEAK_BYTES.load(SeqCst);
+                if live <= peak ||
+                    PEAK_BYTES.compare_and_swap(peak, live, SeqCst) == peak {
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn on_dealloc(size: uint) {
+        unsafe {
+            LIVE_BUFFERS.fetch_sub(1, SeqCst);
+            LIVE_BYTES.fetch_sub(size, SeqCst);
+            WIPED_BYTES.fetch_add(size, SeqCst);
+        }
+    }
+
+    pub fn stats() -> Option<Stats> {
+        unsafe {
+            Some(Stats {
+                live_buffers: LIVE_BUFFERS.load(SeqCst),
+                live_bytes: LIVE_BYTES.load(SeqCst),
+                peak_bytes: PEAK_BYTES.load(SeqCst),
+                wiped_bytes: WIPED_BYTES.load(SeqCst)
+            })
+        }
+    }
+}
+
+#[cfg(not(feature = "sbuf_stats"))]
+mod impstats {
+    use super::Stats;
+
+
+    pub fn on_alloc(_: uint) {
+    }
+
+    pub fn on_dealloc(_: uint) {
+    }
+
+    pub fn stats() -> Option<Stats> {
+        None
+    }
+}
+
+/// Return the current secure buffers usage statistics. Statistics are
+/// only collected when the crate is built with the `sbuf_stats` feature,
+/// otherwise `None` is returned.
+pub fn stats() -> Option<Stats> {
+    self::impstats::stats()
+}
+
+
 unsafe fn alloc<A: Allocator, T>(count: uint) -> *mut T {
     let size_of_t = mem::size_of::<T>();
 
@@ -218,7 +375,8 @@ unsafe fn alloc<A: Allocator, T>(count: uint) -> *mut T {
 
     // allocate
     let allocator: A = Allocator::new();
-    let ptr = allocator.allocate(size, mem::min_align_of::<T>()) as *mut T;
+    let align = cmp::max(mem::min_align_of::<T>(), allocator.min_align());
+    let ptr = allocator.allocate(size, align) as *mut T;
 
     // mlock
     let ret = mman::mlock(ptr as *const c_void, size as size_t);
@@ -231,6 +389,8 @@ unsafe fn alloc<A: Allocator, T>(count: uint) -> *mut T {
     // madvise
     self::impadv::madvise(ptr as *mut u8, size);
 
+    self::impstats::on_alloc(size);
+
     ptr
 }
 
@@ -248,6 +408,7 @@ unsafe fn dealloc<A: Allocator, T>(ptr: *mut T, count: uint) {
     // FIXME: not sure how much this llvm intrinsics could not be
     // optimized-out, maybe it would be better to use memset_s.
     intrinsics::volatile_set_memory(ptr, 0, count);
+    self::impstats::on_dealloc(size);
 
     // munlock
     let ret = mman::munlock(ptr as *const c_void, size as size_t);
@@ -259,7 +420,8 @@ unsafe fn dealloc<A: Allocator, T>(ptr: *mut T, count: uint) {
 
     // deallocate
     let allocator: A = Allocator::new();
-    allocator.deallocate(ptr as *mut u8, size, mem::min_align_of::<T>())
+    let align = cmp::max(mem::min_align_of::<T>(), allocator.min_align());
+    allocator.deallocate(ptr as *mut u8, size, align)
 }
 
 
@@ -267,14 +429,16 @@ unsafe fn dealloc<A: Allocator, T>(ptr: *mut T, count: uint) {
 //        pub struct SBuf<T, A = StdHeapAllocator> {
 pub struct SBuf<A, T> {
     len: uint,
-    ptr: *mut T
+    ptr: *mut T,
+    frozen: bool
 }
 
 impl<A: Allocator, T> SBuf<A, T> {
     fn from_raw_parts(length: uint, ptr: *mut T) -> SBuf<A, T> {
         SBuf {
             len: length,
-            ptr: ptr
+            ptr: ptr,
+            frozen: false
         }
     }
 
@@ -345,8 +509,10 @@ impl<A: Allocator, T> SBuf<A, T> {
         }
     }
 
-    /// Return a mutable pointer to buffer's memory.
+    /// Return a mutable pointer to buffer's memory. Fails if the buffer
+    /// is frozen.
     pub fn as_mut_ptr(&mut self) -> *mut T {
+        assert!(!self.frozen);
         // see above for the 0-size check
         if mem::size_of::<T>() == 0 {
             1 as *mut T
@@ -376,17 +542,32 @@ impl<A: Allocator, T> SBuf<A, T> {
     }
 
     /// Return a reference to the value at index `index`. Fails if
-    /// `index` is out of bounds.
+    /// `index` is out of bounds, only meant for indexes bounded by the
+    /// fixed size of the buffer; use `checked_get()` for indexes derived
+    /// from untrusted input.
     pub fn get<'a>(&'a self, index: uint) -> &'a T {
         &self.as_slice()[index]
     }
 
     /// Return a mutable reference to the value at index `index`. Fails
-    /// if `index` is out of bounds.
+    /// if `index` is out of bounds, see `get()`.
     pub fn get_mut<'a>(&'a mut self, index: uint) -> &'a mut T {
         &mut self.as_mut_slice()[index]
     }
 
+    /// Return a reference to the value at index `index`, or `None` if
+    /// `index` is out of bounds.
+    pub fn checked_get<'a>(&'a self, index: uint) -> Option<&'a T> {
+        self.as_slice().get(index)
+    }
+
+    /// Return a mutable reference to the value at index `index`, or
+    /// `None` if `index` is out of bounds.
+    pub fn checked_get_mut<'a>(&'a mut self, index: uint)
+                               -> Option<&'a mut T> {
+        self.as_mut_slice().get_mut(index)
+    }
+
     /// Return an iterator over references to the elements of the buffer
     /// in order.
     pub fn iter<'a>(&'a self) -> Items<'a, T> {
@@ -448,6 +629,80 @@ impl<A: Allocator, T> SBuf<A, T> {
         self.as_mut_slice().mut_split_at(mid)
     }
 
+    /// Return a borrowed view over the whole buffer.
+    pub fn view<'a>(&'a self) -> SBufRef<'a, T> {
+        SBufRef::new(self.as_slice())
+    }
+
+    /// Return a borrowed mutable view over the whole buffer.
+    pub fn mut_view<'a>(&'a mut self) -> SBufMutRef<'a, T> {
+        SBufMutRef::new(self.as_mut_slice())
+    }
+
+    /// Return a pair of views dividing the buffer at index `mid`, see
+    /// `SBufRef::split_at()`.
+    pub fn split_at<'a>(&'a self, mid: uint) -> (SBufRef<'a, T>,
+                                                 SBufRef<'a, T>) {
+        self.view().split_at(mid)
+    }
+
+    /// Return an iterator over views of `size` elements of the buffer,
+    /// the last view may be shorter. Fails if `size` is `0`.
+    pub fn chunks<'a>(&'a self, size: uint) -> SBufChunks<'a, T> {
+        SBufChunks {
+            iter: self.as_slice().chunks(size)
+        }
+    }
+
+    /// Return an iterator over mutable views of `size` elements of the
+    /// buffer, the last view may be shorter. Fails if `size` is `0`.
+    pub fn mut_chunks<'a>(&'a mut self, size: uint) -> SBufMutChunks<'a, T> {
+        SBufMutChunks {
+            iter: self.as_mut_slice().mut_chunks(size)
+        }
+    }
+
+    /// Make the memory of this buffer read-only, any later attempt to
+    /// write to it fails. Return `false` if its allocator doesn't support
+    /// memory protection (only `GuardedHeapAllocator` does), in which case
+    /// the buffer is left unchanged.
+    pub fn freeze(&mut self) -> bool {
+        if self.frozen || self.len == 0 {
+            return self.frozen;
+        }
+
+        let allocator: A = Allocator::new();
+        self.frozen = unsafe {
+            allocator.protect(self.ptr as *mut u8, self.size(), true)
+        };
+        self.frozen
+    }
+
+    /// Make the memory of a frozen buffer writable again.
+    pub fn unfreeze(&mut self) {
+        if !self.frozen {
+            return;
+        }
+
+        let allocator: A = Allocator::new();
+        unsafe {
+            allocator.protect(self.ptr as *mut u8, self.size(), false);
+        }
+        self.frozen = false;
+    }
+
+    /// Return the alignment in bytes guaranteed for the memory of this
+    /// buffer.
+    pub fn align(&self) -> uint {
+        let allocator: A = Allocator::new();
+        cmp::max(mem::min_align_of::<T>(), allocator.min_align())
+    }
+
+    /// Return `true` iff this buffer is frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
     /// Reverse the order of elements in a buffer, in place.
     pub fn reverse(&mut self) {
         self.as_mut_slice().reverse()
@@ -471,6 +726,8 @@ impl<A: Allocator, T: FromPrimitive> SBuf<A, T> {
 impl<A: Allocator, T> Drop for SBuf<A, T> {
     fn drop(&mut self) {
         if self.len != 0 && !self.ptr.is_null() {
+            // Memory must be writable to be zeroed-out.
+            self.unfreeze();
             unsafe {
                 dealloc::<A, T>(self.ptr, self.len)
             }
@@ -514,12 +771,25 @@ impl<A: Allocator,
     }
 }
 
+/// Maximum number of elements of a decoded `SBuf`, longer sequences are
+/// rejected before anything is allocated. The largest fixed-size buffers
+/// of the crate hold 114 bytes.
+pub static MAX_DECODED_LEN: uint = 1024;
+
 impl<A: Allocator,
      E,
      D: Decoder<E>,
      T: Decodable<D, E>> Decodable<D, E> for SBuf<A, T> {
     fn decode(d: &mut D) -> Result<SBuf<A, T>, E> {
         d.read_seq(|d, len| {
+            // Empty buffers can't be allocated, and len comes from the
+            // input: it is bounded before allocating.
+            if len == 0 {
+                return Err(d.error("empty secure buffer"));
+            }
+            if len > MAX_DECODED_LEN {
+                return Err(d.error("secure buffer too long"));
+            }
             let mut n = SBuf::with_length(len);
             for i in range(0u, len) {
                 *n.get_mut(i) = try!(d.read_seq_elt(i, |d| Decodable::decode(d)));
@@ -562,9 +832,295 @@ impl ToHex for u8 {
 */
 
 
+/// Borrowed view over (part of) the elements of a `SBuf`.
+///
+/// Views don't copy elements, the memory stays owned by the viewed buffer
+/// which still wipes it when it is dropped.
+pub struct SBufRef<'a, T> {
+    slice: &'a [T]
+}
+
+impl<'a, T> SBufRef<'a, T> {
+    fn new(slice: &'a [T]) -> SBufRef<'a, T> {
+        SBufRef {
+            slice: slice
+        }
+    }
+
+    /// Work with `self` as a slice.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.slice
+    }
+
+    /// Return a reference to the value at index `index`. Fails if
+    /// `index` is out of bounds.
+    pub fn get(&self, index: uint) -> &'a T {
+        &self.slice[index]
+    }
+
+    /// Return a pair of views dividing this view at index `mid`, the
+    /// first one covers indices `[0, mid)` and the second one indices
+    /// `[mid, len)`. Fails if `mid > len`.
+    pub fn split_at(&self, mid: uint) -> (SBufRef<'a, T>, SBufRef<'a, T>) {
+        (SBufRef::new(self.slice.slice_to(mid)),
+         SBufRef::new(self.slice.slice_from(mid)))
+    }
+}
+
+impl<'a, T> Collection for SBufRef<'a, T> {
+    fn len(&self) -> uint {
+        self.slice.len()
+    }
+}
+
+/// Borrowed mutable view over (part of) the elements of a `SBuf`, see
+/// `SBufRef`.
+pub struct SBufMutRef<'a, T> {
+    slice: &'a mut [T]
+}
+
+impl<'a, T> SBufMutRef<'a, T> {
+    fn new(slice: &'a mut [T]) -> SBufMutRef<'a, T> {
+        SBufMutRef {
+            slice: slice
+        }
+    }
+
+    /// Work with `self` as a slice.
+    pub fn as_slice<'b>(&'b self) -> &'b [T] {
+        &*self.slice
+    }
+
+    /// Work with `self` as a mutable slice.
+    pub fn as_mut_slice<'b>(&'b mut self) -> &'b mut [T] {
+        &mut *self.slice
+    }
+
+    /// Return a mutable reference to the value at index `index`. Fails
+    /// if `index` is out of bounds.
+    pub fn get_mut<'b>(&'b mut self, index: uint) -> &'b mut T {
+        &mut self.slice[index]
+    }
+
+    /// Consume this view and return a pair of mutable views dividing it at
+    /// index `mid`. Fails if `mid > len`.
+    pub fn mut_split_at(self, mid: uint) -> (SBufMutRef<'a, T>,
+                                             SBufMutRef<'a, T>) {
+        let (a, b) = self.slice.mut_split_at(mid);
+        (SBufMutRef::new(a), SBufMutRef::new(b))
+    }
+}
+
+impl<'a, T> Collection for SBufMutRef<'a, T> {
+    fn len(&self) -> uint {
+        self.slice.len()
+    }
+}
+
+/// Iterator over views of a `SBuf`, see `SBuf::chunks()`.
+pub struct SBufChunks<'a, T> {
+    iter: Chunks<'a, T>
+}
+
+impl<'a, T> Iterator<SBufRef<'a, T>> for SBufChunks<'a, T> {
+    fn next(&mut self) -> Option<SBufRef<'a, T>> {
+        self.iter.next().map(|s| SBufRef::new(s))
+    }
+}
+
+/// Iterator over mutable views of a `SBuf`, see `SBuf::mut_chunks()`.
+pub struct SBufMutChunks<'a, T> {
+    iter: MutChunks<'a, T>
+}
+
+impl<'a, T> Iterator<SBufMutRef<'a, T>> for SBufMutChunks<'a, T> {
+    fn next(&mut self) -> Option<SBufMutRef<'a, T>> {
+        self.iter.next().map(|s| SBufMutRef::new(s))
+    }
+}
+
+
+/// Maximum length of a `StackBuf`.
+pub static STACK_BUF_SIZE: uint = 104;
+
+/// Fixed-capacity buffer of limbs allocated on the stack, used for the
+/// temporaries of arithmetic operations in hot loops (52, 78, 103 and 104
+/// limbs) without touching the allocator. Field and scalar elements store
+/// their limbs in the fixed-size `Limbs26` and `Limbs52`, `SBuf` remains
+/// used for variable sizes and for long-lived secrets. Its memory is
+/// zeroed-out when it is dropped, but unlike `SBuf` it is neither locked
+/// in memory nor surrounded by guarded pages. Its capacity is fixed as the
+/// language has no integer generic parameters.
+pub struct StackBuf {
+    len: uint,
+    buf: [i64, ..STACK_BUF_SIZE]
+}
+
+impl StackBuf {
+    /// New buffer of length `length` with its memory zeroed-out. Fails if
+    /// `length > STACK_BUF_SIZE`.
+    pub fn new_zero(length: uint) -> StackBuf {
+        assert!(length <= STACK_BUF_SIZE);
+        StackBuf {
+            len: length,
+            buf: [0, ..STACK_BUF_SIZE]
+        }
+    }
+
+    /// New buffer holding a copy of `s`. Fails if `s.len() >
+    /// STACK_BUF_SIZE`.
+    pub fn from_slice(s: &[i64]) -> StackBuf {
+        let mut r = StackBuf::new_zero(s.len());
+        for (d, v) in r.mut_iter().zip(s.iter()) {
+            *d = *v;
+        }
+        r
+    }
+
+    /// Return an iterator over the limbs of this buffer.
+    pub fn iter<'a>(&'a self) -> Items<'a, i64> {
+        self.as_slice().iter()
+    }
+
+    /// Return an iterator of mutable references over the limbs of this
+    /// buffer.
+    pub fn mut_iter<'a>(&'a mut self) -> MutItems<'a, i64> {
+        self.as_mut_slice().mut_iter()
+    }
+
+    /// Work with `self` as a slice.
+    pub fn as_slice<'a>(&'a self) -> &'a [i64] {
+        self.buf.slice_to(self.len)
+    }
+
+    /// Work with `self` as a mutable slice.
+    pub fn as_mut_slice<'a>(&'a mut self) -> &'a mut [i64] {
+        self.buf.mut_slice_to(self.len)
+    }
+
+    /// Return a reference to the value at index `index`. Fails if
+    /// `index` is out of bounds, only meant for indexes bounded by the
+    /// fixed size of the buffer; use `checked_get()` for indexes derived
+    /// from untrusted input.
+    pub fn get<'a>(&'a self, index: uint) -> &'a i64 {
+        &self.as_slice()[index]
+    }
+
+    /// Return a mutable reference to the value at index `index`. Fails
+    /// if `index` is out of bounds, see `get()`.
+    pub fn get_mut<'a>(&'a mut self, index: uint) -> &'a mut i64 {
+        &mut self.as_mut_slice()[index]
+    }
+
+    /// Return a reference to the value at index `index`, or `None` if
+    /// `index` is out of bounds.
+    pub fn checked_get<'a>(&'a self, index: uint) -> Option<&'a i64> {
+        self.as_slice().get(index)
+    }
+
+    /// Return a mutable reference to the value at index `index`, or
+    /// `None` if `index` is out of bounds.
+    pub fn checked_get_mut<'a>(&'a mut self, index: uint)
+                               -> Option<&'a mut i64> {
+        self.as_mut_slice().get_mut(index)
+    }
+}
+
+impl Drop for StackBuf {
+    fn drop(&mut self) {
+        unsafe {
+            intrinsics::volatile_set_memory(self.buf.as_mut_ptr(), 0,
+                                            STACK_BUF_SIZE);
+        }
+    }
+}
+
+impl Clone for StackBuf {
+    fn clone(&self) -> StackBuf {
+        StackBuf::from_slice(self.as_slice())
+    }
+}
+
+impl Collection for StackBuf {
+    fn len(&self) -> uint {
+        self.len
+    }
+}
+
+// Fixed-size buffer of $size limbs held inline, in the element owning it.
+// Like StackBuf it is zeroed-out when dropped but neither locked in memory
+// nor guarded. Its limbs are only accessed through slices, indexes are
+// bounded by the fixed sizes of the arithmetic.
+macro_rules! limbs_impl(($name:ident, $size:expr) => (
+    pub struct $name {
+        buf: [i64, ..$size]
+    }
+
+    impl $name {
+        pub fn new_zero() -> $name {
+            $name {
+                buf: [0, ..$size]
+            }
+        }
+
+        // Fails unless s holds exactly $size limbs.
+        pub fn from_slice(s: &[i64]) -> $name {
+            assert!(s.len() == $size);
+            let mut r = $name::new_zero();
+            for (d, v) in r.buf.mut_iter().zip(s.iter()) {
+                *d = *v;
+            }
+            r
+        }
+
+        pub fn as_slice<'a>(&'a self) -> &'a [i64] {
+            self.buf.as_slice()
+        }
+
+        pub fn as_mut_slice<'a>(&'a mut self) -> &'a mut [i64] {
+            self.buf.as_mut_slice()
+        }
+
+        pub fn mut_iter<'a>(&'a mut self) -> MutItems<'a, i64> {
+            self.buf.mut_iter()
+        }
+    }
+
+    impl Drop for $name {
+        fn drop(&mut self) {
+            unsafe {
+                intrinsics::volatile_set_memory(self.buf.as_mut_ptr(), 0,
+                                                $size);
+            }
+        }
+    }
+
+    impl Clone for $name {
+        fn clone(&self) -> $name {
+            $name::from_slice(self.as_slice())
+        }
+    }
+
+    impl Collection for $name {
+        fn len(&self) -> uint {
+            $size
+        }
+    }
+))
+
+limbs_impl!(Limbs26, 26)
+limbs_impl!(Limbs52, 52)
+
+
 #[cfg(test)]
 mod test {
-    use sbuf::{StdHeapAllocator, SBuf};
+    use serialize::json;
+    use std::mem;
+
+    use sbuf;
+    use sbuf::{AlignedHeapAllocator, GuardedHeapAllocator, StdHeapAllocator,
+               SBuf, StackBuf};
+    use sbuf::{Limbs26, Limbs52};
 
 
     #[test]
@@ -590,5 +1146,143 @@ mod test {
             SBuf::from_buf(c.as_ptr(), c.len())
         };
         assert!(d == c);
+
+        let mut e: SBuf<StdHeapAllocator, u8> =
+            SBuf::from_slice(s.slice_to(4));
+        assert!(e.checked_get(3) == Some(&3u8));
+        assert!(e.checked_get(4).is_none());
+        *e.checked_get_mut(0).unwrap() = 9;
+        assert!(*e.get(0) == 9);
+        assert!(e.checked_get_mut(256).is_none());
+    }
+
+    #[test]
+    fn test_stack_buf() {
+        let mut a = StackBuf::new_zero(78);
+        assert!(a.len() == 78);
+        assert!(a.as_slice() == [0i64, ..78].as_slice());
+
+        for i in range(0u, 78) {
+            *a.get_mut(i) = i as i64;
+        }
+        let b: SBuf<StdHeapAllocator, i64> = SBuf::from_slice(a.as_slice());
+        assert!(b.as_slice() == a.as_slice());
+
+        let c = a.clone();
+        assert!(c.len() == 78 && c.as_slice() == a.as_slice());
+        let d = StackBuf::from_slice(b.slice_to(52));
+        assert!(d.iter().enumerate().all(|(i, x)| *x == i as i64));
+        for x in a.mut_iter() {
+            *x = -1;
+        }
+        assert!(c.as_slice() != a.as_slice());
+        assert!(a.checked_get(77) == Some(&-1i64));
+        assert!(a.checked_get(78).is_none());
+        assert!(a.checked_get_mut(78).is_none());
+    }
+
+    #[test]
+    fn test_decode_length() {
+        let b: SBuf<StdHeapAllocator, u8> = json::decode("[1,2,3]").unwrap();
+        assert!(b.as_slice() == [1u8, 2, 3].as_slice());
+        let long = Vec::from_elem(sbuf::MAX_DECODED_LEN + 1, "0");
+        let s = format!("[{}]", long.connect(","));
+        let r: json::DecodeResult<SBuf<StdHeapAllocator, u8>> =
+            json::decode(s.as_slice());
+        assert!(r.is_err());
+        let r: json::DecodeResult<SBuf<StdHeapAllocator, u8>> =
+            json::decode("[]");
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_limbs() {
+        let a = Limbs26::new_zero();
+        assert!(a.len() == 26 && a.as_slice() == [0i64, ..26].as_slice());
+        let v = Vec::from_fn(52, |i| i as i64);
+        let mut b = Limbs52::from_slice(v.as_slice());
+        let c = b.clone();
+        assert!(c.as_slice() == v.as_slice());
+        for x in b.mut_iter() {
+            *x = -1;
+        }
+        assert!(b.as_slice() == [-1i64, ..52].as_slice());
+        assert!(c.as_slice() == v.as_slice());
+    }
+
+    #[cfg(feature = "sbuf_stats")]
+    #[test]
+    fn test_stats() {
+        let before = sbuf::stats().unwrap();
+        {
+            let a: SBuf<StdHeapAllocator, i64> = SBuf::new_zero(32);
+            assert!(a.len() == 32);
+            let s = sbuf::stats().unwrap();
+            assert!(s.peak_bytes >= 256);
+        }
+        let after = sbuf::stats().unwrap();
+        assert!(after.wiped_bytes >= before.wiped_bytes + 256);
+    }
+
+    #[cfg(not(feature = "sbuf_stats"))]
+    #[test]
+    fn test_stats() {
+        assert!(sbuf::stats().is_none());
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut a: SBuf<GuardedHeapAllocator, i64> = SBuf::new_rand(64);
+        let b = a.clone();
+        assert!(a.freeze());
+        assert!(a.is_frozen());
+        assert!(a == b);
+
+        a.unfreeze();
+        *a.get_mut(0) = 42;
+        assert!(a.freeze());
+
+        let mut c: SBuf<StdHeapAllocator, i64> = SBuf::new_zero(64);
+        assert!(!c.freeze());
+        assert!(!c.is_frozen());
+    }
+
+    #[test]
+    fn test_align() {
+        let a: SBuf<AlignedHeapAllocator, i64> = SBuf::new_zero(26);
+        assert!(a.align() == 64);
+        assert!(a.as_ptr() as uint % 64 == 0);
+
+        let b: SBuf<GuardedHeapAllocator, u8> = SBuf::new_zero(3);
+        assert!(b.as_ptr() as uint % b.align() == 0);
+
+        let c: SBuf<StdHeapAllocator, i64> = SBuf::new_zero(8);
+        assert!(c.align() == mem::min_align_of::<i64>());
+    }
+
+    #[test]
+    fn test_views() {
+        let mut r: [i64, ..103] = [0, ..103];
+        for i in range(0u, 103) {
+            r[i] = i as i64;
+        }
+        let mut a: SBuf<StdHeapAllocator, i64> = SBuf::from_slice(r);
+
+        let (lo, hi) = a.split_at(52);
+        assert!(lo.len() == 52 && hi.len() == 51);
+        assert!(lo.as_slice() == r.slice_to(52));
+        assert!(*hi.get(0) == 52);
+
+        let lens: Vec<uint> = a.chunks(26).map(|c| c.len()).collect();
+        assert!(lens == vec![26, 26, 26, 25]);
+
+        for mut c in a.mut_chunks(26) {
+            *c.get_mut(0) = -1;
+        }
+        assert!(*a.get(26) == -1 && *a.get(27) == 27);
+
+        let (mut x, _) = a.mut_view().mut_split_at(1);
+        *x.get_mut(0) = 42;
+        assert!(*a.view().get(0) == 42);
     }
 }
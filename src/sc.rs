@@ -4,40 +4,80 @@ use std::default::Default;
 use std::fmt::{Show, Formatter, Result};
 use std::io::extensions;
 use std::rand::{Rand, Rng};
-
-use bytes::{B416, B832, Bytes, Scalar, Uniformity};
-use sbuf::{DefaultAllocator, SBuf};
+use std::result;
+
+use audit;
+use bytes::{B416, B512, B832, Bytes, Scalar, Uniformity};
+use choice::{Choice, ConditionallySelectable};
+use constants;
+use constants::L;
+use error;
+use error::{Error, NonCanonical, UnsupportedLength};
+use limbs;
+use sbuf::{Limbs52, StackBuf};
 use utils;
 
 
 static SCE_SIZE: uint = 52;
 
-// L = 2^411 - d
-//   = 2^411 - 33364140863755142520810177694098385178984727200411208589594759
-static L: [u8, ..52] = [
-  0x79, 0xaf, 0x06, 0xe1, 0xa5, 0x71, 0x0e, 0x1b,
-  0x18, 0xcf, 0x63, 0xad, 0x38, 0x03, 0x1c, 0x6f,
-  0xb3, 0x22, 0x60, 0x70, 0xcf, 0x14, 0x24, 0xc9,
-  0x3c, 0xeb, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-  0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-  0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-  0xff, 0xff, 0xff, 0x07];
+// Input lengths accepted by unpack_slice().
+static UNPACK_LENGTHS: &'static [uint] = &[52, 64, 84, 96, 104, 114];
+
+// Maximum number of products accumulated in a double-width buffer before
+// it must be weakly reduced. Limbs of unpacked scalars are bounded by
+// 2^9 hence each accumulated product adds at most 2^24 to a limb and
+// reduce_weak() multiplies its input twice by 2^13 at most, 2^10
+// products keep everything under 2^61.
+static SOP_CHUNK: uint = 1024;
 
 // LD = 2^5 * d
-static LD: [u8, ..27] = [
+static LD: [i64, ..27] = [
   0xe0, 0x10, 0x2a, 0xdf, 0x43, 0xcb, 0x31, 0x9e,
   0xfc, 0x1c, 0x86, 0x53, 0xea, 0x98, 0x7f, 0x1c,
   0x92, 0xa9, 0xfb, 0xf3, 0x11, 0x66, 0x7d, 0xdb,
   0x66, 0x98, 0x02];
 
 
+/// Length class of an input unpacked by `ScalarElem::unpack_slice()`.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub enum LengthClass {
+    /// 52 bytes canonical encoding, not reduced.
+    Canonical416,
+    /// 64 bytes input weakly reduced, e.g. a SHA-512 output.
+    Wide512,
+    /// 84 bytes input reduced, e.g. a XOF output for hash-to-scalar.
+    Wide672,
+    /// 96 bytes input reduced.
+    Wide768,
+    /// 104 bytes input weakly reduced.
+    Wide832,
+    /// 114 bytes input reduced.
+    Wide912
+}
+
+impl LengthClass {
+    /// Return the length in bytes of the inputs of this class.
+    pub fn bytes(&self) -> uint {
+        match *self {
+            Canonical416 => 52,
+            Wide512 => 64,
+            Wide672 => 84,
+            Wide768 => 96,
+            Wide832 => 104,
+            Wide912 => 114
+        }
+    }
+}
+
+
 /// Scalar element used in scalar operations.
 ///
 /// Provide commons Curve41417 scalar operations computed `mod L`, where
-/// `L` is the order of the base point.
+/// `L` is the order of the base point. Limbs are held inline like those
+/// of `FieldElem` and zeroed-out when the element is dropped.
 #[deriving(Clone)]
 pub struct ScalarElem {
-    elem: SBuf<DefaultAllocator, i64>
+    elem: Limbs52
 }
 
 impl ScalarElem {
@@ -56,22 +96,20 @@ impl ScalarElem {
     /// Return scalar value representing `0`.
     pub fn zero() -> ScalarElem {
         ScalarElem {
-            elem: SBuf::new_zero(SCE_SIZE)
+            elem: Limbs52::new_zero()
         }
     }
 
-    // Return a reference to the limb at index `index`. Fails if
-    // `index` is out of bounds.
-    #[doc(hidden)]
-    pub fn get<'a>(&'a self, index: uint) -> &'a i64 {
-        self.elem.get(index)
+    // Return a reference to the limb at index `index`, in
+    // [0, SCE_SIZE - 1].
+    fn get<'a>(&'a self, index: uint) -> &'a i64 {
+        &self.elem.as_slice()[index]
     }
 
-    // Return a mutable reference to the limb at index `index`. Fails
-    // if `index` is out of bounds.
-    #[doc(hidden)]
-    pub fn get_mut<'a>(&'a mut self, index: uint) -> &'a mut i64 {
-        self.elem.get_mut(index)
+    // Return a mutable reference to the limb at index `index`, in
+    // [0, SCE_SIZE - 1].
+    fn get_mut<'a>(&'a mut self, index: uint) -> &'a mut i64 {
+        &mut self.elem.as_mut_slice()[index]
     }
 
     // Conditionally swap this scalar element with `other`. `cond` serves
@@ -85,50 +123,64 @@ impl ScalarElem {
 
     // Requirements: len >= 52
     fn carry(&mut self) {
-        let top = self.len() - 1;
-        let mut carry: i64;
-
-        for i in range(0u, top) {
-            *self.get_mut(i) += 1_i64 << 8;
-            carry = *self.get(i) >> 8;
-            *self.get_mut(i + 1) += carry - 1;
-            *self.get_mut(i) -= carry << 8;
-        }
-
-        *self.get_mut(top) += 1_i64 << 8;
-        carry = *self.get(top) >> 8;
-        for i in range(0u, 27) {
-            *self.get_mut(top - 51 + i) += (carry - 1) * (LD[i] as i64);
-        }
-        *self.get_mut(top) -= carry << 8;
+        let at = self.len() - 52;
+        limbs::carry(self.elem.as_mut_slice(), 8, LD, at);
     }
 
     // Reduce mod 2^416 - 2^5 * d and put limbs between [0, 2^16-1] through
     // carry.
     // Requirements: 52 < nlen <= 104
     fn reduce_weak(&mut self, n: &[i64]) {
+        let mut t = StackBuf::new_zero(78);
+        self.reduce_weak_with(n, t.as_mut_slice());
+    }
+
+    // Same as reduce_weak() but use t as temporary buffer, its previous
+    // content is overwritten.
+    //
+    // Limb 52 + k of n has weight 2^(8.(52 + k)) = 2^416.2^(8k) and 2^416 =
+    // LD mod 2^416 - LD = 2^5.L, it is folded by adding n[52 + k].LD to the
+    // 27 limbs t[k..k + 27]. The first fold leaves a value of 78 limbs at
+    // most whose limbs 52.. are folded a second time, they land under limb
+    // 52 as nlen <= 104.
+    //
+    // Bounds: if |n[i]| <= M, the first fold adds 27 products of at most
+    // 255.M to each limb hence |t[i]| <= 6886.M < 2^13.M, the second fold
+    // does the same with 6885.M instead of M and |t[i]| < 2^26.M for all
+    // i < 52. Any M < 2^37 keeps the limbs under 2^63.
+    //
+    // Requirements: 52 < nlen <= 104, tlen >= 78, |n[i]| < 2^37
+    fn reduce_weak_with(&mut self, n: &[i64], t: &mut [i64]) {
         assert!(n.len() > 52);
         assert!(n.len() <= 104);
+        assert!(t.len() >= 78);
+        debug_assert!(n.iter().all(|x| x.abs() < 1 << 37));
 
-        let mut t: SBuf<DefaultAllocator, i64> = SBuf::new_zero(78);
-        for i in range(0u, 52) {
-            *t.get_mut(i) = n[i];
+        let t = t.mut_slice_to(78);
+        let (lo, hi) = (n.slice_to(52), n.slice_from(52));
+        for (d, s) in t.mut_iter().zip(lo.iter()) {
+            *d = *s;
+        }
+        for d in t.mut_slice_from(52).mut_iter() {
+            *d = 0;
         }
 
-        for i in range(52u, n.len()) {
-            for j in range(0u, 27) {
-                *t.get_mut(i + j - 52) += n[i] * (LD[j] as i64);
-            }
+        // First fold: n[52 + k] into t[k..k + 27], k + 27 <= 78.
+        for (k, h) in hi.iter().enumerate() {
+            limbs::mul_add(t.mut_slice(k, k + 27), *h, LD);
         }
 
-        for i in range(52u, n.len() - 26) {
-            for j in range(0u, 27) {
-                *t.get_mut(i + j - 52) += *t.get(i) * (LD[j] as i64);
-            }
+        // Second fold: t[52 + k] into t[k..k + 27], only the limbs reached
+        // by the first fold (up to index hlen + 25) are non-zero.
+        let folds = if hi.len() > 26 { hi.len() - 26 } else { 0 };
+        for k in range(0u, folds) {
+            debug_assert!(k + 27 <= 52);
+            let h = t[52 + k];
+            limbs::mul_add(t.mut_slice(k, k + 27), h, LD);
         }
 
-        for i in range(0u, 52) {
-            *self.get_mut(i) = *t.get(i);
+        for (d, s) in self.elem.mut_iter().zip(t.slice_to(52).iter()) {
+            *d = *s;
         }
 
         self.carry();
@@ -170,7 +222,7 @@ impl ScalarElem {
 
     fn unpack_w_reduce<T: Bytes>(n: &T) -> ScalarElem {
         let l = n.as_bytes().len();
-        let mut t: SBuf<DefaultAllocator, i64> = SBuf::new_zero(l);
+        let mut t = StackBuf::new_zero(l);
 
         for i in range(0u, l) {
             *t.get_mut(i) = *n.get(i) as i64;
@@ -188,20 +240,196 @@ impl ScalarElem {
     /// * For larger values of `n` i.e. for `B512` and `B832` instances, `n`
     ///   is weakly reduced on input. `B832` might provide a better uniformity
     ///   of distribution on reductions `mod L`.
+    /// * Containers larger than 104 bytes (`B912`) are reduced with
+    ///   `from_bytes_mod_order()`.
     ///
     /// In any case it is not until its result is packed back to a byte
     /// representation (through `pack()` method) that it will be reduced to
-    /// its canonical form.
+    /// its canonical form. See `unpack_slice()` for unpacking byte slices
+    /// with errors telling why an input was rejected.
     pub fn unpack<T: Bytes>(n: &T) -> Option<ScalarElem> {
         let l = n.as_bytes().len();
 
         match l {
             52 => Some(ScalarElem::unpack_wo_reduce(n)),
             52..104 => Some(ScalarElem::unpack_w_reduce(n)),
+            _ if l > 104 => {
+                ScalarElem::from_bytes_mod_order(n.as_bytes()).ok()
+            },
             _ => None
         }
     }
 
+    /// Unpack the little-endian integer `bytes` and return it along with
+    /// the length class it was unpacked as. Unlike `unpack()` only the
+    /// lengths of the `Bytes` containers (52, 64, 84, 96, 104 and 114) are
+    /// accepted, any other length is rejected with
+    /// `Err(UnsupportedLength([52, 64, 84, 96, 104, 114], len))`. 52 bytes
+    /// inputs must be canonical else `Err(NonCanonical)` is returned,
+    /// larger inputs are reduced `mod L`.
+    pub fn unpack_slice(bytes: &[u8])
+                        -> result::Result<(ScalarElem, LengthClass), Error> {
+        let class = match bytes.len() {
+            52 => {
+                let s = try!(ScalarElem::decode(bytes));
+                return Ok((s, Canonical416));
+            },
+            64 => Wide512,
+            84 => Wide672,
+            96 => Wide768,
+            104 => Wide832,
+            114 => Wide912,
+            l => return Err(UnsupportedLength(UNPACK_LENGTHS, l))
+        };
+        let s = try!(ScalarElem::from_bytes_mod_order(bytes));
+        Ok((s, class))
+    }
+
+    /// Return a true choice iff `n` represents a canonical scalar value in
+    /// `[0, L-1]`. Always executes the same operations.
+    pub fn is_canonical(n: &B416) -> Choice {
+        // Compute n - L and keep the final borrow, n < L iff the result is
+        // negative.
+        let mut borrow: i64 = 0;
+        for i in range(0u, 52) {
+            borrow = (*n.get(i) as i64 - L[i] as i64 + borrow) >> 8;
+        }
+        Choice::from_u8((borrow & 1) as u8)
+    }
+
+    /// Constant-time equality comparison, `==` is the same test returning
+    /// a `bool`.
+    pub fn ct_eq(&self, other: &ScalarElem) -> Choice {
+        self.pack().get_ref().ct_eq(other.pack().get_ref())
+    }
+
+    /// Return a true choice iff this scalar is `0 mod L`. Always executes
+    /// the same operations.
+    pub fn is_zero(&self) -> Choice {
+        self.ct_eq(&ScalarElem::zero())
+    }
+
+    /// Decode a scalar value from its 52 bytes little-endian encoding.
+    /// Return `Err(InvalidLength)` if `bytes` doesn't have the expected
+    /// length and `Err(NonCanonical)` if its value is not in `[0, L-1]`.
+    pub fn decode(bytes: &[u8]) -> result::Result<ScalarElem, Error> {
+        try!(error::check_length(bytes, SCE_SIZE));
+        let b: B416 = Bytes::from_bytes(bytes).unwrap();
+        match ScalarElem::is_canonical(&b).unwrap_u8() {
+            1 => Ok(ScalarElem::unpack(&b).unwrap()),
+            _ => Err(NonCanonical)
+        }
+    }
+
+    /// Return the canonical encoding of this scalar value reduced `mod L`
+    /// in little-endian order, same as `pack()`.
+    pub fn to_bytes_le(&self) -> B416 {
+        self.pack().unwrap()
+    }
+
+    /// Return the canonical encoding of this scalar value reduced `mod L`
+    /// in big-endian order.
+    pub fn to_bytes_be(&self) -> B416 {
+        self.pack().to_bytes_be()
+    }
+
+    /// Decode a scalar value from its 52 bytes little-endian encoding, same
+    /// as `decode()`.
+    pub fn from_bytes_le(bytes: &[u8]) -> result::Result<ScalarElem, Error> {
+        ScalarElem::decode(bytes)
+    }
+
+    /// Decode a scalar value from its 52 bytes big-endian encoding, errors
+    /// are the same as those of `decode()`.
+    pub fn from_bytes_be(bytes: &[u8]) -> result::Result<ScalarElem, Error> {
+        try!(error::check_length(bytes, SCE_SIZE));
+        let s = Scalar::from_bytes_be(bytes).unwrap();
+        ScalarElem::decode(s.get_ref().as_bytes())
+    }
+
+    /// Return the scalar value of the integer whose little-endian 64 bits
+    /// limbs are `limbs`, e.g. exported from a bignum library. Return
+    /// `Err(InvalidLength)` unless there are 7 limbs and `Err(NonCanonical)`
+    /// if the value is not in `[0, L-1]`.
+    pub fn from_u64_limbs(limbs: &[u64]) -> result::Result<ScalarElem, Error> {
+        if limbs.len() != 7 {
+            return Err(error::InvalidLength(7, limbs.len()));
+        }
+        match utils::b416_from_u64_limbs(limbs) {
+            Some(b) => ScalarElem::decode(b.as_bytes()),
+            None => Err(NonCanonical)
+        }
+    }
+
+    /// Return the 7 little-endian 64 bits limbs of the canonical value of
+    /// this scalar, see `from_u64_limbs()`.
+    pub fn to_u64_limbs(&self) -> [u64, ..7] {
+        utils::b416_to_u64_limbs(self.pack().get_ref())
+    }
+
+    /// Return the decimal representation of the canonical value of this
+    /// scalar, e.g. for comparisons with integers printed by Sage. This is
+    /// slow and not constant-time, don't call it on secret scalars.
+    pub fn to_dec_string(&self) -> String {
+        let mut n = self.pack().unwrap();
+        let mut digits: Vec<u8> = Vec::new();
+        loop {
+            // Divide n by 10 starting from its most significant byte.
+            let mut r = 0u32;
+            for b in n.as_mut_bytes().mut_iter().rev() {
+                let d = (r << 8) | *b as u32;
+                *b = (d / 10) as u8;
+                r = d % 10;
+            }
+            digits.push('0' as u8 + r as u8);
+            if n.as_bytes().iter().all(|b| *b == 0) {
+                break;
+            }
+        }
+        digits.reverse();
+        String::from_utf8(digits).unwrap()
+    }
+
+    /// Parse the decimal representation of a scalar value, see
+    /// `to_dec_string()`. Return `Err(NonCanonical)` unless `s` is a
+    /// non-empty string of digits without leading zeros whose value is in
+    /// `[0, L-1]`. This is slow and not constant-time.
+    pub fn from_dec_str(s: &str) -> result::Result<ScalarElem, Error> {
+        let digits = s.as_bytes();
+        if digits.len() == 0 || (digits.len() > 1 && digits[0] == '0' as u8) {
+            return Err(NonCanonical);
+        }
+
+        let mut b: B416 = Bytes::new_zero();
+        for c in digits.iter() {
+            if *c < '0' as u8 || *c > '9' as u8 {
+                return Err(NonCanonical);
+            }
+            // b = 10 * b + digit
+            let mut carry = (*c - '0' as u8) as u32;
+            for x in b.as_mut_bytes().mut_iter() {
+                let d = *x as u32 * 10 + carry;
+                *x = d as u8;
+                carry = d >> 8;
+            }
+            if carry != 0 {
+                return Err(NonCanonical);
+            }
+        }
+        ScalarElem::decode(b.as_bytes())
+    }
+
+    /// Return `L`, the order of the base point, as little-endian bytes.
+    pub fn order() -> B416 {
+        Bytes::from_bytes(L).unwrap()
+    }
+
+    /// Return the scalar `L - 1`, see `constants::L_MINUS_ONE`.
+    pub fn l_minus_one() -> ScalarElem {
+        let b: B416 = Bytes::from_bytes(constants::L_MINUS_ONE).unwrap();
+        ScalarElem::unpack(&b).unwrap()
+    }
+
     /// Pack the current scalar value reduced `mod L`.
     pub fn pack(&self) -> Scalar {
         let mut t = self.clone();
@@ -214,10 +442,381 @@ impl ScalarElem {
         Scalar(b)
     }
 
+    /// Return `a1 * b1 + a2 * b2 + ... + an * bn` where `(ai, bi)` are
+    /// the elements of `pairs`. Products are accumulated in a double-width
+    /// buffer which is reduced only once (or once every 1024 pairs for
+    /// very large inputs), this is faster than computing each product and
+    /// sum separately. See `UnreducedScalar`.
+    pub fn sum_of_products(pairs: &[(ScalarElem, ScalarElem)]) -> ScalarElem {
+        let mut acc = UnreducedScalar::new();
+        for &(ref a, ref b) in pairs.iter() {
+            acc.add_product(a, b);
+        }
+        acc.reduce()
+    }
+
+    /// Compute `self * other` into `out` using `scratch` for temporaries.
+    /// Unlike `self * other` this doesn't allocate any memory.
+    pub fn mul_with_scratch(&self, other: &ScalarElem, out: &mut ScalarElem,
+                            scratch: &mut Scratch) {
+        let w = scratch.wide.as_mut_slice();
+        for x in w.mut_iter() {
+            *x = 0;
+        }
+
+        for i in range(0u, 52) {
+            for j in range(0u, 52) {
+                w[i + j] += *self.get(i) * *other.get(j);
+            }
+        }
+
+        out.reduce_weak_with(&*w, scratch.tmp.as_mut_slice());
+    }
+
     /// Pack scalar value `n` reduced `n mod L`.
     pub fn reduce_from_bytes<T: Bytes + Uniformity>(n: &T) -> Scalar {
         ScalarElem::unpack(n).unwrap().pack()
     }
+
+    /// Return the scalar value `n mod L` where `n` is the little-endian
+    /// integer `bytes` of any length of at least 64 bytes, typically the
+    /// output of a hash function. As `L ~ 2^411` the statistical distance
+    /// of the result from the uniform distribution is at most
+    /// `2^(411 - 8.len)` for uniform inputs: `2^-101` for 64 bytes, `2^-421`
+    /// for 104 bytes and `2^-501` for 114 bytes. Shorter inputs are
+    /// rejected with `Err(InvalidLength(64, len))` as they would be too
+    /// biased.
+    pub fn from_bytes_mod_order(bytes: &[u8])
+                                -> result::Result<ScalarElem, Error> {
+        if bytes.len() < 64 {
+            return Err(error::InvalidLength(64, bytes.len()));
+        }
+
+        // 2^416 mod L.
+        let mut t = StackBuf::new_zero(53);
+        *t.get_mut(52) = 1;
+        let mut shift = ScalarElem::new_zero();
+        shift.reduce_weak(t.as_slice());
+
+        // Horner evaluation over 52 bytes chunks from the most significant
+        // one.
+        let mut r = ScalarElem::new_zero();
+        let nchunks = (bytes.len() + 51) / 52;
+        for k in range(0u, nchunks).rev() {
+            let mut c = ScalarElem::new_zero();
+            for i in range(0u, 52) {
+                if 52 * k + i < bytes.len() {
+                    *c.get_mut(i) = bytes[52 * k + i] as i64;
+                }
+            }
+            r = r * shift + c;
+        }
+        Ok(r)
+    }
+
+    /// Pack scalar value `bytes mod L`, see `from_bytes_mod_order()`.
+    pub fn reduce_from_slice(bytes: &[u8]) -> result::Result<Scalar, Error> {
+        let r = try!(ScalarElem::from_bytes_mod_order(bytes));
+        Ok(r.pack())
+    }
+
+    /// Return `2 * self`, same as `self + self`.
+    pub fn dbl(&self) -> ScalarElem {
+        let mut r = self.clone();
+        for i in range(0u, r.len()) {
+            *r.get_mut(i) <<= 1;
+        }
+        r
+    }
+
+    /// Return `n * self` `mod L`. Cheaper than a full multiplication as
+    /// each limb is only multiplied by `n` before being carried.
+    pub fn mul_small(&self, n: u32) -> ScalarElem {
+        let mut r = self.clone();
+        for i in range(0u, r.len()) {
+            *r.get_mut(i) *= n as i64;
+        }
+        r.carry();
+        r.carry();
+        r
+    }
+
+    /// Return `2^k * self` `mod L`.
+    pub fn shl(&self, k: uint) -> ScalarElem {
+        let mut r = self.clone();
+        let mut k = k;
+        while k > 0 {
+            let s = if k > 24 { 24 } else { k };
+            r = r.mul_small(1 << s);
+            k -= s;
+        }
+        r
+    }
+
+    /// Negate this scalar in place.
+    pub fn neg_assign(&mut self) {
+        for i in range(0u, self.len()) {
+            *self.get_mut(i) = -*self.get(i);
+        }
+    }
+
+    /// Negate this scalar in place iff `choice` is true, leave it unchanged
+    /// otherwise. Executes the same operations in both cases.
+    pub fn conditional_negate(&mut self, choice: Choice) {
+        let mask = -(choice.unwrap_u8() as i64);
+        for i in range(0u, self.len()) {
+            let v = *self.get(i);
+            *self.get_mut(i) = v ^ ((v ^ -v) & mask);
+        }
+    }
+
+    /// Return the multiplicative inverse of this scalar `mod L`, computed
+    /// as `self^(L-2)`. The inverse of `0` is `0`.
+    pub fn inv(&self) -> ScalarElem {
+        // L - 2 only differs from L on its first byte.
+        let mut e = L;
+        e[0] -= 2;
+
+        let mut r = self.clone();
+        for i in range(0u, 410).rev() {
+            r = r * r;
+            if (e[i >> 3] >> (i & 7)) & 1 == 1 {
+                r = r * *self;
+            }
+        }
+        r
+    }
+
+    /// Return `self^e mod L` by square-and-multiply. Constant time: the
+    /// bits of `e` are processed with the same operations, both `self` and
+    /// `e` may be secret. `e` is the canonical value of the scalar, in
+    /// `[0, L-1]`, and `self^0` is `1`.
+    pub fn pow(&self, e: &ScalarElem) -> ScalarElem {
+        let n = e.pack();
+        let eb = n.get_ref().as_bytes();
+        let mut r: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+
+        for i in range(0u, 411).rev() {
+            audit::access("sc::pow", eb.slice(i >> 3, (i >> 3) + 1));
+            r = r * r;
+            let mut t = r * *self;
+            let bit = ((eb[i >> 3] >> (i & 7)) & 1) as i64;
+            r.cswap(bit, &mut t);
+        }
+        r
+    }
+
+    /// Return `self^e` where `e` is a little-endian exponent. Variable time:
+    /// leading zero bits of `e` are skipped and a multiplication is only
+    /// executed for the set bits of `e`, `e` must not be secret. The
+    /// executed operations only depend on `e`, `self` may be secret.
+    /// `self^0` is `1`.
+    pub fn pow_vartime(&self, e: &[u8]) -> ScalarElem {
+        let mut r: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        let mut started = false;
+        for i in range(0u, e.len() * 8).rev() {
+            if started {
+                r = r * r;
+            }
+            if (e[i >> 3] >> (i & 7)) & 1 == 1 {
+                r = if started { r * *self } else { self.clone() };
+                started = true;
+            }
+        }
+        r
+    }
+
+    /// Return the multiplicative inverse of this scalar `mod L` like
+    /// `inv()` but computed in variable time with the binary extended
+    /// Euclidean algorithm, much faster. Only use it on public values
+    /// (e.g. in signature verification). The inverse of `0` is `0`.
+    pub fn invert_vartime(&self) -> ScalarElem {
+        let l = utils::b416_to_u64_limbs(&ScalarElem::order());
+        let mut u = self.to_u64_limbs();
+        if u == [0u64, ..7] {
+            return ScalarElem::zero();
+        }
+        let mut v = l;
+        let mut one = [0u64, ..7];
+        one[0] = 1;
+        let mut x1 = one;
+        let mut x2 = [0u64, ..7];
+
+        // Invariants: u = x1.self and v = x2.self mod L, x1, x2 < L.
+        while u != one && v != one {
+            while u[0] & 1 == 0 {
+                limbs_shr1(&mut u);
+                limbs_half_mod(&mut x1, &l);
+            }
+            while v[0] & 1 == 0 {
+                limbs_shr1(&mut v);
+                limbs_half_mod(&mut x2, &l);
+            }
+            if limbs_ge(&u, &v) {
+                limbs_sub(&mut u, &v);
+                limbs_sub_mod(&mut x1, &x2, &l);
+            } else {
+                limbs_sub(&mut v, &u);
+                limbs_sub_mod(&mut x2, &x1, &l);
+            }
+        }
+
+        let r = if u == one { x1 } else { x2 };
+        ScalarElem::from_u64_limbs(r.as_slice()).unwrap()
+    }
+
+    /// Return the Legendre symbol of this scalar `mod L`: `0` if it is `0`,
+    /// `1` if it is a non-zero square and `-1` otherwise. Computed as
+    /// `self^((L-1)/2)` in a fixed sequence of operations.
+    pub fn legendre(&self) -> i8 {
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        let chi = self.pow_vartime(shr_bytes(L, 1));
+        let is_one = (chi == one) as i8;
+        let is_zero = (chi == ScalarElem::zero()) as i8;
+        2 * is_one + is_zero - 1
+    }
+
+    /// Return a true choice iff this scalar is a square `mod L` (`0`
+    /// included), see `legendre()`.
+    pub fn is_square(&self) -> Choice {
+        Choice::from_u8(((self.legendre() as u8) >> 7) ^ 1)
+    }
+
+    /// Return a square root of this scalar `mod L`, or `None` if it is not
+    /// a square. Either of the two roots `r` and `-r` may be returned.
+    /// Computed with Tonelli-Shanks (`L - 1 = 2^3.q` with `q` odd) in a
+    /// fixed sequence of operations, the result is checked by squaring it.
+    pub fn sqrt(&self) -> Option<ScalarElem> {
+        // Exponents q = (L - 1) / 8 = L >> 3 and (q + 1) / 2 = (L + 7) >> 4.
+        let mut lp7 = L;
+        lp7[0] += 7;
+        let q = shr_bytes(L, 3);
+        let q1 = shr_bytes(lp7, 4);
+
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        let thirteen: ScalarElem = FromPrimitive::from_u64(13).unwrap();
+        // 13 is the smallest non-square mod L, 13^q has order 8.
+        let mut c = thirteen.pow_vartime(q);
+        let mut r = self.pow_vartime(q1);
+        let mut t = self.pow_vartime(q);
+
+        // Invariant: r^2 = self.t, t has order dividing 2^(k-1).
+        for k in range(2u, 4).rev() {
+            let mut w = t.clone();
+            for _ in range(0u, k - 2) {
+                w = w * w;
+            }
+            let flag = (w != one) as i64;
+            let mut rc = r * c;
+            r.cswap(flag, &mut rc);
+            c = c * c;
+            let mut tc = t * c;
+            t.cswap(flag, &mut tc);
+        }
+
+        match r * r == *self {
+            true => Some(r),
+            false => None
+        }
+    }
+
+    /// Return the inverses of all the elements of `elems` `mod L` using
+    /// a single inversion (Montgomery's trick). If any of the elements is
+    /// `0` then the returned values are all set to `0`.
+    pub fn batch_inv(elems: &[ScalarElem]) -> Vec<ScalarElem> {
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+
+        // prods[i] = elems[0] * ... * elems[i - 1]
+        let mut prods = Vec::with_capacity(elems.len());
+        let mut acc = one.clone();
+        for e in elems.iter() {
+            prods.push(acc.clone());
+            acc = acc * *e;
+        }
+
+        let mut inv = acc.inv();
+        let mut r = Vec::from_fn(elems.len(), |_| ScalarElem::zero());
+        for i in range(0u, elems.len()).rev() {
+            *r.get_mut(i) = inv * *prods.get(i);
+            inv = inv * elems[i];
+        }
+        r
+    }
+}
+
+// Variable-time helpers of invert_vartime() over little-endian 64 bits
+// limbs.
+
+// Return a >= b.
+fn limbs_ge(a: &[u64, ..7], b: &[u64, ..7]) -> bool {
+    for i in range(0u, 7).rev() {
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+    true
+}
+
+// a = a + b, return the carry.
+fn limbs_add(a: &mut [u64, ..7], b: &[u64, ..7]) -> u64 {
+    let mut carry = 0u64;
+    for i in range(0u, 7) {
+        let s = a[i] + b[i];
+        let c1 = (s < a[i]) as u64;
+        a[i] = s + carry;
+        carry = c1 | ((a[i] < s) as u64);
+    }
+    carry
+}
+
+// a = a - b, a >= b.
+fn limbs_sub(a: &mut [u64, ..7], b: &[u64, ..7]) {
+    let mut borrow = 0u64;
+    for i in range(0u, 7) {
+        let d = a[i] - b[i];
+        let b1 = (a[i] < b[i]) as u64;
+        a[i] = d - borrow;
+        borrow = b1 | ((d < borrow) as u64);
+    }
+}
+
+// a = a >> 1 with top as the bit shifted in at the top.
+fn limbs_shr1_with(a: &mut [u64, ..7], top: u64) {
+    for i in range(0u, 7) {
+        let hi = if i + 1 < 7 { a[i + 1] } else { top };
+        a[i] = (a[i] >> 1) | (hi << 63);
+    }
+}
+
+fn limbs_shr1(a: &mut [u64, ..7]) {
+    limbs_shr1_with(a, 0);
+}
+
+// a = a / 2 mod l, a < l and l odd.
+fn limbs_half_mod(a: &mut [u64, ..7], l: &[u64, ..7]) {
+    let carry = match a[0] & 1 {
+        0 => 0,
+        _ => limbs_add(a, l)
+    };
+    limbs_shr1_with(a, carry);
+}
+
+// a = a - b mod l, a, b < l.
+fn limbs_sub_mod(a: &mut [u64, ..7], b: &[u64, ..7], l: &[u64, ..7]) {
+    if !limbs_ge(a, b) {
+        limbs_add(a, l);
+    }
+    limbs_sub(a, b);
+}
+
+// Return the little-endian bytes b shifted right by k bits, 0 < k < 8.
+fn shr_bytes(b: [u8, ..52], k: uint) -> [u8, ..52] {
+    let mut r = [0u8, ..52];
+    for i in range(0u, 52) {
+        let hi = if i + 1 < 52 { b[i + 1] } else { 0 };
+        r[i] = (b[i] >> k) | (hi << (8 - k));
+    }
+    r
 }
 
 impl Add<ScalarElem, ScalarElem> for ScalarElem {
@@ -245,18 +844,114 @@ impl Sub<ScalarElem, ScalarElem> for ScalarElem {
 impl Neg<ScalarElem> for ScalarElem {
     /// Negate scalar.
     fn neg(&self) -> ScalarElem {
-        ScalarElem::zero() - *self
+        let mut r = self.clone();
+        r.neg_assign();
+        r
+    }
+}
+
+/// Reusable buffers for the temporaries of scalar operations.
+///
+/// Methods taking a `Scratch` argument (e.g. `mul_with_scratch()`) don't
+/// allocate memory, allocate one instance and reuse it across calls in
+/// tight loops. Its buffers live on the stack (see `sbuf::StackBuf`) and
+/// are wiped when it is dropped.
+pub struct Scratch {
+    wide: StackBuf,
+    tmp: StackBuf
+}
+
+impl Scratch {
+    /// Return a new scratch space.
+    pub fn new() -> Scratch {
+        Scratch {
+            wide: StackBuf::new_zero(103),
+            tmp: StackBuf::new_zero(78)
+        }
     }
 }
 
-impl Mul<ScalarElem, ScalarElem> for ScalarElem {
-    /// Multiply scalars.
-    fn mul(&self, other: &ScalarElem) -> ScalarElem {
-        let mut t: SBuf<DefaultAllocator, i64> = SBuf::new_zero(103);
+/// Double-width scalar accumulator
+///
+/// Accumulates sums of products of scalars in 104 limbs without reducing
+/// them, the sum is only reduced `mod L` by `reduce()`. This is the
+/// delayed reduction used by `ScalarElem::sum_of_products()`, for custom
+/// formulas combining many products. To keep the limbs from overflowing
+/// the accumulator is reduced internally every 1024 products. Its buffer
+/// is wiped when it is dropped.
+pub struct UnreducedScalar {
+    wide: StackBuf,
+    // Reduced value of the previous chunks.
+    acc: ScalarElem,
+    // Number of terms accumulated in wide.
+    terms: uint
+}
+
+impl UnreducedScalar {
+    /// Return a new accumulator of value `0`.
+    pub fn new() -> UnreducedScalar {
+        UnreducedScalar {
+            wide: StackBuf::new_zero(104),
+            acc: ScalarElem::new_zero(),
+            terms: 0
+        }
+    }
+
+    // Reduce wide into acc if it holds SOP_CHUNK terms.
+    fn reserve(&mut self) {
+        if self.terms < SOP_CHUNK {
+            return;
+        }
+        let mut c = ScalarElem::new_zero();
+        c.reduce_weak(self.wide.as_slice());
+        self.acc = self.acc + c;
+        for x in self.wide.mut_iter() {
+            *x = 0;
+        }
+        self.terms = 0;
+    }
 
+    /// Add the product `a * b` without reducing it.
+    pub fn add_product(&mut self, a: &ScalarElem, b: &ScalarElem) {
+        self.reserve();
         for i in range(0u, 52) {
             for j in range(0u, 52) {
-                *t.get_mut(i + j) += *self.get(i) * *other.get(j);
+                *self.wide.get_mut(i + j) += *a.get(i) * *b.get(j);
+            }
+        }
+        self.terms += 1;
+    }
+
+    /// Add the scalar `a` without reducing it.
+    pub fn add(&mut self, a: &ScalarElem) {
+        self.reserve();
+        for i in range(0u, 52) {
+            *self.wide.get_mut(i) += *a.get(i);
+        }
+        self.terms += 1;
+    }
+
+    /// Return the accumulated sum reduced `mod L`. The accumulator is left
+    /// unchanged and may keep accumulating terms.
+    pub fn reduce(&self) -> ScalarElem {
+        let mut c = ScalarElem::new_zero();
+        c.reduce_weak(self.wide.as_slice());
+        self.acc + c
+    }
+}
+
+#[doc(hidden)]
+pub trait ScalarElemMul<R> {
+    fn mul_scalar_elem(&self, lhs: &ScalarElem) -> R;
+}
+
+impl ScalarElemMul<ScalarElem> for ScalarElem {
+    fn mul_scalar_elem(&self, lhs: &ScalarElem) -> ScalarElem {
+        let mut t = StackBuf::new_zero(103);
+
+        for i in range(0u, 52) {
+            for j in range(0u, 52) {
+                *t.get_mut(i + j) += *lhs.get(i) * *self.get(j);
             }
         }
 
@@ -266,6 +961,13 @@ impl Mul<ScalarElem, ScalarElem> for ScalarElem {
     }
 }
 
+impl<S, R: ScalarElemMul<S>> Mul<R, S> for ScalarElem {
+    /// Multiply scalars, or multiply scalar with point.
+    fn mul(&self, other: &R) -> S {
+        other.mul_scalar_elem(self)
+    }
+}
+
 impl FromPrimitive for ScalarElem {
     #[allow(unused_variable)]
     fn from_i64(n: i64) -> Option<ScalarElem> {
@@ -313,6 +1015,12 @@ impl ToHex for ScalarElem {
     }
 }
 
+impl ConditionallySelectable for ScalarElem {
+    fn conditional_swap(&mut self, other: &mut ScalarElem, choice: Choice) {
+        self.cswap(choice.unwrap_u8() as i64, other);
+    }
+}
+
 impl Eq for ScalarElem {
 }
 
@@ -332,8 +1040,12 @@ impl Collection for ScalarElem {
 
 #[cfg(test)]
 mod tests {
-    use bytes::{B416, B512, B832, Bytes};
-    use sc::ScalarElem;
+    use bytes::{B416, B512, B672, B768, B832, B912, Bytes, Scalar};
+    use choice::Choice;
+    use error::{InvalidLength, NonCanonical, UnsupportedLength};
+    use sc::{Canonical416, ScalarElem, Scratch, UnreducedScalar, Wide512};
+    use sc::{Wide672, Wide768};
+    use sc::{Wide832, Wide912};
 
 
     #[test]
@@ -570,6 +1282,361 @@ mod tests {
         assert!(s.pack().unwrap() == rr);
     }
 
+    #[test]
+    fn test_sum_of_products() {
+        let mut pairs = Vec::new();
+        let mut s1 = ScalarElem::zero();
+
+        for _ in range(0u, 8) {
+            let a = ScalarElem::new_rand();
+            let b = ScalarElem::new_rand();
+            s1 = s1 + a * b;
+            pairs.push((a, b));
+        }
+
+        let s2 = ScalarElem::sum_of_products(pairs.as_slice());
+        assert!(s1 == s2);
+        assert!(ScalarElem::sum_of_products([]) == ScalarElem::zero());
+    }
+
+    #[test]
+    fn test_mul_with_scratch() {
+        let mut scratch = Scratch::new();
+        let mut r = ScalarElem::new_rand();
+
+        for _ in range(0u, 4) {
+            let a = ScalarElem::new_rand();
+            let b = ScalarElem::new_rand();
+            a.mul_with_scratch(&b, &mut r, &mut scratch);
+            assert!(r == a * b);
+        }
+    }
+
+    #[test]
+    fn test_small_mul() {
+        let a = ScalarElem::new_rand();
+        let n2: ScalarElem = FromPrimitive::from_u64(2).unwrap();
+        let n8: ScalarElem = FromPrimitive::from_u64(8).unwrap();
+        let n: ScalarElem = FromPrimitive::from_u64(0xdeadbeef).unwrap();
+
+        assert!(a.dbl() == a * n2);
+        assert!(a.mul_small(8) == a * n8);
+        assert!(a.mul_small(0xdeadbeef) == a * n);
+        assert!(a.shl(3) == a * n8);
+
+        let mut b = a.clone();
+        for _ in range(0u, 100) {
+            b = b.mul_small(2);
+        }
+        assert!(a.shl(100) == b);
+    }
+
+    #[test]
+    fn test_neg() {
+        let a = ScalarElem::new_rand();
+        let mut b = a.clone();
+
+        b.neg_assign();
+        assert!(b == ScalarElem::zero() - a);
+        assert!(b == -a);
+        assert!(a + b == ScalarElem::zero());
+
+        b.conditional_negate(Choice::from_u8(0));
+        assert!(b == -a);
+        b.conditional_negate(Choice::from_u8(1));
+        assert!(b == a);
+    }
+
+    #[test]
+    fn test_is_canonical() {
+        let mut l: B416 = Bytes::from_bytes(super::L).unwrap();
+        assert!(ScalarElem::is_canonical(&l).unwrap_u8() == 0);
+        *l.get_mut(0) -= 1;
+        assert!(ScalarElem::is_canonical(&l).unwrap_u8() == 1);
+        assert!(ScalarElem::is_canonical(ScalarElem::new_rand().pack()
+                                         .get_ref()).unwrap_u8() == 1);
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        let a = ScalarElem::new_rand();
+        let b = a + ScalarElem::zero();
+        assert!(a.ct_eq(&b).unwrap_u8() == 1);
+        assert!(a.ct_eq(&(a + a)).unwrap_u8() == 0);
+        assert!((a - b).is_zero().unwrap_u8() == 1);
+        assert!((a.is_zero() | b.is_zero()).unwrap_u8() == 0);
+    }
+
+    #[test]
+    fn test_decode() {
+        let a = ScalarElem::new_rand();
+        let b = a.pack();
+        assert!(ScalarElem::decode(b.get_ref().as_bytes()).unwrap() == a);
+
+        let l = ScalarElem::order();
+        assert!(ScalarElem::decode(l.as_bytes()) == Err(NonCanonical));
+        assert!(ScalarElem::decode(l.as_bytes().slice_to(51)) ==
+                Err(InvalidLength(52, 51)));
+    }
+
+    #[test]
+    fn test_endianness() {
+        let a = ScalarElem::new_rand();
+        let le = a.to_bytes_le();
+        let be = a.to_bytes_be();
+        assert!(le == a.pack().unwrap());
+        for i in range(0u, 52) {
+            assert!(*be.get(i) == *le.get(51 - i));
+        }
+        assert!(ScalarElem::from_bytes_le(le.as_bytes()).unwrap() == a);
+        assert!(ScalarElem::from_bytes_be(be.as_bytes()).unwrap() == a);
+
+        let l = Scalar(ScalarElem::order());
+        assert!(ScalarElem::from_bytes_be(l.to_bytes_be().as_bytes()) ==
+                Err(NonCanonical));
+        assert!(ScalarElem::from_bytes_be(be.as_bytes().slice_to(51)) ==
+                Err(InvalidLength(52, 51)));
+    }
+
+    #[test]
+    fn test_u64_limbs() {
+        let a = ScalarElem::new_rand();
+        let limbs = a.to_u64_limbs();
+        assert!(ScalarElem::from_u64_limbs(limbs).unwrap() == a);
+        assert!(limbs[6] >> 32 == 0);
+
+        let one = [1u64, 0, 0, 0, 0, 0, 0];
+        let n1: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        assert!(ScalarElem::from_u64_limbs(one).unwrap() == n1);
+        let mut big = one;
+        big[6] = 1 << 32;
+        assert!(ScalarElem::from_u64_limbs(big) == Err(NonCanonical));
+        let l = ScalarElem::unpack(&ScalarElem::order()).unwrap();
+        assert!(ScalarElem::from_u64_limbs(l.to_u64_limbs()).unwrap() ==
+                ScalarElem::zero());
+        assert!(ScalarElem::from_u64_limbs([1u64]) ==
+                Err(InvalidLength(7, 1)));
+    }
+
+    #[test]
+    fn test_dec_string() {
+        let a = ScalarElem::new_rand();
+        assert!(ScalarElem::from_dec_str(a.to_dec_string().as_slice())
+                .unwrap() == a);
+        assert!(ScalarElem::zero().to_dec_string().as_slice() == "0");
+        let n: ScalarElem = FromPrimitive::from_u64(1234567890).unwrap();
+        assert!(n.to_dec_string().as_slice() == "1234567890");
+
+        let lm1 = "52884477503219887916153224642621683186272374637142497542\
+                   77190328831105466135348245791335989419337099796002495788\
+                   978276839288";
+        assert!(ScalarElem::l_minus_one().to_dec_string().as_slice() == lm1);
+        assert!(ScalarElem::from_dec_str(lm1).unwrap() ==
+                ScalarElem::l_minus_one());
+        let l = "5288447750321988791615322464262168318627237463714249754277\
+                 1903288311054661353482457913359894193370997960024957889782\
+                 76839289";
+        assert!(ScalarElem::from_dec_str(l) == Err(NonCanonical));
+
+        let long = String::from_char(130, '1');
+        for s in ["", "01", "12a", "-1", " 1", long.as_slice()].iter() {
+            assert!(ScalarElem::from_dec_str(*s) == Err(NonCanonical));
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_mod_order() {
+        let b512: B512 = Bytes::new_rand();
+        let b832: B832 = Bytes::new_rand();
+        assert!(ScalarElem::from_bytes_mod_order(b512.as_bytes()).unwrap() ==
+                ScalarElem::unpack(&b512).unwrap());
+        assert!(ScalarElem::reduce_from_slice(b832.as_bytes()).unwrap() ==
+                ScalarElem::reduce_from_bytes(&b832));
+
+        // 114 bytes: n = lo + 2^832.hi with lo on 104 bytes.
+        let mut b = Vec::from_slice(b832.as_bytes());
+        b.push_all([1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        let mut hi: B512 = Bytes::new_zero();
+        for i in range(0u, 10) {
+            *hi.get_mut(i) = (i + 1) as u8;
+        }
+        let mut one: B832 = Bytes::new_zero();
+        *one.get_mut(103) = 1;
+        // 2^832 = (2^824).2^8
+        let s824 = ScalarElem::unpack(&one).unwrap();
+        let mut two8: B416 = Bytes::new_zero();
+        *two8.get_mut(1) = 1;
+        let s832 = s824 * ScalarElem::unpack(&two8).unwrap();
+        let expected = ScalarElem::unpack(&b832).unwrap() +
+            s832 * ScalarElem::unpack(&hi).unwrap();
+        assert!(ScalarElem::from_bytes_mod_order(b.as_slice()).unwrap() ==
+                expected);
+
+        assert!(ScalarElem::from_bytes_mod_order(b512.as_bytes()
+                                                 .slice_to(63)).err() ==
+                Some(InvalidLength(64, 63)));
+    }
+
+    #[test]
+    fn test_unpack_slice() {
+        let a = ScalarElem::new_rand();
+        let (b, c) = ScalarElem::unpack_slice(a.pack().as_bytes()).unwrap();
+        assert!(b == a && c == Canonical416 && c.bytes() == 52);
+
+        let n: B512 = Bytes::new_rand();
+        let (s, c) = ScalarElem::unpack_slice(n.as_bytes()).unwrap();
+        assert!(s == ScalarElem::unpack(&n).unwrap() && c == Wide512);
+        let n: B832 = Bytes::new_rand();
+        let (s, c) = ScalarElem::unpack_slice(n.as_bytes()).unwrap();
+        assert!(s == ScalarElem::unpack(&n).unwrap() && c == Wide832);
+        let n: B672 = Bytes::new_rand();
+        let (s, c) = ScalarElem::unpack_slice(n.as_bytes()).unwrap();
+        assert!(s == ScalarElem::unpack(&n).unwrap() && c == Wide672);
+        let n: B768 = Bytes::new_rand();
+        let (s, c) = ScalarElem::unpack_slice(n.as_bytes()).unwrap();
+        assert!(s == ScalarElem::unpack(&n).unwrap() && c == Wide768);
+        let n: B912 = Bytes::new_rand();
+        let (s, c) = ScalarElem::unpack_slice(n.as_bytes()).unwrap();
+        assert!(s == ScalarElem::unpack(&n).unwrap() && c == Wide912);
+        assert!(c.bytes() == 114);
+
+        assert!(ScalarElem::unpack_slice(ScalarElem::order().as_bytes())
+                .err() == Some(NonCanonical));
+        for &l in [0u, 51, 53, 63, 65, 103, 105, 113, 115].iter() {
+            let b = Vec::from_elem(l, 0u8);
+            match ScalarElem::unpack_slice(b.as_slice()) {
+                Err(UnsupportedLength(accepted, len)) => {
+                    assert!(accepted == &[52u, 64, 84, 96, 104, 114] &&
+                            len == l);
+                },
+                _ => fail!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_inv() {
+        let a = ScalarElem::new_rand();
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+
+        assert!(a * a.inv() == one);
+        assert!(one.inv() == one);
+        assert!(ScalarElem::zero().inv() == ScalarElem::zero());
+    }
+
+    #[test]
+    fn test_batch_inv() {
+        let elems = Vec::from_fn(5, |_| ScalarElem::new_rand());
+        let invs = ScalarElem::batch_inv(elems.as_slice());
+
+        for (e, i) in elems.iter().zip(invs.iter()) {
+            assert!(*i == e.inv());
+        }
+    }
+
+    #[test]
+    fn test_sqrt() {
+        for _ in range(0u, 8) {
+            let a = ScalarElem::new_rand();
+            let r = (a * a).sqrt().unwrap();
+            assert!(r == a || r == -a);
+        }
+        assert!(ScalarElem::zero().sqrt().unwrap() == ScalarElem::zero());
+
+        // 13 is not a square, none of its multiples by squares are.
+        let n: ScalarElem = FromPrimitive::from_u64(13).unwrap();
+        let a = ScalarElem::new_rand();
+        assert!(n.sqrt().is_none());
+        assert!((n * a * a).sqrt().is_none());
+    }
+
+    #[test]
+    fn test_pow_vartime() {
+        let a = ScalarElem::new_rand();
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        assert!(a.pow_vartime([]) == one && a.pow_vartime([0, 0]) == one);
+        assert!(a.pow_vartime([1]) == a);
+        assert!(a.pow_vartime([13, 0]) == a * a * a * a * a * a * a * a *
+                a * a * a * a * a);
+
+        // a^(L-1) = 1 and a^(L-2) = a^-1.
+        let mut e = ScalarElem::order();
+        *e.get_mut(0) -= 1;
+        assert!(a.pow_vartime(e.as_bytes()) == one);
+        *e.get_mut(0) -= 1;
+        assert!(a.pow_vartime(e.as_bytes()) == a.inv());
+    }
+
+    #[test]
+    fn test_unreduced_scalar() {
+        let mut acc = UnreducedScalar::new();
+        assert!(acc.reduce() == ScalarElem::zero());
+
+        let a = ScalarElem::new_rand();
+        let b = ScalarElem::new_rand();
+        let c = ScalarElem::new_rand();
+        acc.add_product(&a, &b);
+        acc.add(&c);
+        assert!(acc.reduce() == a * b + c);
+        acc.add_product(&b, &c);
+        assert!(acc.reduce() == a * b + c + b * c);
+
+        // More terms than a chunk.
+        let mut acc = UnreducedScalar::new();
+        let mut expected = ScalarElem::zero();
+        for _ in range(0u, 1100) {
+            acc.add_product(&a, &b);
+            expected = expected + a * b;
+        }
+        assert!(acc.reduce() == expected);
+    }
+
+    #[test]
+    fn test_pow() {
+        let a = ScalarElem::new_rand();
+        let e = ScalarElem::new_rand();
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        let two: ScalarElem = FromPrimitive::from_u64(2).unwrap();
+        assert!(a.pow(&ScalarElem::zero()) == one);
+        assert!(a.pow(&one) == a && a.pow(&two) == a * a);
+        assert!(a.pow(&e) == a.pow_vartime(e.pack().get_ref().as_bytes()));
+        assert!(a.pow(&(e + one)) == a.pow(&e) * a);
+
+        // a^(L-1) = 1, L-1 is the largest exponent.
+        assert!(a.pow(&ScalarElem::l_minus_one()) == one);
+        assert!(ScalarElem::zero().pow(&e) == ScalarElem::zero());
+    }
+
+    #[test]
+    fn test_invert_vartime() {
+        for _ in range(0u, 16) {
+            let a = ScalarElem::new_rand();
+            assert!(a.invert_vartime() == a.inv());
+        }
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        let m1 = ScalarElem::zero() - one;
+        assert!(one.invert_vartime() == one);
+        assert!(m1.invert_vartime() == m1);
+        assert!(ScalarElem::zero().invert_vartime() == ScalarElem::zero());
+    }
+
+    #[test]
+    fn test_legendre() {
+        let a = ScalarElem::new_rand();
+        assert!((a * a).legendre() == 1);
+        assert!((a * a).is_square().unwrap_u8() == 1);
+        assert!(ScalarElem::zero().legendre() == 0);
+
+        // L = 1 mod 8, -1 is a square and 13 is the smallest non-square.
+        let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+        let m1 = ScalarElem::zero() - one;
+        let n: ScalarElem = FromPrimitive::from_u64(13).unwrap();
+        assert!(m1.is_square().unwrap_u8() == 1);
+        assert!(n.legendre() == -1);
+        assert!((n * a * a).is_square().unwrap_u8() == 0);
+        assert!((n.is_square().unwrap_u8() == 1) == n.sqrt().is_some());
+    }
+
     #[test]
     fn test_from_u64() {
         let n: u64 = 72623859790382856;
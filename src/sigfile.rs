@@ -0,0 +1,180 @@
+//! Detached signature files
+//!
+//! A small text envelope for detached signatures, in the spirit of
+//! minisign, so that command-line tools built on this crate can exchange
+//! signature files. A file has exactly two lines:
+//!
+//! ```text
+//! untrusted comment: <free text>
+//! <base64 of "C4" || key id (8 bytes) || signature (104 bytes)>
+//! ```
+//!
+//! The key id is a fingerprint of the signer's public key (see `key_id()`)
+//! letting verifiers pick the right key, the comment isn't signed. The
+//! signature is the one of `sign::sign()` over the contents of the signed
+//! file.
+use serialize::base64::{FromBase64, STANDARD, ToBase64};
+use std::result;
+
+use digest::Digest;
+use error::{Error, Malformed};
+use kdf;
+use keys::{KeyPair, PublicKey};
+use sign::Signature;
+
+
+static ALGORITHM: &'static [u8] = b"C4";
+static COMMENT_PREFIX: &'static str = "untrusted comment: ";
+static KEY_ID_LABEL: &'static [u8] = b"curve41417 signature file key id";
+
+
+/// Return the key id of `pk`: 8 bytes derived from its packed point with
+/// `kdf::derive()`.
+pub fn key_id<D: Digest>(digest: &mut D, pk: &PublicKey) -> [u8, ..8] {
+    let mut id = [0u8, ..8];
+    kdf::derive(digest, [], pk.as_point().get_ref().as_bytes(), KEY_ID_LABEL,
+                id.as_mut_slice());
+    id
+}
+
+/// Detached signature file.
+#[deriving(Clone, PartialEq, Eq)]
+pub struct SignatureFile {
+    /// Untrusted comment, a single line.
+    pub comment: String,
+    /// Key id of the signer's public key.
+    pub key_id: [u8, ..8],
+    /// Signature of the contents of the signed file.
+    pub signature: Signature
+}
+
+impl SignatureFile {
+    /// Sign `msg` with `kp` and return its signature file with the
+    /// untrusted comment `comment`. Return `Err(Malformed)` if `comment`
+    /// contains a line break and `Err(OffCurve)` if the public key of `kp`
+    /// is not on the curve.
+    pub fn sign<D: Digest>(digest: &mut D, kp: &KeyPair, msg: &[u8],
+                           comment: &str)
+                           -> result::Result<SignatureFile, Error> {
+        if comment.contains_char('\n') || comment.contains_char('\r') {
+            return Err(Malformed);
+        }
+        let sig = try!(kp.sign(digest, msg));
+        Ok(SignatureFile {
+            comment: comment.to_string(),
+            key_id: key_id(digest, kp.public()),
+            signature: sig
+        })
+    }
+
+    /// Return the two lines of this signature file, each one terminated by
+    /// a line feed.
+    pub fn encode(&self) -> String {
+        let mut body = Vec::from_slice(ALGORITHM);
+        body.push_all(self.key_id.as_slice());
+        body.push_all(self.signature.to_bytes().as_bytes());
+        format!("{}{}\n{}\n", COMMENT_PREFIX, self.comment,
+                body.as_slice().to_base64(STANDARD))
+    }
+
+    /// Parse a signature file returned by `encode()`, the final line feed
+    /// is optional. Return `Err(Malformed)` unless `s` has exactly the two
+    /// expected lines with a valid base64 body of the expected algorithm
+    /// and length, or the error of `Signature::from_bytes()`.
+    pub fn parse(s: &str) -> result::Result<SignatureFile, Error> {
+        let s = match s.ends_with("\n") {
+            true => s.slice_to(s.len() - 1),
+            false => s
+        };
+        let lines: Vec<&str> = s.split('\n').collect();
+        if lines.len() != 2 || !lines.get(0).starts_with(COMMENT_PREFIX) {
+            return Err(Malformed);
+        }
+        let body = match lines.get(1).from_base64() {
+            Ok(b) => b,
+            Err(_) => return Err(Malformed)
+        };
+        if body.len() != ALGORITHM.len() + 8 + 104 ||
+            body.slice_to(2) != ALGORITHM {
+            return Err(Malformed);
+        }
+
+        let mut id = [0u8, ..8];
+        for (d, b) in id.mut_iter().zip(body.slice(2, 10).iter()) {
+            *d = *b;
+        }
+        Ok(SignatureFile {
+            comment: lines.get(0).slice_from(COMMENT_PREFIX.len())
+                .to_string(),
+            key_id: id,
+            signature: try!(Signature::from_bytes(body.slice_from(10)))
+        })
+    }
+
+    /// Return `true` iff this file holds a valid signature of `msg` for
+    /// `pk` and its key id is the one of `pk`.
+    pub fn verify<D: Digest>(&self, digest: &mut D, pk: &PublicKey,
+                             msg: &[u8]) -> bool {
+        key_id(digest, pk) == self.key_id &&
+            pk.verify(digest, msg, &self.signature)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use digest::sha256::Sha256;
+    use error::Malformed;
+    use keys::KeyPair;
+    use sigfile;
+    use sigfile::SignatureFile;
+
+
+    #[test]
+    fn test_sign_parse() {
+        let mut h = Sha256::new();
+        let kp = KeyPair::generate();
+        let msg = "file contents".as_bytes();
+        let f = SignatureFile::sign(&mut h, &kp, msg, "release 1.0").unwrap();
+        assert!(f.key_id == sigfile::key_id(&mut h, kp.public()));
+        assert!(f.verify(&mut h, kp.public(), msg));
+        assert!(!f.verify(&mut h, kp.public(), "other".as_bytes()));
+        let other = KeyPair::generate();
+        assert!(!f.verify(&mut h, other.public(), msg));
+
+        let s = f.encode();
+        assert!(s.as_slice().starts_with("untrusted comment: release 1.0\n"));
+        assert!(s.as_slice().lines().count() == 2);
+        let g = SignatureFile::parse(s.as_slice()).unwrap();
+        assert!(g == f);
+        let t = s.as_slice().slice_to(s.len() - 1);
+        assert!(SignatureFile::parse(t).unwrap() == f);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut h = Sha256::new();
+        let kp = KeyPair::generate();
+        let f = SignatureFile::sign(&mut h, &kp, [], "").unwrap();
+        let s = f.encode();
+        let lines: Vec<&str> = s.as_slice().lines().collect();
+        let body = *lines.get(1);
+
+        let bad = [
+            format!("comment: \n{}\n", body),
+            format!("{}\n", body),
+            format!("{}\n{}\n\n", lines.get(0), body),
+            format!("{}\n{}!\n", lines.get(0), body),
+            format!("{}\n{}\n", lines.get(0), body.slice_from(4)),
+            format!("{}\nRWQ{}\n", lines.get(0), body.slice_from(3))
+        ];
+        for b in bad.iter() {
+            assert!(SignatureFile::parse(b.as_slice()) == Err(Malformed));
+        }
+
+        for c in ["two\nlines", "cr\r"].iter() {
+            assert!(SignatureFile::sign(&mut h, &kp, [], *c) ==
+                    Err(Malformed));
+        }
+    }
+}
@@ -0,0 +1,1302 @@
+//! Schnorr signatures
+//!
+//! EdDSA-like signatures over Edwards points built with a caller-supplied
+//! `Digest`. Nonces are derived deterministically from the secret key and
+//! the message. A signature is serialized as the 104 bytes string
+//! `R || s`, see `Signature`, `sign_attached()` prepends it to the message.
+//! Batches of signatures are verified at once by `verify_batch()`, which
+//! also locates the invalid signatures of failing batches. Montgomery
+//! (Diffie-Hellman) keys may also sign, see `sign_xeddsa()`.
+use serialize::hex::{FromHex, ToHex};
+use std::default::Default;
+use std::fmt::{Show, Formatter};
+use std::fmt;
+use std::from_str::FromStr;
+use std::rand::Rng;
+use std::result;
+
+use bytes;
+use bytes::{B416, B512, B832, Bytes, EdPoint, MontPoint, Scalar};
+use choice::{Choice, ConditionallySelectable};
+use digest::Digest;
+use ed::{GroupElem, PointTable};
+use error;
+use error::{Error, Malformed, NonCanonical};
+use kdf;
+use sc::ScalarElem;
+use utils;
+use vectors;
+
+
+static NONCE_LABEL: &'static [u8] = b"curve41417 signature nonce";
+static CHALLENGE_LABEL: &'static [u8] = b"curve41417 signature challenge";
+static TWEAK_NONCE_LABEL: &'static [u8] =
+    b"curve41417 tweaked signature nonce";
+static XEDDSA_NONCE_LABEL: &'static [u8] = b"curve41417 xeddsa nonce";
+static POP_NONCE_LABEL: &'static [u8] = b"curve41417 possession nonce";
+static POP_CHALLENGE_LABEL: &'static [u8] =
+    b"curve41417 possession challenge";
+static AGGREGATE_LABEL: &'static [u8] = b"curve41417 signature aggregation";
+static AGGREGATE_LIST_LABEL: &'static [u8] =
+    b"curve41417 signature aggregation list";
+
+
+/// Signature `(R, s)`.
+///
+/// `R` is a packed point and `s` a scalar in `[0, L-1]`. Its serialized
+/// form is the 104 bytes string `R || s` where `R` is encoded like
+/// `GroupElem::pack()` and `s` in little-endian. `Show` formats it as an
+/// hex-string of this serialized form.
+#[deriving(Clone, PartialEq, Eq)]
+pub struct Signature {
+    r: EdPoint,
+    s: Scalar
+}
+
+impl Signature {
+    /// Return the point `R` of this signature.
+    pub fn r<'a>(&'a self) -> &'a EdPoint {
+        &self.r
+    }
+
+    /// Return the scalar `s` of this signature.
+    pub fn s<'a>(&'a self) -> &'a Scalar {
+        &self.s
+    }
+
+    /// Return the serialized form `R || s` of this signature.
+    pub fn to_bytes(&self) -> B832 {
+        let mut b: B832 = Bytes::new_zero();
+        for (d, s) in b.as_mut_bytes().mut_iter().zip(
+            self.r.get_ref().as_bytes().iter()
+                .chain(self.s.get_ref().as_bytes().iter())) {
+            *d = *s;
+        }
+        b
+    }
+
+    /// Parse the serialized form `R || s` of a signature. Only canonical
+    /// encodings are accepted: return `Err(InvalidLength)` if `bytes` is not
+    /// 104 bytes long, `Err(NonCanonical)` if `R` isn't the canonical
+    /// encoding of a point or if `s` is not in `[0, L-1]`, and
+    /// `Err(OffCurve)` if `R` doesn't encode a point.
+    pub fn from_bytes(bytes: &[u8]) -> result::Result<Signature, Error> {
+        try!(error::check_length(bytes, 104));
+        let r = try!(GroupElem::decode_canonical(bytes.slice_to(52)));
+        let s = try!(ScalarElem::decode(bytes.slice_from(52)));
+        Ok(Signature {
+            r: r.pack(),
+            s: s.pack()
+        })
+    }
+
+    /// Return the DER encoding of this signature, the `SEQUENCE` of the two
+    /// `INTEGER`s `r` and `s` expected by X.509 tools for ECDSA-style
+    /// signatures. `r` is the integer whose 52 bytes little-endian
+    /// encoding is `R`'s (including the sign bit of its x-coordinate) and
+    /// `s` the value of `s`. The native form `to_bytes()` is smaller.
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(110);
+        der_push_integer(&mut body, self.r.get_ref().as_bytes());
+        der_push_integer(&mut body, self.s.get_ref().as_bytes());
+        let mut der = vec![0x30u8, body.len() as u8];
+        der.push_all(body.as_slice());
+        der
+    }
+
+    /// Parse the DER encoding of a signature, see `to_der()`. Parsing is
+    /// strict: return `Err(Malformed)` unless `der` is exactly a `SEQUENCE`
+    /// of two `INTEGER`s with minimal lengths and minimal encodings of
+    /// non-negative values, `Err(NonCanonical)` if an integer doesn't fit
+    /// in 52 bytes, otherwise the errors of `from_bytes()`.
+    pub fn from_der(der: &[u8]) -> result::Result<Signature, Error> {
+        if der.len() < 2 || der[0] != 0x30 || der[1] >= 0x80 ||
+            der[1] as uint != der.len() - 2 {
+            return Err(Malformed);
+        }
+        let (r, rest) = try!(der_read_integer(der.slice_from(2)));
+        let (s, rest) = try!(der_read_integer(rest));
+        if rest.len() != 0 {
+            return Err(Malformed);
+        }
+        let mut b = Vec::from_slice(r.as_bytes());
+        b.push_all(s.as_bytes());
+        Signature::from_bytes(b.as_slice())
+    }
+}
+
+// Append the DER INTEGER of the little-endian bytes le to out. Lengths
+// are below 128 and always use the short form.
+fn der_push_integer(out: &mut Vec<u8>, le: &[u8]) {
+    let mut be: Vec<u8> = le.iter().rev().map(|b| *b)
+        .skip_while(|b| *b == 0).collect();
+    if be.len() == 0 || *be.get(0) & 0x80 != 0 {
+        be.insert(0, 0);
+    }
+    out.push(0x02);
+    out.push(be.len() as u8);
+    out.push_all(be.as_slice());
+}
+
+// Parse the DER INTEGER at the start of der, return its value as 52
+// little-endian bytes and the rest of der.
+fn der_read_integer<'a>(der: &'a [u8])
+                        -> result::Result<(B416, &'a [u8]), Error> {
+    if der.len() < 2 || der[0] != 0x02 {
+        return Err(Malformed);
+    }
+    let len = der[1] as uint;
+    if len == 0 || len >= 0x80 || der.len() < 2 + len {
+        return Err(Malformed);
+    }
+    let mut v = der.slice(2, 2 + len);
+    // Negative values and leading zeros not followed by a high bit.
+    if v[0] & 0x80 != 0 || (len > 1 && v[0] == 0 && v[1] & 0x80 == 0) {
+        return Err(Malformed);
+    }
+    if len > 1 && v[0] == 0 {
+        v = v.slice_from(1);
+    }
+    if v.len() > 52 {
+        return Err(NonCanonical);
+    }
+
+    let mut b: B416 = Bytes::new_zero();
+    for (d, x) in b.as_mut_bytes().mut_iter().zip(v.iter().rev()) {
+        *d = *x;
+    }
+    Ok((b, der.slice_from(2 + len)))
+}
+
+impl ToHex for Signature {
+    fn to_hex(&self) -> String {
+        self.to_bytes().to_hex()
+    }
+}
+
+impl Show for Signature {
+    /// Format as hex-string.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl fmt::LowerHex for Signature {
+    /// Format as lowercase hex-string, see `bytes::B416`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        bytes::fmt_hex(self.to_bytes().as_bytes(), false, f)
+    }
+}
+
+impl fmt::UpperHex for Signature {
+    /// Format as uppercase hex-string, see `bytes::B416`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        bytes::fmt_hex(self.to_bytes().as_bytes(), true, f)
+    }
+}
+
+impl FromStr for Signature {
+    /// Parse from an hex-string, see `from_bytes()`.
+    fn from_str(s: &str) -> Option<Signature> {
+        match s.from_hex() {
+            Ok(b) => Signature::from_bytes(b.as_slice()).ok(),
+            Err(_) => None
+        }
+    }
+}
+
+
+// Return the secret scalar sk reduced mod L.
+fn secret_scalar(sk: &Scalar) -> ScalarElem {
+    let mut b: B512 = Bytes::new_zero();
+    for (d, s) in b.as_mut_bytes().mut_iter().zip(sk.get_ref().as_bytes()
+                                                  .iter()) {
+        *d = *s;
+    }
+    ScalarElem::unpack(&b).unwrap()
+}
+
+/// Sign a message
+///
+/// Return the signature of `msg` with the secret key `sk` whose public
+/// key is `pk` (`pk=sk.BP`, see `GroupElem::keypair()`). The signature is
+/// deterministic: `s = r + H(R || pk || msg).sk mod L` with `R = r.BP` and
+/// the nonce `r` derived from `sk`, `pk` and `msg`.
+pub fn sign<D: Digest>(digest: &mut D, sk: &Scalar, pk: &GroupElem,
+                       msg: &[u8]) -> Signature {
+    sign_labeled(digest, "sign::sign", NONCE_LABEL, CHALLENGE_LABEL, sk, pk,
+                 msg)
+}
+
+/// Sign a message for a tweaked key
+///
+/// Return the signature of `msg` for the public key `tpk = pk + t.BP`
+/// tweaked by `t` (see `keys::PublicKey::tweak_add()`), where `pk` is the
+/// public key of the secret key `sk`. The tweaked secret `sk + t mod L`
+/// only exists in a wiped buffer for the duration of the call, callers
+/// never handle it. `tpk` must be the tweaked public key, the signature
+/// verifies with `verify()` and `tpk`. The nonce is derived from `sk`,
+/// `t`, `tpk` and `msg`.
+pub fn sign_tweaked<D: Digest>(digest: &mut D, sk: &Scalar, t: &ScalarElem,
+                               tpk: &GroupElem, msg: &[u8]) -> Signature {
+    let a = secret_scalar(sk) + *t;
+    let tb = t.pack();
+    sign_with_secret(digest, "sign::sign_tweaked", TWEAK_NONCE_LABEL,
+                     CHALLENGE_LABEL, &a,
+                     [sk.get_ref().as_bytes(), tb.get_ref().as_bytes()], tpk,
+                     msg)
+}
+
+/// Sign a message with a Montgomery key
+///
+/// XEdDSA-like signature of `msg` with the Diffie-Hellman secret key `sk`
+/// (see `mont::keypair()`), verifiable with `verify_xeddsa()` against its
+/// Montgomery public key. `sk` is clamped, then the Edwards public key
+/// `E = sk.BP` is computed and the signature is made for the one of the
+/// points `E` and `-E` whose x-coordinate has the sign `0`, with the
+/// secret scalar `sk` or `-sk mod L` accordingly, selected in constant
+/// time. Such a Montgomery public key only identifies a point up to its
+/// sign, hence this choice. Protocols using a key for both Diffie-Hellman
+/// and signatures must be designed for it.
+pub fn sign_xeddsa<D: Digest>(digest: &mut D, sk: &Scalar, msg: &[u8])
+                              -> Signature {
+    let mut z = sk.get_ref().clone();
+    z.clamp_41417();
+    let z = Scalar(z);
+    let e = GroupElem::scalar_mult_base(&z);
+    let a = secret_scalar(&z);
+
+    let neg = Choice::from_u8(e.sign_of_x());
+    let pk: GroupElem = ConditionallySelectable::conditional_select(&e, &-e,
+                                                                    neg);
+    let a: ScalarElem = ConditionallySelectable::conditional_select(&a, &-a,
+                                                                    neg);
+    sign_with_secret(digest, "sign::sign_xeddsa", XEDDSA_NONCE_LABEL,
+                     CHALLENGE_LABEL, &a, [z.get_ref().as_bytes()], &pk, msg)
+}
+
+/// Verify a signature made with a Montgomery key
+///
+/// Return `true` iff `sig` is a valid signature of `msg` made by
+/// `sign_xeddsa()` with the secret key of the Montgomery public key `pk`:
+/// `pk` is converted to the Edwards point of sign `0` (see
+/// `GroupElem::from_mont()`) and `sig` is checked like by `verify()`.
+/// Return `false` if `pk` has no Edwards equivalent or if this point has a
+/// small order (e.g. `u = 0`, of order 2), such keys are never produced by
+/// `mont::keypair()`.
+pub fn verify_xeddsa<D: Digest>(digest: &mut D, pk: &MontPoint, msg: &[u8],
+                                sig: &Signature) -> bool {
+    match GroupElem::from_mont(pk, 0) {
+        Some(e) => {
+            e.scalar_mult_cofactor().is_identity().unwrap_u8() == 0 &&
+                verify(digest, &e, msg, sig)
+        },
+        None => false
+    }
+}
+
+// Sign msg with nonce and challenge derived with the given labels, the
+// intermediate values are recorded at site.
+fn sign_labeled<D: Digest>(digest: &mut D, site: &'static str,
+                           nonce_label: &[u8], challenge_label: &[u8],
+                           sk: &Scalar, pk: &GroupElem, msg: &[u8])
+                           -> Signature {
+    sign_with_secret(digest, site, nonce_label, challenge_label,
+                     &secret_scalar(sk), [sk.get_ref().as_bytes()], pk, msg)
+}
+
+// Sign msg with the secret scalar a of pk, the nonce is derived from the
+// secret parts nonce_key, pk and msg. The intermediate values are recorded
+// at site.
+fn sign_with_secret<D: Digest>(digest: &mut D, site: &'static str,
+                               nonce_label: &[u8], challenge_label: &[u8],
+                               a: &ScalarElem, nonce_key: &[&[u8]],
+                               pk: &GroupElem, msg: &[u8]) -> Signature {
+    let pkb = pk.pack();
+    let mut parts: Vec<&[u8]> = Vec::from_slice(nonce_key);
+    parts.push_all([pkb.get_ref().as_bytes(), msg]);
+    let r = kdf::derive_scalar(digest, nonce_label, parts.as_slice());
+    let rb = (GroupElem::base() * r).pack();
+
+    let k = GroupElem::hash_encoded_with_domain(digest, challenge_label, &rb,
+                                                [pkb.get_ref().as_bytes(),
+                                                 msg]);
+    let s = (r + k * *a).pack();
+    if vectors::enabled() {
+        vectors::record(site, "nonce", r.pack().get_ref().as_bytes());
+        vectors::record(site, "R", rb.get_ref().as_bytes());
+        vectors::record(site, "challenge", k.pack().get_ref().as_bytes());
+        vectors::record(site, "s", s.get_ref().as_bytes());
+    }
+    Signature {
+        r: rb,
+        s: s
+    }
+}
+
+// Check s.BP == R + H(R || pk || msg).pk, s is used without being
+// reduced mod L but its bit 415 is dropped by scalar_mult(). If given,
+// table must be the table of pk. If cofactored both sides are only
+// compared up to a small-order component.
+fn verify_equation<D: Digest>(digest: &mut D, challenge_label: &[u8],
+                              pk: &GroupElem, table: Option<&PointTable>,
+                              msg: &[u8], r: &EdPoint, s: &Scalar,
+                              cofactored: bool) -> bool {
+    let rp = match GroupElem::unpack(r) {
+        Some(rp) => rp,
+        None => return false
+    };
+
+    let pkb = pk.pack();
+    let k = GroupElem::hash_encoded_with_domain(digest, challenge_label, r,
+                                                [pkb.get_ref().as_bytes(),
+                                                 msg]);
+    if vectors::enabled() {
+        vectors::record("sign::verify", "challenge",
+                        k.pack().get_ref().as_bytes());
+    }
+    let kpk = match table {
+        Some(t) => t.scalar_mult(&k.pack()),
+        None => *pk * k
+    };
+    let lhs = GroupElem::base().scalar_mult(s);
+    match cofactored {
+        true => lhs.cofactor_equal(&(rp + kpk)).unwrap_u8() == 1,
+        false => lhs == rp + kpk
+    }
+}
+
+/// Verify a signature
+///
+/// Return `true` iff `sig` is a valid signature of `msg` for the public
+/// key `pk`, that is iff `s.BP == R + H(R || pk || msg).pk`. As `sig` was
+/// parsed with `Signature::from_bytes()` its encoding is canonical, use
+/// `verify_with_options()` for choosing other acceptance rules.
+pub fn verify<D: Digest>(digest: &mut D, pk: &GroupElem, msg: &[u8],
+                         sig: &Signature) -> bool {
+    verify_equation(digest, CHALLENGE_LABEL, pk, None, msg, sig.r(),
+                    sig.s(), false)
+}
+
+/// Same as `verify()` but the multiplication by `pk` uses its precomputed
+/// table `table` (see `PointTable::new()`), this is faster for verifying
+/// many signatures made with the same key. `table` must be the table of
+/// `pk`.
+pub fn verify_with_table<D: Digest>(digest: &mut D, pk: &GroupElem,
+                                    table: &PointTable, msg: &[u8],
+                                    sig: &Signature) -> bool {
+    verify_equation(digest, CHALLENGE_LABEL, pk, Some(table), msg, sig.r(),
+                    sig.s(), false)
+}
+
+/// Signature acceptance rules used by `verify_with_options()`.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub struct VerifyOptions {
+    /// Reject signatures whose `s` is not in `[0, L-1]`. Otherwise any
+    /// 52 bytes value is accepted and used without reduction `mod L`, but
+    /// its bit 415 (the top bit of the last byte) is ignored like by
+    /// `GroupElem::scalar_mult()`: values only differing by this bit are
+    /// the same `s`.
+    pub canonical_s: bool,
+    /// Reject signatures whose `R` is not the canonical encoding of a
+    /// point (see `GroupElem::decode_canonical()`).
+    pub canonical_r: bool,
+    /// Reject signatures whose `R`, and public keys, are not in the
+    /// subgroup of order `L` (i.e. have a small-order component).
+    pub reject_torsion: bool,
+    /// Check the cofactored equation `8.s.BP == 8.R + 8.H(R || pk ||
+    /// msg).pk` instead of the cofactorless one `s.BP == R + H(R || pk ||
+    /// msg).pk`.
+    ///
+    /// Both equations agree on every signature and key without a
+    /// small-order component. Otherwise the cofactored equation accepts
+    /// signatures whose equation only holds up to a small-order point,
+    /// which the cofactorless one rejects (or accepts depending on the
+    /// parity of the challenge), so that implementations using different
+    /// equations disagree on such signatures. Consensus systems must pick
+    /// one for all their verifiers; the cofactored equation is the one
+    /// consistent with batch verification. Irrelevant when
+    /// `reject_torsion` is set.
+    pub cofactored: bool
+}
+
+impl VerifyOptions {
+    /// Strict rules, all checks enabled. A given message and key then
+    /// have at most one valid signature per nonce (no malleability).
+    pub fn strict() -> VerifyOptions {
+        VerifyOptions {
+            canonical_s: true,
+            canonical_r: true,
+            reject_torsion: true,
+            cofactored: false
+        }
+    }
+
+    /// Legacy-compatible rules, all checks disabled. Only the
+    /// cofactorless verification equation must hold.
+    pub fn legacy() -> VerifyOptions {
+        VerifyOptions {
+            canonical_s: false,
+            canonical_r: false,
+            reject_torsion: false,
+            cofactored: false
+        }
+    }
+}
+
+impl Default for VerifyOptions {
+    /// Return the strict rules.
+    fn default() -> VerifyOptions {
+        VerifyOptions::strict()
+    }
+}
+
+/// Verify a serialized signature with explicit acceptance rules
+///
+/// Return `true` iff the 104 bytes `sig` is a valid signature of `msg`
+/// for the public key `pk` (see `verify()`) and complies with `options`.
+pub fn verify_with_options<D: Digest>(digest: &mut D, pk: &GroupElem,
+                                      msg: &[u8], sig: &[u8],
+                                      options: &VerifyOptions) -> bool {
+    if sig.len() != 104 {
+        return false;
+    }
+
+    let r = EdPoint(Bytes::from_bytes(sig.slice_to(52)).unwrap());
+    let s = Scalar(Bytes::from_bytes(sig.slice_from(52)).unwrap());
+
+    if options.canonical_s &&
+        ScalarElem::is_canonical(s.get_ref()).unwrap_u8() == 0 {
+        return false;
+    }
+    if options.canonical_r &&
+        GroupElem::decode_canonical(r.get_ref().as_bytes()).is_err() {
+        return false;
+    }
+    if options.reject_torsion {
+        let rp = match GroupElem::unpack(&r) {
+            Some(rp) => rp,
+            None => return false
+        };
+        if rp.is_torsion_free().unwrap_u8() == 0 ||
+            pk.is_torsion_free().unwrap_u8() == 0 {
+            return false;
+        }
+    }
+
+    verify_equation(digest, CHALLENGE_LABEL, pk, None, msg, &r, &s,
+                    options.cofactored)
+}
+
+/// Outcome of `verify_batch()`.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub enum BatchResult {
+    /// Every signature of the batch is valid.
+    BatchValid,
+    /// The signature at this index is invalid, the lowest such index.
+    BatchInvalid(uint),
+    /// The verification was cancelled before completion.
+    BatchCancelled
+}
+
+/// Cancellation token polled by `verify_batch_with_cancel()`, e.g. set
+/// by another task or checking a deadline.
+pub trait Cancellation {
+    /// Return `true` once the operation must stop.
+    fn is_cancelled(&self) -> bool;
+}
+
+// Token never cancelling.
+struct NoCancellation;
+
+impl Cancellation for NoCancellation {
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+// Return the terms (ni, Pi) of the batch equation of entries, such that
+// 8.(sum ni.Pi) is the identity iff 8.(sum zi.si).BP == 8.(sum zi.Ri +
+// (zi.ki).pki) with random 128 bits coefficients zi, where entries[i]
+// holds (Ri, ki). Return None if some entries[i] is None, that is if Ri
+// is not on the curve.
+fn batch_terms(entries: &[Option<(GroupElem, ScalarElem)>],
+               pks: &[GroupElem], sigs: &[Signature])
+               -> Option<(Vec<Scalar>, Vec<GroupElem>)> {
+    let mut rng = utils::urandom_rng();
+    let mut s = ScalarElem::zero();
+    let mut ns = Vec::with_capacity(2 * entries.len() + 1);
+    let mut ps = Vec::with_capacity(2 * entries.len() + 1);
+    for ((e, pk), sig) in entries.iter().zip(pks.iter()).zip(sigs.iter()) {
+        let (r, k) = match *e {
+            Some((ref r, ref k)) => (r, k),
+            None => return None
+        };
+        let mut zb: B416 = Bytes::new_zero();
+        rng.fill_bytes(zb.as_mut_bytes().mut_slice_to(16));
+        let z = ScalarElem::unpack(&zb).unwrap();
+        s = s + z * ScalarElem::unpack(sig.s().get_ref()).unwrap();
+        ns.push((-z).pack());
+        ps.push(r.clone());
+        ns.push((-(z * *k)).pack());
+        ps.push(pk.clone());
+    }
+    ns.push(s.pack());
+    ps.push(GroupElem::base());
+    Some((ns, ps))
+}
+
+// Check the batch equation of entries, see batch_terms().
+fn batch_equation(entries: &[Option<(GroupElem, ScalarElem)>],
+                  pks: &[GroupElem], sigs: &[Signature]) -> bool {
+    match batch_terms(entries, pks, sigs) {
+        Some((ns, ps)) =>
+            GroupElem::multiscalar_mult_vartime(ns.as_slice(), ps.as_slice())
+                .scalar_mult_cofactor().is_identity().unwrap_u8() == 1,
+        None => false
+    }
+}
+
+// Return (Ri, ki) for each signature with ki its challenge, or None if Ri
+// is not on the curve.
+fn batch_entries<D: Digest>(digest: &mut D, pks: &[GroupElem],
+                            msgs: &[&[u8]], sigs: &[Signature])
+                            -> Vec<Option<(GroupElem, ScalarElem)>> {
+    let mut entries = Vec::with_capacity(sigs.len());
+    for ((pk, msg), sig) in pks.iter().zip(msgs.iter()).zip(sigs.iter()) {
+        entries.push(GroupElem::unpack(sig.r()).map(|r| {
+            let pkb = pk.pack();
+            let k = GroupElem::hash_encoded_with_domain(
+                digest, CHALLENGE_LABEL, sig.r(),
+                [pkb.get_ref().as_bytes(), *msg]);
+            (r, k)
+        }));
+    }
+    entries
+}
+
+// Return the terms of the batch equation of the whole batch, see
+// batch_terms(), for `parallel::verify_batch_parallel()`.
+#[doc(hidden)]
+pub fn batch_equation_terms<D: Digest>(digest: &mut D, pks: &[GroupElem],
+                                       msgs: &[&[u8]], sigs: &[Signature])
+                                       -> Option<(Vec<Scalar>,
+                                                  Vec<GroupElem>)> {
+    assert!(pks.len() == msgs.len() && pks.len() == sigs.len());
+    let entries = batch_entries(digest, pks, msgs, sigs);
+    batch_terms(entries.as_slice(), pks, sigs)
+}
+
+// Return the lowest index of an invalid signature in [lo, hi) by checking
+// the batch equation of the range then of its halves, or Err(()) if
+// cancelled.
+fn find_invalid<C: Cancellation>(entries: &[Option<(GroupElem, ScalarElem)>],
+                                 pks: &[GroupElem], sigs: &[Signature],
+                                 lo: uint, hi: uint, cancel: &C)
+                                 -> result::Result<Option<uint>, ()> {
+    if cancel.is_cancelled() {
+        return Err(());
+    }
+    if batch_equation(entries.slice(lo, hi), pks.slice(lo, hi),
+                      sigs.slice(lo, hi)) {
+        return Ok(None);
+    }
+    if hi - lo == 1 {
+        return Ok(Some(lo));
+    }
+    let mid = lo + (hi - lo) / 2;
+    match try!(find_invalid(entries, pks, sigs, lo, mid, cancel)) {
+        Some(i) => Ok(Some(i)),
+        None => find_invalid(entries, pks, sigs, mid, hi, cancel)
+    }
+}
+
+/// Verify a batch of signatures
+///
+/// Return `BatchValid` iff every `sigs[i]` is a valid signature of
+/// `msgs[i]` for `pks[i]`, checked at once with a single variable-time
+/// multi-scalar multiplication (see
+/// `GroupElem::multiscalar_mult_vartime()`). This is the cofactored
+/// equation, see `VerifyOptions::cofactored`: a signature whose equation
+/// only holds up to a small-order component is accepted here but rejected
+/// by `verify()`. When the batch fails its halves are re-verified
+/// recursively, down to the lowest index of an invalid signature returned
+/// as `BatchInvalid(index)`. Fails if the three slices don't have the
+/// same length.
+pub fn verify_batch<D: Digest>(digest: &mut D, pks: &[GroupElem],
+                               msgs: &[&[u8]], sigs: &[Signature])
+                               -> BatchResult {
+    verify_batch_with_cancel(digest, pks, msgs, sigs, &NoCancellation)
+}
+
+/// Same as `verify_batch()` but `cancel` is polled before each batch
+/// equation, `BatchCancelled` is returned as soon as it is cancelled.
+pub fn verify_batch_with_cancel<D: Digest, C: Cancellation>(
+    digest: &mut D, pks: &[GroupElem], msgs: &[&[u8]], sigs: &[Signature],
+    cancel: &C) -> BatchResult {
+    assert!(pks.len() == msgs.len() && pks.len() == sigs.len());
+    if pks.len() == 0 {
+        return BatchValid;
+    }
+
+    let entries = batch_entries(digest, pks, msgs, sigs);
+    match find_invalid(entries.as_slice(), pks, sigs, 0, sigs.len(), cancel) {
+        Ok(None) => BatchValid,
+        Ok(Some(i)) => BatchInvalid(i),
+        Err(()) => BatchCancelled
+    }
+}
+
+/// Sign a message, attached form
+///
+/// Return the signed message `sig || msg` where `sig` is the 104 bytes
+/// serialized signature of `msg` (see `sign()`), like NaCl's
+/// `crypto_sign`. Use `open()` to verify it.
+pub fn sign_attached<D: Digest>(digest: &mut D, sk: &Scalar, pk: &GroupElem,
+                                msg: &[u8]) -> Vec<u8> {
+    let sig = sign(digest, sk, pk, msg);
+    let mut sm = Vec::with_capacity(104 + msg.len());
+    sm.push_all(sig.to_bytes().as_bytes());
+    sm.push_all(msg);
+    sm
+}
+
+/// Open a signed message, attached form
+///
+/// Verify the signed message `sm` produced by `sign_attached()` for the
+/// public key `pk` and return the message part of `sm`, or `None` if `sm`
+/// is too short or if its signature is not valid (see `verify()`). Like
+/// NaCl's `crypto_sign_open`.
+pub fn open<'a, D: Digest>(digest: &mut D, pk: &GroupElem, sm: &'a [u8])
+                           -> Option<&'a [u8]> {
+    if sm.len() < 104 {
+        return None;
+    }
+    let sig = match Signature::from_bytes(sm.slice_to(104)) {
+        Ok(sig) => sig,
+        Err(_) => return None
+    };
+    let msg = sm.slice_from(104);
+    if verify(digest, pk, msg, &sig) {
+        Some(msg)
+    } else {
+        None
+    }
+}
+
+
+/// Prove the possession of a secret key
+///
+/// Return a proof that the owner of the public key `pk` knows its secret
+/// key `sk`, bound to the registration `context` (e.g. a protocol and
+/// session identifier). It is a signature of `context` whose nonce and
+/// challenge are derived with labels distinct from those of `sign()`, it
+/// can't be mistaken for or produced from a regular signature. Schemes
+/// aggregating public keys should require such proofs for preventing
+/// rogue-key attacks.
+pub fn prove_possession<D: Digest>(digest: &mut D, sk: &Scalar,
+                                   pk: &GroupElem, context: &[u8])
+                                   -> Signature {
+    sign_labeled(digest, "sign::prove_possession", POP_NONCE_LABEL,
+                 POP_CHALLENGE_LABEL, sk, pk, context)
+}
+
+/// Verify a proof of possession
+///
+/// Return `true` iff `proof` produced by `prove_possession()` proves the
+/// possession of the secret key of `pk` for `context`. `pk` must also be
+/// torsion-free.
+pub fn verify_possession<D: Digest>(digest: &mut D, pk: &GroupElem,
+                                    proof: &Signature, context: &[u8])
+                                    -> bool {
+    pk.is_torsion_free().unwrap_u8() == 1 &&
+        verify_equation(digest, POP_CHALLENGE_LABEL, pk, None, context,
+                        proof.r(), proof.s(), false)
+}
+
+
+/// Half-aggregated signature
+///
+/// Aggregation of `n` signatures `(R_i, s_i)` keeping every `R_i` but
+/// replacing the `s_i` by the single scalar `s = z_1.s_1 + ... + z_n.s_n
+/// mod L` where the coefficients `z_i` are derived from all the signed
+/// messages, public keys and `R_i` (see `aggregate()`). Its serialized form
+/// is `R_1 || ... || R_n || s`, `52.(n + 1)` bytes instead of `104.n`.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub struct AggregateSignature {
+    rs: Vec<EdPoint>,
+    s: Scalar
+}
+
+impl AggregateSignature {
+    /// Return the points `R_i` of the aggregated signatures.
+    pub fn rs<'a>(&'a self) -> &'a [EdPoint] {
+        self.rs.as_slice()
+    }
+
+    /// Return the aggregated scalar `s`.
+    pub fn s<'a>(&'a self) -> &'a Scalar {
+        &self.s
+    }
+
+    /// Return the serialized form `R_1 || ... || R_n || s`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(52 * (self.rs.len() + 1));
+        for r in self.rs.iter() {
+            b.push_all(r.get_ref().as_bytes());
+        }
+        b.push_all(self.s.get_ref().as_bytes());
+        b
+    }
+
+    /// Parse the serialized form of an aggregated signature, with the same
+    /// checks as `Signature::from_bytes()`. Its length must be a multiple
+    /// of 52 bytes and at least 104 bytes, otherwise return
+    /// `Err(InvalidLength)` with the closest smaller valid length (or 104).
+    pub fn from_bytes(bytes: &[u8])
+                      -> result::Result<AggregateSignature, Error> {
+        let len = bytes.len();
+        if len < 104 {
+            return Err(error::InvalidLength(104, len));
+        }
+        try!(error::check_length(bytes, len - len % 52));
+
+        let n = len / 52 - 1;
+        let mut rs = Vec::with_capacity(n);
+        for i in range(0u, n) {
+            let r = try!(GroupElem::decode_canonical(
+                bytes.slice(52 * i, 52 * (i + 1))));
+            rs.push(r.pack());
+        }
+        let s = try!(ScalarElem::decode(bytes.slice_from(52 * n)));
+        Ok(AggregateSignature {
+            rs: rs,
+            s: s.pack()
+        })
+    }
+}
+
+// Return the aggregation coefficients z_i bound to all the signed
+// messages, public keys and R_i: the list is hashed once into a key with
+// HMAC, then each z_i is derived from the key and i.
+fn aggregation_coefficients<D: Digest>(digest: &mut D, pks: &[GroupElem],
+                                       msgs: &[&[u8]], rs: &[EdPoint])
+                                       -> Vec<ScalarElem> {
+    let n = rs.len();
+    let pkbs: Vec<EdPoint> = pks.iter().map(|pk| pk.pack()).collect();
+    let lens: Vec<[u8, ..8]> =
+        msgs.iter().map(|msg| utils::le64(msg.len())).collect();
+
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(4 * n);
+    for (((r, pkb), len), msg) in rs.iter().zip(pkbs.iter())
+        .zip(lens.iter()).zip(msgs.iter()) {
+        parts.push(r.get_ref().as_bytes());
+        parts.push(pkb.get_ref().as_bytes());
+        parts.push(len.as_slice());
+        parts.push(*msg);
+    }
+    let mut key = Vec::from_elem(digest.output_bytes(), 0u8);
+    kdf::hmac(digest, AGGREGATE_LIST_LABEL, parts.as_slice(),
+              key.as_mut_slice());
+
+    range(0u, n).map(|i| {
+        let idx = utils::le64(i);
+        kdf::derive_scalar(digest, AGGREGATE_LABEL,
+                           [key.as_slice(), idx.as_slice()])
+    }).collect()
+}
+
+/// Aggregate signatures
+///
+/// Return the half-aggregation of the signatures `sigs`, where `sigs[i]`
+/// signs `msgs[i]` for the public key `pks[i]`. Signatures are not
+/// verified, the aggregate of invalid signatures is invalid. Return
+/// `Err(Malformed)` if `pks`, `msgs` and `sigs` don't have the same length
+/// or are empty.
+pub fn aggregate<D: Digest>(digest: &mut D, pks: &[GroupElem],
+                            msgs: &[&[u8]], sigs: &[Signature])
+                            -> result::Result<AggregateSignature, Error> {
+    if sigs.len() == 0 || pks.len() != sigs.len() ||
+        msgs.len() != sigs.len() {
+        return Err(Malformed);
+    }
+
+    let rs: Vec<EdPoint> = sigs.iter().map(|sig| sig.r().clone()).collect();
+    let zs = aggregation_coefficients(digest, pks, msgs, rs.as_slice());
+    let mut s = ScalarElem::zero();
+    for (z, sig) in zs.iter().zip(sigs.iter()) {
+        s = s + *z * ScalarElem::unpack(sig.s().get_ref()).unwrap();
+    }
+    Ok(AggregateSignature {
+        rs: rs,
+        s: s.pack()
+    })
+}
+
+/// Verify an aggregated signature
+///
+/// Return `true` iff `agg` is the valid aggregation of signatures of
+/// `msgs[i]` for the public keys `pks[i]`, that is iff
+/// `s.BP == z_1.(R_1 + k_1.pk_1) + ... + z_n.(R_n + k_n.pk_n)` with
+/// `k_i = H(R_i || pk_i || msg_i)` (see `verify()`). Return `false` if the
+/// numbers of public keys, messages and signatures differ.
+pub fn verify_aggregate<D: Digest>(digest: &mut D, pks: &[GroupElem],
+                                   msgs: &[&[u8]],
+                                   agg: &AggregateSignature) -> bool {
+    let n = agg.rs().len();
+    if n == 0 || pks.len() != n || msgs.len() != n {
+        return false;
+    }
+
+    let zs = aggregation_coefficients(digest, pks, msgs, agg.rs());
+    let mut q = GroupElem::neutral();
+    for (((rb, pk), msg), z) in agg.rs().iter().zip(pks.iter())
+        .zip(msgs.iter()).zip(zs.iter()) {
+        let r = match GroupElem::unpack(rb) {
+            Some(r) => r,
+            None => return false
+        };
+        let pkb = pk.pack();
+        let k = GroupElem::hash_encoded_with_domain(
+            digest, CHALLENGE_LABEL, rb, [pkb.get_ref().as_bytes(), *msg]);
+        q = q + r * *z + *pk * (*z * k);
+    }
+    GroupElem::base().scalar_mult(agg.s()) == q
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::cmp;
+    use std::from_str::FromStr;
+
+    use bytes::{B416, Bytes, EdPoint, MontPoint};
+    use constants;
+    use digest::sha256::Sha256;
+    use ed::{GroupElem, PointTable};
+    use error::{InvalidLength, Malformed, NonCanonical};
+    use mont;
+    use sc::ScalarElem;
+    use sign;
+    use sign::{AggregateSignature, Signature, VerifyOptions};
+    use sign::{BatchCancelled, BatchInvalid, BatchValid, Cancellation};
+
+
+    #[test]
+    fn test_sign_verify() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let msg = "message".as_bytes();
+
+        let sig = sign::sign(&mut h, &sk, &pk, msg);
+        assert!(sign::verify(&mut h, &pk, msg, &sig));
+        assert!(sig == sign::sign(&mut h, &sk, &pk, msg));
+
+        assert!(!sign::verify(&mut h, &pk, "other".as_bytes(), &sig));
+        let (pk2, _) = GroupElem::keypair();
+        assert!(!sign::verify(&mut h, &pk2, msg, &sig));
+
+        let t = PointTable::new(&pk);
+        assert!(sign::verify_with_table(&mut h, &pk, &t, msg, &sig));
+        assert!(!sign::verify_with_table(&mut h, &pk, &t, "other".as_bytes(),
+                                         &sig));
+    }
+
+    #[test]
+    fn test_sign_tweaked() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let t = ScalarElem::new_rand();
+        let tpk = pk + GroupElem::scalar_mult_base(&t.pack());
+        let msg = "message".as_bytes();
+
+        let sig = sign::sign_tweaked(&mut h, &sk, &t, &tpk, msg);
+        assert!(sign::verify(&mut h, &tpk, msg, &sig));
+        assert!(!sign::verify(&mut h, &pk, msg, &sig));
+        assert!(sig == sign::sign_tweaked(&mut h, &sk, &t, &tpk, msg));
+
+        let t2 = t + ScalarElem::new_rand();
+        let sig2 = sign::sign_tweaked(&mut h, &sk, &t2, &tpk, msg);
+        assert!(!sign::verify(&mut h, &tpk, msg, &sig2));
+        assert!(sig2.r() != sig.r());
+    }
+
+    #[test]
+    fn test_xeddsa() {
+        let mut h = Sha256::new();
+        let msg = "message".as_bytes();
+        // Keys whose Edwards public keys have either sign.
+        for _ in range(0u, 8) {
+            let (pk, sk) = mont::keypair();
+            let sig = sign::sign_xeddsa(&mut h, &sk, msg);
+            assert!(sign::verify_xeddsa(&mut h, &pk, msg, &sig));
+            assert!(sig == sign::sign_xeddsa(&mut h, &sk, msg));
+            assert!(!sign::verify_xeddsa(&mut h, &pk, "other".as_bytes(),
+                                         &sig));
+            let (other, _) = mont::keypair();
+            assert!(!sign::verify_xeddsa(&mut h, &other, msg, &sig));
+
+            let e = GroupElem::from_mont(&pk, 0).unwrap();
+            assert!(sign::verify(&mut h, &e, msg, &sig));
+        }
+        let (_, sk) = mont::keypair();
+        let sig = sign::sign_xeddsa(&mut h, &sk, msg);
+        // u = 0 maps to the point of order 2.
+        let zero: MontPoint = MontPoint(Bytes::new_zero());
+        assert!(GroupElem::from_mont(&zero, 0).is_some());
+        assert!(!sign::verify_xeddsa(&mut h, &zero, msg, &sig));
+        // A small u with no Edwards equivalent.
+        let mut found = false;
+        for u in range(1u8, 255) {
+            let mut b: B416 = Bytes::new_zero();
+            *b.get_mut(0) = u;
+            let pk = MontPoint(b);
+            if GroupElem::from_mont(&pk, 0).is_none() {
+                assert!(!sign::verify_xeddsa(&mut h, &pk, msg, &sig));
+                found = true;
+                break;
+            }
+        }
+        assert!(found);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let sig = sign::sign(&mut h, &sk, &pk, [1u8, 2, 3]);
+
+        let b = sig.to_bytes();
+        assert!(b.len() == 104);
+        assert!(Signature::from_bytes(b.as_bytes()).unwrap() == sig);
+        assert!(Signature::from_bytes(b.as_bytes().slice_to(103)) ==
+                Err(InvalidLength(104, 103)));
+
+        let hex = format!("{}", sig);
+        let sig2: Signature = FromStr::from_str(hex.as_slice()).unwrap();
+        assert!(sig2 == sig);
+
+        // s + L is rejected.
+        let s = ScalarElem::unpack(sig.s().get_ref()).unwrap();
+        let l = ScalarElem::order();
+        let mut c = b.clone();
+        let mut carry = 0u;
+        for i in range(0u, 52) {
+            let v = *s.pack().get(i) as uint + *l.get(i) as uint + carry;
+            *c.get_mut(52 + i) = v as u8;
+            carry = v >> 8;
+        }
+        assert!(carry == 0);
+        assert!(Signature::from_bytes(c.as_bytes()) == Err(NonCanonical));
+    }
+
+    // Return the minimal big-endian INTEGER contents of le.
+    fn be(le: &[u8]) -> Vec<u8> {
+        let mut v: Vec<u8> = le.iter().rev().map(|b| *b)
+            .skip_while(|b| *b == 0).collect();
+        if v.len() == 0 || *v.get(0) >= 0x80 {
+            v.insert(0, 0);
+        }
+        v
+    }
+
+    // Return the DER SEQUENCE of the INTEGERs of contents r and s.
+    fn der(r: &[u8], s: &[u8]) -> Vec<u8> {
+        let mut d = vec![0x30u8, (r.len() + s.len() + 4) as u8,
+                         0x02, r.len() as u8];
+        d.push_all(r);
+        d.push_all([0x02, s.len() as u8]);
+        d.push_all(s);
+        d
+    }
+
+    #[test]
+    fn test_der() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let sig = sign::sign(&mut h, &sk, &pk, [1u8, 2, 3]);
+        let r = be(sig.r().get_ref().as_bytes());
+        let s = be(sig.s().get_ref().as_bytes());
+
+        let d = sig.to_der();
+        assert!(d == der(r.as_slice(), s.as_slice()));
+        assert!(Signature::from_der(d.as_slice()).unwrap() == sig);
+
+        // Framing errors.
+        let mut t = d.clone();
+        t.push(0);
+        assert!(Signature::from_der(t.as_slice()) == Err(Malformed));
+        assert!(Signature::from_der(d.slice_to(d.len() - 1)) ==
+                Err(Malformed));
+        let mut t = d.clone();
+        *t.get_mut(0) = 0x31;
+        assert!(Signature::from_der(t.as_slice()) == Err(Malformed));
+        let mut t = vec![0x30u8, 0x81];
+        t.push_all(d.slice_from(1));
+        assert!(Signature::from_der(t.as_slice()) == Err(Malformed));
+        assert!(Signature::from_der([]) == Err(Malformed));
+
+        // Integer encodings: leading zero, negative, empty, too large.
+        let mut z = vec![0u8];
+        z.push_all(s.as_slice());
+        assert!(Signature::from_der(der(r.as_slice(), z.as_slice())
+                                    .as_slice()) == Err(Malformed));
+        assert!(Signature::from_der(der(r.as_slice(), [0x80]).as_slice()) ==
+                Err(Malformed));
+        assert!(Signature::from_der(der(r.as_slice(), []).as_slice()) ==
+                Err(Malformed));
+        let mut big = vec![1u8];
+        big.grow(52, &0);
+        assert!(Signature::from_der(der(r.as_slice(), big.as_slice())
+                                    .as_slice()) == Err(NonCanonical));
+        let l = be(ScalarElem::order().as_bytes());
+        assert!(Signature::from_der(der(r.as_slice(), l.as_slice())
+                                    .as_slice()) == Err(NonCanonical));
+
+        // s = 0 is encoded on a single byte.
+        let d0 = der(r.as_slice(), [0]);
+        let sig0 = Signature::from_der(d0.as_slice()).unwrap();
+        assert!(sig0.to_der() == d0);
+    }
+
+    #[test]
+    fn test_verify_options() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let msg = "message".as_bytes();
+        let strict = VerifyOptions::strict();
+        let legacy = VerifyOptions::legacy();
+
+        let sig = sign::sign(&mut h, &sk, &pk, msg);
+        let b = sig.to_bytes();
+        assert!(sign::verify_with_options(&mut h, &pk, msg, b.as_bytes(),
+                                          &strict));
+        assert!(sign::verify_with_options(&mut h, &pk, msg, b.as_bytes(),
+                                          &legacy));
+
+        // s + L is only accepted by legacy rules.
+        let s = ScalarElem::unpack(sig.s().get_ref()).unwrap();
+        let l = ScalarElem::order();
+        let mut c = b.clone();
+        let mut carry = 0u;
+        for i in range(0u, 52) {
+            let v = *s.pack().get(i) as uint + *l.get(i) as uint + carry;
+            *c.get_mut(52 + i) = v as u8;
+            carry = v >> 8;
+        }
+        assert!(!sign::verify_with_options(&mut h, &pk, msg, c.as_bytes(),
+                                           &strict));
+        assert!(sign::verify_with_options(&mut h, &pk, msg, c.as_bytes(),
+                                          &legacy));
+        // So is s with its ignored bit 415 set.
+        let mut c = b.clone();
+        *c.get_mut(103) |= 0x80;
+        assert!(!sign::verify_with_options(&mut h, &pk, msg, c.as_bytes(),
+                                           &strict));
+        assert!(sign::verify_with_options(&mut h, &pk, msg, c.as_bytes(),
+                                          &legacy));
+
+        // A public key with a torsion component, y = -1 encodes the point
+        // (0, -1) of order 2.
+        let y: B416 = Bytes::from_bytes(constants::P_MINUS_ONE).unwrap();
+        let t = GroupElem::unpack(&EdPoint(y)).unwrap();
+        assert!(t != GroupElem::neutral());
+        assert!((t + t).is_identity().unwrap_u8() == 1);
+        let pkt = pk + t;
+        let sig = sign::sign(&mut h, &sk, &pkt, msg);
+        assert!(!sign::verify_with_options(&mut h, &pkt, msg,
+                                           sig.to_bytes().as_bytes(),
+                                           &strict));
+
+        // The challenge k of a signature made with sk for pkt is odd for
+        // about one message out of two, then s.BP == R + k.pkt - t: only
+        // the cofactored equation holds.
+        let cofactored = VerifyOptions {
+            cofactored: true,
+            ..legacy.clone()
+        };
+        let mut diverged = false;
+        for i in range(0u8, 64) {
+            let m = [i];
+            let b = sign::sign(&mut h, &sk, &pkt, m).to_bytes();
+            assert!(sign::verify_with_options(&mut h, &pkt, m, b.as_bytes(),
+                                              &cofactored));
+            if !sign::verify_with_options(&mut h, &pkt, m, b.as_bytes(),
+                                          &legacy) {
+                diverged = true;
+                break;
+            }
+        }
+        assert!(diverged);
+    }
+
+    struct CancelAfter {
+        polls: Cell<uint>
+    }
+
+    impl Cancellation for CancelAfter {
+        fn is_cancelled(&self) -> bool {
+            let n = self.polls.get();
+            self.polls.set(n - cmp::min(n, 1));
+            n == 0
+        }
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let mut h = Sha256::new();
+        let mut msgs: Vec<&[u8]> = Vec::new();
+        let mut pks = Vec::new();
+        let mut sigs = Vec::new();
+        let all = ["a", "b", "c", "d", "e", "f", "g"];
+        for m in all.iter() {
+            let (pk, sk) = GroupElem::keypair();
+            sigs.push(sign::sign(&mut h, &sk, &pk, m.as_bytes()));
+            pks.push(pk);
+            msgs.push(m.as_bytes());
+        }
+        assert!(sign::verify_batch(&mut h, pks.as_slice(), msgs.as_slice(),
+                                   sigs.as_slice()) == BatchValid);
+        assert!(sign::verify_batch(&mut h, [], [], []) == BatchValid);
+
+        // Wrong messages at 2 and 5, the lowest one is reported.
+        *msgs.get_mut(5) = "x".as_bytes();
+        assert!(sign::verify_batch(&mut h, pks.as_slice(), msgs.as_slice(),
+                                   sigs.as_slice()) == BatchInvalid(5));
+        *msgs.get_mut(2) = "x".as_bytes();
+        assert!(sign::verify_batch(&mut h, pks.as_slice(), msgs.as_slice(),
+                                   sigs.as_slice()) == BatchInvalid(2));
+        *msgs.get_mut(2) = "c".as_bytes();
+        *msgs.get_mut(5) = "f".as_bytes();
+
+        // R off the curve.
+        let mut b: B416 = Bytes::new_zero();
+        for i in range(0u8, 255) {
+            *b.get_mut(0) = i;
+            if GroupElem::unpack(&EdPoint(b.clone())).is_none() {
+                break;
+            }
+        }
+        let s = sigs.get(3).s().clone();
+        *sigs.get_mut(3) = Signature {
+            r: EdPoint(b),
+            s: s
+        };
+        assert!(sign::verify_batch(&mut h, pks.as_slice(), msgs.as_slice(),
+                                   sigs.as_slice()) == BatchInvalid(3));
+
+        // The first poll only succeeds, then the search is cancelled.
+        let cancel = CancelAfter {
+            polls: Cell::new(1)
+        };
+        assert!(sign::verify_batch_with_cancel(&mut h, pks.as_slice(),
+                                               msgs.as_slice(),
+                                               sigs.as_slice(), &cancel) ==
+                BatchCancelled);
+        let cancel = CancelAfter {
+            polls: Cell::new(100)
+        };
+        assert!(sign::verify_batch_with_cancel(&mut h, pks.as_slice(),
+                                               msgs.as_slice(),
+                                               sigs.as_slice(), &cancel) ==
+                BatchInvalid(3));
+    }
+
+    #[test]
+    fn test_attached() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let msg = "message".as_bytes();
+
+        let mut sm = sign::sign_attached(&mut h, &sk, &pk, msg);
+        assert!(sm.len() == 104 + msg.len());
+        assert!(sm.slice_to(104) ==
+                sign::sign(&mut h, &sk, &pk, msg).to_bytes().as_bytes());
+        assert!(sign::open(&mut h, &pk, sm.as_slice()).unwrap() == msg);
+
+        let (pk2, _) = GroupElem::keypair();
+        assert!(sign::open(&mut h, &pk2, sm.as_slice()).is_none());
+        assert!(sign::open(&mut h, &pk, sm.slice_to(103)).is_none());
+        *sm.get_mut(104) ^= 1;
+        assert!(sign::open(&mut h, &pk, sm.as_slice()).is_none());
+
+        let empty = sign::sign_attached(&mut h, &sk, &pk, []);
+        assert!(sign::open(&mut h, &pk, empty.as_slice()).unwrap().len() == 0);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let mut h = Sha256::new();
+        let msgs: Vec<&[u8]> = vec!["a".as_bytes(), "bb".as_bytes(),
+                                    "ccc".as_bytes()];
+        let mut pks = Vec::new();
+        let mut sigs = Vec::new();
+        for msg in msgs.iter() {
+            let (pk, sk) = GroupElem::keypair();
+            sigs.push(sign::sign(&mut h, &sk, &pk, *msg));
+            pks.push(pk);
+        }
+
+        let agg = sign::aggregate(&mut h, pks.as_slice(), msgs.as_slice(),
+                                  sigs.as_slice()).unwrap();
+        assert!(sign::verify_aggregate(&mut h, pks.as_slice(),
+                                       msgs.as_slice(), &agg));
+
+        let b = agg.to_bytes();
+        assert!(b.len() == 52 * 4);
+        assert!(AggregateSignature::from_bytes(b.as_slice()).unwrap() == agg);
+        assert!(AggregateSignature::from_bytes(b.slice_to(200)) ==
+                Err(InvalidLength(156, 200)));
+        assert!(AggregateSignature::from_bytes(b.slice_to(52)) ==
+                Err(InvalidLength(104, 52)));
+
+        // Wrong message, swapped keys, missing signature.
+        let other: Vec<&[u8]> = vec!["a".as_bytes(), "bb".as_bytes(),
+                                     "cc".as_bytes()];
+        assert!(!sign::verify_aggregate(&mut h, pks.as_slice(),
+                                        other.as_slice(), &agg));
+        pks.as_mut_slice().swap(0, 1);
+        assert!(!sign::verify_aggregate(&mut h, pks.as_slice(),
+                                        msgs.as_slice(), &agg));
+        assert!(!sign::verify_aggregate(&mut h, pks.slice_to(2),
+                                        msgs.slice_to(2), &agg));
+
+        // A single signature aggregates to a verifiable aggregate too.
+        pks.as_mut_slice().swap(0, 1);
+        let one = sign::aggregate(&mut h, pks.slice_to(1), msgs.slice_to(1),
+                                  sigs.slice_to(1)).unwrap();
+        assert!(sign::verify_aggregate(&mut h, pks.slice_to(1),
+                                       msgs.slice_to(1), &one));
+
+        // Empty or mismatched inputs are rejected.
+        assert!(sign::aggregate(&mut h, [], [], []).err() == Some(Malformed));
+        assert!(sign::aggregate(&mut h, pks.slice_to(2), msgs.as_slice(),
+                                sigs.as_slice()).err() == Some(Malformed));
+        let empty = AggregateSignature {
+            rs: Vec::new(),
+            s: one.s().clone()
+        };
+        assert!(!sign::verify_aggregate(&mut h, [], [], &empty));
+    }
+
+    #[test]
+    fn test_possession() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let ctx = "registration".as_bytes();
+
+        let proof = sign::prove_possession(&mut h, &sk, &pk, ctx);
+        assert!(sign::verify_possession(&mut h, &pk, &proof, ctx));
+        assert!(!sign::verify_possession(&mut h, &pk, &proof,
+                                         "other".as_bytes()));
+        let (pk2, _) = GroupElem::keypair();
+        assert!(!sign::verify_possession(&mut h, &pk2, &proof, ctx));
+
+        // Proofs and signatures are not interchangeable.
+        let sig = sign::sign(&mut h, &sk, &pk, ctx);
+        assert!(sig != proof);
+        assert!(!sign::verify_possession(&mut h, &pk, &sig, ctx));
+        assert!(!sign::verify(&mut h, &pk, ctx, &proof));
+    }
+}
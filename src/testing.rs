@@ -0,0 +1,116 @@
+//! Algebraic laws checks
+//!
+//! Functions asserting that field elements, scalars and group elements
+//! satisfy the laws expected from their algebraic structures, on random
+//! values drawn from a caller-supplied RNG. They fail on the first law
+//! found not to hold and are intended to be called from tests, including
+//! downstream integration tests.
+use std::rand::Rng;
+
+use bytes::B416;
+use ed::GroupElem;
+use fe::FieldElem;
+use sc::ScalarElem;
+
+
+fn rand_fe<R: Rng>(rng: &mut R) -> FieldElem {
+    let b: B416 = rng.gen();
+    FieldElem::unpack(&b)
+}
+
+/// Check `iterations` times the field laws on random field elements:
+/// associativity, commutativity and distributivity of `+` and `*`, neutral
+/// elements, opposites and inverses.
+pub fn check_field_laws<R: Rng>(rng: &mut R, iterations: uint) {
+    let zero = FieldElem::zero();
+    let one = FieldElem::one();
+
+    for _ in range(0u, iterations) {
+        let a = rand_fe(rng);
+        let b = rand_fe(rng);
+        let c = rand_fe(rng);
+
+        assert!((a + b) + c == a + (b + c));
+        assert!((a * b) * c == a * (b * c));
+        assert!(a + b == b + a);
+        assert!(a * b == b * a);
+        assert!(a * (b + c) == a * b + a * c);
+        assert!(a + zero == a && a * one == a);
+        assert!(a + -a == zero);
+        assert!(a - b == a + -b);
+        assert!(a.square() == a * a);
+        if a != zero {
+            assert!(a * a.inv() == one);
+        }
+    }
+}
+
+/// Check `iterations` times the ring laws `mod L` on random scalars:
+/// associativity, commutativity and distributivity of `+` and `*`, neutral
+/// elements, opposites and inverses.
+pub fn check_scalar_laws<R: Rng>(rng: &mut R, iterations: uint) {
+    let zero = ScalarElem::zero();
+    let one: ScalarElem = FromPrimitive::from_u64(1).unwrap();
+
+    for _ in range(0u, iterations) {
+        let a: ScalarElem = rng.gen();
+        let b: ScalarElem = rng.gen();
+        let c: ScalarElem = rng.gen();
+
+        assert!((a + b) + c == a + (b + c));
+        assert!((a * b) * c == a * (b * c));
+        assert!(a + b == b + a);
+        assert!(a * b == b * a);
+        assert!(a * (b + c) == a * b + a * c);
+        assert!(a + zero == a && a * one == a);
+        assert!(a + -a == zero);
+        assert!(a - b == a + -b);
+        assert!(ScalarElem::unpack(&a.pack()).unwrap() == a);
+        if a != zero {
+            assert!(a * a.inv() == one);
+        }
+    }
+}
+
+/// Check `iterations` times the group laws on random group elements:
+/// associativity and commutativity of `+`, neutral element, opposites,
+/// compatibility of scalar multiplication with scalars addition and
+/// multiplication, and round-trips through the packed representations.
+pub fn check_group_laws<R: Rng>(rng: &mut R, iterations: uint) {
+    let neutral = GroupElem::neutral();
+
+    for _ in range(0u, iterations) {
+        let p: GroupElem = rng.gen();
+        let q: GroupElem = rng.gen();
+        let r: GroupElem = rng.gen();
+        let m: ScalarElem = rng.gen();
+        let n: ScalarElem = rng.gen();
+
+        assert!((p + q) + r == p + (q + r));
+        assert!(p + q == q + p);
+        assert!(p + neutral == p);
+        assert!(p + -p == neutral);
+        assert!(p - q == p + -q);
+        assert!(p * (m + n) == p * m + p * n);
+        assert!(p * (m * n) == (p * n) * m);
+        assert!((p + q) * m == p * m + q * m);
+        assert!(GroupElem::unpack(&p.pack()).unwrap() == p);
+        assert!(p.to_mont() == (-p).to_mont());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use testing;
+    use utils;
+
+
+    #[test]
+    fn test_laws() {
+        let mut rng = utils::urandom_rng();
+        testing::check_field_laws(&mut rng, 8);
+        testing::check_scalar_laws(&mut rng, 8);
+        testing::check_group_laws(&mut rng, 2);
+    }
+}
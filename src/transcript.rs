@@ -0,0 +1,205 @@
+//! Protocol transcripts
+//!
+//! Merlin-like transcripts for Fiat-Shamir transforms built over a
+//! caller-supplied `Digest`. A transcript absorbs labeled messages and
+//! squeezes challenges bound to everything absorbed so far, protocols
+//! then don't have to define their own serialization of what is hashed.
+//! Transcripts may be forked into labeled sub-transcripts for composing
+//! protocols, and may rekey a RNG with the protocol witnesses.
+use std::rand::{Rand, Rng};
+
+use bytes::{B416, B832, Bytes};
+use digest::Digest;
+use kdf;
+use kdf::KdfRng;
+use sbuf::{DefaultAllocator, SBuf};
+use sc::ScalarElem;
+use utils;
+use vectors;
+
+
+static PROTOCOL_LABEL: &'static [u8] = b"curve41417 transcript";
+static APPEND_TAG: &'static [u8] = b"append";
+static CHALLENGE_TAG: &'static [u8] = b"challenge";
+static FORK_TAG: &'static [u8] = b"fork";
+static RNG_TAG: &'static [u8] = b"rng";
+
+
+/// Transcript of a protocol
+///
+/// Its state is a chaining key `K` of `digest.output_bytes()` bytes,
+/// initially `kdf::extract(salt = "", ikm = "curve41417 transcript" ||
+/// label)`. Every operation `op` with inputs `x1, x2, ...` updates it to
+/// `HMAC(K, op || len(x1) || x1 || len(x2) || x2 || ...)` where lengths
+/// are encoded as 8 little-endian bytes, hence the framing of the inputs
+/// is unambiguous.
+pub struct Transcript<D> {
+    digest: D,
+    key: SBuf<DefaultAllocator, u8>
+}
+
+impl<D: Digest + Clone> Transcript<D> {
+    /// Return a new transcript for the protocol identified by `label`.
+    pub fn new(mut digest: D, label: &[u8]) -> Transcript<D> {
+        let mut ikm: SBuf<DefaultAllocator, u8> =
+            SBuf::new_zero(PROTOCOL_LABEL.len() + label.len());
+        for (d, s) in ikm.mut_iter().zip(PROTOCOL_LABEL.iter()
+                                         .chain(label.iter())) {
+            *d = *s;
+        }
+        let mut key: SBuf<DefaultAllocator, u8> =
+            SBuf::new_zero(digest.output_bytes());
+        kdf::extract(&mut digest, [], ikm.as_slice(), key.as_mut_slice());
+
+        Transcript {
+            digest: digest,
+            key: key
+        }
+    }
+
+    // Update the chaining key with op and the framed parts.
+    fn update(&mut self, op: &[u8], parts: &[&[u8]]) {
+        let mut lens = Vec::with_capacity(parts.len());
+        for p in parts.iter() {
+            lens.push(utils::le64(p.len()));
+        }
+        let mut data: Vec<&[u8]> = vec![op];
+        for (l, p) in lens.iter().zip(parts.iter()) {
+            data.push(l.as_slice());
+            data.push(*p);
+        }
+
+        let prev: SBuf<DefaultAllocator, u8> =
+            SBuf::from_slice(self.key.as_slice());
+        kdf::hmac(&mut self.digest, prev.as_slice(), data.as_slice(),
+                  self.key.as_mut_slice());
+    }
+
+    /// Absorb the message `msg` labeled with `label`.
+    pub fn append_message(&mut self, label: &[u8], msg: &[u8]) {
+        self.update(APPEND_TAG, [label, msg]);
+    }
+
+    /// Fill `out` with challenge bytes labeled with `label`, bound to all
+    /// the messages absorbed so far. The challenge is also absorbed, two
+    /// successive challenges are then different.
+    pub fn challenge_bytes(&mut self, label: &[u8], out: &mut [u8]) {
+        let len = utils::le64(out.len());
+        let mut info = Vec::with_capacity(CHALLENGE_TAG.len() + 8 +
+                                          label.len());
+        info.push_all(CHALLENGE_TAG);
+        info.push_all(len.as_slice());
+        info.push_all(label);
+        kdf::expand(&mut self.digest, self.key.as_slice(), info.as_slice(),
+                    out);
+        vectors::record("transcript::challenge", "challenge",
+                        out.as_slice());
+        self.update(CHALLENGE_TAG, [label, out.as_slice()]);
+    }
+
+    /// Return a challenge scalar labeled with `label`: 104 challenge bytes
+    /// reduced `mod L`, see `challenge_bytes()`.
+    pub fn challenge_scalar(&mut self, label: &[u8]) -> ScalarElem {
+        let mut b: B832 = Bytes::new_zero();
+        self.challenge_bytes(label, b.as_mut_bytes());
+        ScalarElem::unpack(&b).unwrap()
+    }
+
+    /// Return a sub-transcript labeled with `label`. It starts from the
+    /// current state of this transcript, which also absorbs the fork.
+    /// Further operations on either transcript don't affect the other one.
+    pub fn fork(&mut self, label: &[u8]) -> Transcript<D> {
+        self.update(FORK_TAG, [label]);
+        let mut child = Transcript {
+            digest: self.digest.clone(),
+            key: SBuf::from_slice(self.key.as_slice())
+        };
+        child.update(FORK_TAG, [label, b"child"]);
+        child
+    }
+
+    /// Return a generator rekeyed with the current state of this
+    /// transcript, the secret `witness` (e.g. a secret key) and 52 bytes
+    /// generated from `rng`. Its output is bound to the transcript and
+    /// remains unpredictable if either `witness` or `rng` is, this is the
+    /// recommended way to generate secret nonces in protocols. The
+    /// transcript itself is not modified.
+    pub fn build_rng<R: Rng>(&self, witness: &[u8], rng: &mut R)
+                             -> KdfRng<D> {
+        let rnd: B416 = Rand::rand(rng);
+        let wlen = utils::le64(witness.len());
+        let parts = [self.key.as_slice(), wlen.as_slice(), witness,
+                     rnd.as_bytes()];
+        let len = parts.iter().fold(0u, |l, p| l + p.len());
+        let mut seed: SBuf<DefaultAllocator, u8> = SBuf::new_zero(len);
+        let mut pos = 0u;
+        for p in parts.iter() {
+            for b in p.iter() {
+                *seed.get_mut(pos) = *b;
+                pos += 1;
+            }
+        }
+        KdfRng::new(self.digest.clone(), seed.as_slice(), RNG_TAG)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::rand::Rng;
+
+    use digest::sha256::Sha256;
+    use kdf::KdfRng;
+    use transcript::Transcript;
+
+
+    fn challenge(t: &mut Transcript<Sha256>) -> Vec<u8> {
+        let mut c = Vec::from_elem(40, 0u8);
+        t.challenge_bytes(b"c", c.as_mut_slice());
+        c
+    }
+
+    #[test]
+    fn test_transcript() {
+        let mut t1 = Transcript::new(Sha256::new(), b"test");
+        let mut t2 = Transcript::new(Sha256::new(), b"test");
+        t1.append_message(b"m", b"message");
+        t2.append_message(b"m", b"message");
+        let c1 = challenge(&mut t1);
+        assert!(c1 == challenge(&mut t2));
+        assert!(c1 != challenge(&mut t1));
+        assert!(t1.challenge_scalar(b"s") == t2.challenge_scalar(b"s"));
+
+        // Framing: moving bytes between label and message changes the
+        // challenge, so does the protocol label.
+        let mut t3 = Transcript::new(Sha256::new(), b"test");
+        let mut t4 = Transcript::new(Sha256::new(), b"test");
+        let mut t5 = Transcript::new(Sha256::new(), b"other");
+        t3.append_message(b"m", b"message");
+        t4.append_message(b"mm", b"essage");
+        t5.append_message(b"m", b"message");
+        let c3 = challenge(&mut t3);
+        assert!(c3 != challenge(&mut t4));
+        assert!(c3 != challenge(&mut t5));
+    }
+
+    #[test]
+    fn test_fork_and_rng() {
+        let mut t1 = Transcript::new(Sha256::new(), b"test");
+        let mut t2 = Transcript::new(Sha256::new(), b"test");
+        let mut f1 = t1.fork(b"sub");
+        let mut f2 = t2.fork(b"sub");
+        assert!(challenge(&mut f1) == challenge(&mut f2));
+        f1.append_message(b"m", b"message");
+        assert!(challenge(&mut t1) == challenge(&mut t2));
+        assert!(challenge(&mut f1) != challenge(&mut f2));
+
+        let mut rng1 = KdfRng::new(Sha256::new(), b"seed", b"");
+        let mut rng2 = KdfRng::new(Sha256::new(), b"seed", b"");
+        let mut r1 = t1.build_rng(b"witness", &mut rng1);
+        let mut r2 = t2.build_rng(b"witness", &mut rng2);
+        assert!(r1.next_u64() == r2.next_u64());
+        let mut r3 = t2.build_rng(b"other", &mut rng2);
+        assert!(r1.next_u64() != r3.next_u64());
+    }
+}
@@ -3,9 +3,11 @@ use std::intrinsics;
 use std::mem;
 use std::num;
 use std::ptr;
-use std::rand::os::OsRng;
 use std::slice::MutableVector;
 
+use bytes::{B416, Bytes};
+use os_random::OsRandom;
+
 
 // Zero-out memory buffer.
 fn zero_memory<T>(b: &mut [T]) {
@@ -27,6 +29,12 @@ pub fn copy_slice_memory<T>(dst: &mut[T], src: &[T], count: uint) {
     }
 }
 
+// Return 1 iff x == y; 0 otherwise. Always executes the same operations.
+pub fn uint_eq(x: uint, y: uint) -> i64 {
+    let d = (x ^ y) as i64;
+    (((d | -d) >> 63) & 1) ^ 1
+}
+
 // Return 1 iff x == y; 0 otherwise.
 fn byte_eq(x: u8, y: u8) -> u8 {
     let mut z: u8 = !(x ^ y);
@@ -38,8 +46,14 @@ fn byte_eq(x: u8, y: u8) -> u8 {
 
 // Return true iff x == y; false otherwise.
 pub fn bytes_eq<T>(x: &[T], y: &[T]) -> bool {
+    bytes_ct_eq(x, y) == 1
+}
+
+// Return 1 iff x == y; 0 otherwise. Always executes the same operations
+// for inputs of the same length.
+pub fn bytes_ct_eq<T>(x: &[T], y: &[T]) -> u8 {
     if x.len() != y.len() {
-        return false;
+        return 0;
     }
 
     let size = x.len() * mem::size_of::<T>();
@@ -53,11 +67,7 @@ pub fn bytes_eq<T>(x: &[T], y: &[T]) -> bool {
         }
     }
 
-    // Would prefer to return the result of byte_eq() instead of making
-    // this last comparison, but this function is called from contexts where
-    // boolean values are explicitly expected and this comparison seems
-    // the only way to convert to a bool in rust.
-    byte_eq(d, 0) == 1
+    byte_eq(d, 0)
 }
 
 // x and y are swapped iff cond is 1, there are left unchanged iff cond is 0.
@@ -76,9 +86,43 @@ pub fn bytes_cswap<T: Signed + Primitive + Int>(cond: T,
     }
 }
 
-// Instanciate a secure RNG (based on urandom).
-pub fn urandom_rng() -> OsRng {
-    OsRng::new().unwrap()
+// Encode n as 8 little-endian bytes, typically for framing lengths.
+pub fn le64(n: uint) -> [u8, ..8] {
+    let mut r = [0u8, ..8];
+    for i in range(0u, 8) {
+        r[i] = (n as u64 >> (8 * i)) as u8;
+    }
+    r
+}
+
+// Return 52 little-endian bytes from the little-endian u64 limbs, None
+// if limbs encode a value >= 2^416.
+pub fn b416_from_u64_limbs(limbs: &[u64]) -> Option<B416> {
+    let mut b: B416 = Bytes::new_zero();
+    for i in range(0u, 8 * limbs.len()) {
+        let v = (limbs[i / 8] >> (8 * (i % 8))) as u8;
+        if i < 52 {
+            *b.get_mut(i) = v;
+        } else if v != 0 {
+            return None;
+        }
+    }
+    Some(b)
+}
+
+// Return the 7 little-endian u64 limbs of 52 little-endian bytes.
+pub fn b416_to_u64_limbs(b: &B416) -> [u64, ..7] {
+    let mut limbs = [0u64, ..7];
+    for i in range(0u, 52) {
+        limbs[i / 8] |= *b.get(i) as u64 << (8 * (i % 8));
+    }
+    limbs
+}
+
+// Instanciate a secure RNG (based on getrandom or urandom, see
+// os_random).
+pub fn urandom_rng() -> OsRandom {
+    OsRandom::new().unwrap()
 }
 
 
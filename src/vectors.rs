@@ -0,0 +1,176 @@
+//! Intermediate values dump for test vectors
+//!
+//! Signatures, Diffie-Hellman functions and transcripts report their
+//! intermediate values (nonces, challenges, shared secrets before key
+//! derivation) to a `Recorder`, so that test vectors covering every step
+//! may be generated and compared with other implementations. Only
+//! compiled in with the `vectors` feature, see `set_recorder()`. Recorded
+//! values include secrets, this feature must never be enabled in
+//! production builds.
+use serialize::hex::ToHex;
+use std::comm;
+use std::fmt::{Show, Formatter, Result};
+
+
+/// Intermediate value reported by an operation.
+#[deriving(Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Operation reporting the value, e.g. `"sign::sign"`.
+    pub site: &'static str,
+    /// Name of the value in its operation, e.g. `"nonce"`.
+    pub name: &'static str,
+    /// Encoding of the value, scalars and points are packed.
+    pub value: Vec<u8>
+}
+
+impl Show for Record {
+    /// Format as `site name = hex`, e.g. `sign::sign nonce = 0a1b...`.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{} {} = {}", self.site, self.name,
+               self.value.as_slice().to_hex())
+    }
+}
+
+/// Receiver of intermediate values.
+pub trait Recorder {
+    /// Report the value `value` named `name` computed at `site`.
+    fn record(&mut self, site: &'static str, name: &'static str,
+              value: &[u8]);
+}
+
+#[cfg(feature = "vectors")]
+mod imphooks {
+    use super::Recorder;
+
+
+    task_hook!(RECORDER, Recorder, set_recorder, take_recorder)
+
+    pub fn record(site: &'static str, name: &'static str, value: &[u8]) {
+        match RECORDER.get() {
+            Some(r) => r.borrow_mut().record(site, name, value),
+            None => ()
+        }
+    }
+}
+
+#[cfg(not(feature = "vectors"))]
+mod imphooks {
+    use super::Recorder;
+
+
+    no_task_hook!(Recorder, set_recorder, take_recorder)
+
+    #[inline(always)]
+    pub fn record(_: &'static str, _: &'static str, _: &[u8]) {
+    }
+}
+
+/// Install `recorder` for the current task, replacing the previous one.
+/// Return `false` if the crate was not built with the `vectors` feature,
+/// the recorder is then never called.
+pub fn set_recorder(recorder: Box<Recorder>) -> bool {
+    self::imphooks::set_recorder(recorder)
+}
+
+/// Remove and return the recorder of the current task.
+pub fn take_recorder() -> Option<Box<Recorder>> {
+    self::imphooks::take_recorder()
+}
+
+// Forward records to a channel.
+struct Sink {
+    tx: comm::Sender<Record>
+}
+
+impl Recorder for Sink {
+    fn record(&mut self, site: &'static str, name: &'static str,
+              value: &[u8]) {
+        self.tx.send(Record {
+            site: site,
+            name: name,
+            value: Vec::from_slice(value)
+        });
+    }
+}
+
+/// Run `f` and return its result along with the values it reported, in
+/// the order they were computed. The recorder of the current task is
+/// removed. The returned records are always empty if the crate was not
+/// built with the `vectors` feature.
+pub fn capture<T>(f: || -> T) -> (T, Vec<Record>) {
+    let (tx, rx) = comm::channel();
+    set_recorder(box Sink { tx: tx });
+    let r = f();
+    drop(take_recorder());
+    (r, rx.iter().collect())
+}
+
+// Return true if values are reported, callers check it before computing
+// the encodings of values they report.
+#[doc(hidden)]
+#[inline(always)]
+pub fn enabled() -> bool {
+    cfg!(feature = "vectors")
+}
+
+// Report value named name computed at site.
+#[doc(hidden)]
+#[inline(always)]
+pub fn record(site: &'static str, name: &'static str, value: &[u8]) {
+    self::imphooks::record(site, name, value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use digest::sha256::Sha256;
+    use ed::GroupElem;
+    use mont;
+    use mont::Contributory;
+    use sign;
+    use vectors;
+
+
+    #[test]
+    fn test_capture() {
+        let mut h = Sha256::new();
+        let (pk, sk) = GroupElem::keypair();
+        let (sig, records) = vectors::capture(|| {
+            sign::sign(&mut h, &sk, &pk, b"message")
+        });
+
+        if !cfg!(feature = "vectors") {
+            assert!(records.len() == 0);
+            return;
+        }
+        let names: Vec<&'static str> = records.iter().map(|r| r.name)
+            .collect();
+        assert!(names == vec!["nonce", "R", "challenge", "s"]);
+        assert!(records.iter().all(|r| r.site == "sign::sign"));
+        assert!(records.get(1).value.as_slice() == sig.r().get_ref()
+                .as_bytes());
+        assert!(records.get(3).value.as_slice() == sig.s().get_ref()
+                .as_bytes());
+
+        let (_, records) = vectors::capture(|| {
+            sign::prove_possession(&mut h, &sk, &pk, b"context")
+        });
+        assert!(records.len() == 4);
+        assert!(records.iter().all(|r| r.site == "sign::prove_possession"));
+        let (_, records) = vectors::capture(|| {
+            sign::sign_xeddsa(&mut h, &sk, b"message")
+        });
+        assert!(records.iter().all(|r| r.site == "sign::sign_xeddsa"));
+
+        let (pk1, _) = mont::keypair();
+        let (_, sk2) = mont::keypair();
+        let (s, records) = vectors::capture(|| {
+            mont::shared_secret(&sk2, &pk1, Contributory).unwrap()
+        });
+        assert!(records.len() == 1);
+        assert!(records.get(0).value.as_slice() == s.get_ref().as_bytes());
+
+        // Nothing is reported once the recorder is removed.
+        assert!(vectors::take_recorder().is_none());
+    }
+}